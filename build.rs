@@ -25,14 +25,13 @@ fn create_code_fragment_directory() -> PathBuf {
 
 fn generate_code_fragment<P: AsRef<Path>>(path: P) -> String {
     let model = obj::load_file(path).unwrap();
-    let fragment = obj::to_rust_code(&model);
 
-    fragment
+    obj::to_rust_code(&model)
 }
 
 fn write_code_fragment<P: AsRef<Path>>(code_path: P, fragment_name: &str, fragment: &str) -> io::Result<()> {
     let path = code_path.as_ref().join(fragment_name);
-    let mut file = File::create(&path)?;
+    let mut file = File::create(path)?;
     file.write_all(fragment.as_bytes())?;
     file.sync_all()
 }
@@ -68,7 +67,7 @@ fn main() {
     write_code_fragment(&code_path, "ground_plane.obj.in", &ground_plane).unwrap();
 
     let dest = env::var("OUT_DIR").unwrap();
-    let mut file = File::create(&Path::new(&dest).join("gl_bindings.rs")).unwrap();
+    let mut file = File::create(Path::new(&dest).join("gl_bindings.rs")).unwrap();
 
     register_gl_api(&mut file);
 }
@@ -1,10 +1,37 @@
 #version 330 core
 
 in vec2 tex_coord;
+in vec3 position_eye;
+uniform mat4 view_mat;
 uniform sampler2D tex;
+uniform int render_mode;
+uniform vec2 depth_range;
 out vec4 frag_color;
 
+// The render modes, as given by `RenderMode::shader_mode`.
+const int RENDER_SHADED = 0;
+const int RENDER_NORMALS = 1;
+const int RENDER_TEX_COORDS = 2;
+const int RENDER_DEPTH = 3;
+const int RENDER_LIGHTING = 4;
+
 
 void main() {
-    frag_color = texture (tex, tex_coord);
+    if (render_mode == RENDER_NORMALS) {
+        // The ground plane has no normals of its own, so take the normal of the
+        // surface from how the position changes across the pixel.
+        vec3 norm_eye = normalize (cross (dFdx (position_eye), dFdy (position_eye)));
+        vec3 norm_wor = normalize (vec3 (inverse (view_mat) * vec4 (norm_eye, 0.0)));
+        frag_color = vec4 (0.5 * norm_wor + 0.5, 1.0);
+    } else if (render_mode == RENDER_TEX_COORDS) {
+        frag_color = vec4 (fract (tex_coord), 0.0, 1.0);
+    } else if (render_mode == RENDER_DEPTH) {
+        float depth = (-position_eye.z - depth_range.x) / (depth_range.y - depth_range.x);
+        frag_color = vec4 (vec3 (clamp (depth, 0.0, 1.0)), 1.0);
+    } else if (render_mode == RENDER_LIGHTING) {
+        // The ground plane is not lit, so it shows up as plain white.
+        frag_color = vec4 (1.0);
+    } else {
+        frag_color = texture (tex, tex_coord);
+    }
 }
@@ -0,0 +1,12 @@
+#version 330 core
+
+in vec3 v_pos;
+in vec4 v_color;
+uniform mat4 proj_mat, view_mat;
+out vec4 color;
+
+
+void main() {
+    color = v_color;
+    gl_Position = proj_mat * view_mat * vec4 (v_pos, 1.0);
+}
@@ -0,0 +1,9 @@
+#version 330 core
+
+in vec3 v_pos;
+uniform mat4 proj_mat, view_mat, model_mat;
+
+
+void main() {
+    gl_Position = proj_mat * view_mat * model_mat * vec4 (v_pos, 1.0);
+}
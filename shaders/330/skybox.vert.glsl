@@ -0,0 +1,14 @@
+#version 330 core
+
+in vec3 v_pos;
+uniform mat4 proj_mat, view_mat;
+out vec3 tex_coord;
+
+
+void main() {
+    tex_coord = v_pos;
+    // Strip the translation from the view matrix so the skybox follows the camera.
+    vec4 position = proj_mat * mat4 (mat3 (view_mat)) * vec4 (v_pos, 1.0);
+    // Force the depth to the far plane so the skybox is drawn behind the scene.
+    gl_Position = position.xyww;
+}
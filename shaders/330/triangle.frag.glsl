@@ -7,6 +7,11 @@ in vec3 normal_eye;
 uniform mat4 view_mat;
 uniform mat4 model_mat;
 uniform sampler2D tex;
+uniform samplerCube env_map;
+uniform float env_reflectivity;
+uniform int render_mode;
+uniform vec2 depth_range;
+uniform vec4 base_color;
 
 uniform PointLight {
     vec3 La;
@@ -14,31 +19,83 @@ uniform PointLight {
     vec3 Ls;
     float specular_exponent;
     vec3 pos_wor;
+    float intensity;
+    // Zero when the light reaches everywhere.
+    float range;
+    vec3 spot_dir_wor;
+    // Both minus one when the light shines in every direction.
+    float spot_inner_cos;
+    float spot_outer_cos;
 } light;
 
 out vec4 frag_color;
 
+// The render modes, as given by `RenderMode::shader_mode`.
+const int RENDER_SHADED = 0;
+const int RENDER_NORMALS = 1;
+const int RENDER_TEX_COORDS = 2;
+const int RENDER_DEPTH = 3;
+const int RENDER_LIGHTING = 4;
+
 
 void main() {
-    vec3 K = vec3 (texture (tex, tex_coord));
+    // The back faces of double sided surfaces are lit from their own side.
+    vec3 norm_eye = normalize (normal_eye);
+    if (!gl_FrontFacing) {
+        norm_eye = -norm_eye;
+    }
+    if (render_mode == RENDER_NORMALS) {
+        vec3 norm_wor = normalize (vec3 (inverse (view_mat) * vec4 (norm_eye, 0.0)));
+        frag_color = vec4 (0.5 * norm_wor + 0.5, 1.0);
+        return;
+    } else if (render_mode == RENDER_TEX_COORDS) {
+        frag_color = vec4 (fract (tex_coord), 0.0, 1.0);
+        return;
+    } else if (render_mode == RENDER_DEPTH) {
+        float depth = (-position_eye.z - depth_range.x) / (depth_range.y - depth_range.x);
+        frag_color = vec4 (vec3 (clamp (depth, 0.0, 1.0)), 1.0);
+        return;
+    }
+
+    // Lighting only shades a white surface.
+    vec3 K = vec3 (1.0);
+    if (render_mode != RENDER_LIGHTING) {
+        K = vec3 (base_color) * vec3 (texture (tex, tex_coord));
+    }
     vec3 Ka = K;
     vec3 Kd = K;
     vec3 Ks = K;
 
-    vec3 norm_eye = normalize (normal_eye);
     vec3 Ia = light.La * Ka;
 
     vec3 light_pos_eye = vec3 (view_mat * vec4 (light.pos_wor, 1.0));
-    vec3 dist_to_light_eye = - position_eye - light_pos_eye;
+    vec3 dist_to_light_eye = light_pos_eye - position_eye;
     vec3 dir_to_light_eye = normalize (dist_to_light_eye);
     float dot_diffuse = max (dot (dir_to_light_eye, norm_eye), 0.0);
-    vec3 Id = light.Ld * Kd * dot_diffuse;
+    // The light fades out smoothly towards the end of its range, and towards the
+    // edge of its cone for a spot light.
+    float falloff = light.intensity;
+    if (light.range > 0.0) {
+        float ratio = length (dist_to_light_eye) / light.range;
+        falloff *= clamp (1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    }
+    if (light.spot_outer_cos > -1.0) {
+        vec3 spot_dir_eye = normalize (vec3 (view_mat * vec4 (light.spot_dir_wor, 0.0)));
+        float cos_angle = dot (-dir_to_light_eye, spot_dir_eye);
+        falloff *= smoothstep (light.spot_outer_cos, light.spot_inner_cos, cos_angle);
+    }
+    vec3 Id = falloff * light.Ld * Kd * dot_diffuse;
 
     vec3 surface_to_viewer_eye = normalize (-position_eye);
     vec3 half_vec_eye = normalize (surface_to_viewer_eye + dir_to_light_eye);
     float dot_specular = max (dot (half_vec_eye, norm_eye), 0.0);
     float specular_factor = pow (dot_specular, light.specular_exponent);
-    vec3 Is = light.Ls * Ks * specular_factor;
+    // Reflect the environment cube map into the specular term. The cube map is
+    // sampled in world space, so rotate the reflected ray out of eye space.
+    vec3 reflected_eye = reflect (-surface_to_viewer_eye, norm_eye);
+    vec3 reflected_wor = vec3 (inverse (view_mat) * vec4 (reflected_eye, 0.0));
+    vec3 Ienv = env_reflectivity * vec3 (texture (env_map, reflected_wor));
+    vec3 Is = Ks * (falloff * light.Ls * specular_factor + Ienv);
 
     frag_color = vec4(Ia + Id + Is, 1.0);
 }
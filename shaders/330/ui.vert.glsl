@@ -0,0 +1,15 @@
+#version 330 core
+
+in vec2 v_pos;
+in vec2 v_tex;
+in vec4 v_color;
+uniform mat4 proj_mat;
+out vec2 tex_coord;
+out vec4 color;
+
+
+void main() {
+    tex_coord = v_tex;
+    color = v_color;
+    gl_Position = proj_mat * vec4 (v_pos, 0.0, 1.0);
+}
@@ -0,0 +1,9 @@
+#version 420 core
+
+in layout (location = 0) vec3 v_pos;
+uniform mat4 proj_mat, view_mat, model_mat;
+
+
+void main() {
+    gl_Position = proj_mat * view_mat * model_mat * vec4 (v_pos, 1.0);
+}
@@ -0,0 +1,10 @@
+#version 420 core
+
+in vec3 tex_coord;
+uniform samplerCube cube_tex;
+out vec4 frag_color;
+
+
+void main() {
+    frag_color = texture (cube_tex, tex_coord);
+}
@@ -16,6 +16,9 @@ out vec3 normal_eye;
 void main() {
     position_eye = vec3 (view_mat * model_mat * vec4 (v_pos, 1.0));
     tex_coord = v_tex;
-    normal_eye = vec3 (view_mat * model_mat * vec4 (v_norm, 0.0));
+    // A scale that differs between axes skews the normals, so they are carried by
+    // the inverse transpose of the model view matrix instead.
+    mat3 normal_mat = transpose (inverse (mat3 (view_mat * model_mat)));
+    normal_eye = normal_mat * v_norm;
     gl_Position = proj_mat * vec4 (position_eye, 1.0);
 }
@@ -0,0 +1,11 @@
+#version 420 core
+
+in vec2 tex_coord;
+in vec4 color;
+uniform sampler2D font_atlas;
+out vec4 frag_color;
+
+
+void main() {
+    frag_color = vec4 (color.rgb, color.a * texture (font_atlas, tex_coord).r);
+}
@@ -0,0 +1,15 @@
+#version 420 core
+
+in layout (location = 0) vec2 v_pos;
+in layout (location = 1) vec2 v_tex;
+in layout (location = 2) vec4 v_color;
+uniform mat4 proj_mat;
+out vec2 tex_coord;
+out vec4 color;
+
+
+void main() {
+    tex_coord = v_tex;
+    color = v_color;
+    gl_Position = proj_mat * vec4 (v_pos, 0.0, 1.0);
+}
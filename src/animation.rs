@@ -0,0 +1,442 @@
+//! Keyframe animation. An animation is a set of keyframe tracks for the translation,
+//! rotation and scale of a scene node. Playing an animation samples each of its
+//! tracks and writes the result into the transform of the node it is attached to.
+use crate::component::Transform;
+use crate::scene::{
+    SceneGraph,
+    SceneNodeID,
+};
+use cglinalg::{
+    Quaternion,
+    Vector3,
+};
+
+
+/// How a track fills in the values between two keyframes.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Interpolation {
+    /// Hold the value of the earlier keyframe until the next keyframe is reached.
+    Step,
+    /// Blend between the keyframes at a constant rate.
+    Linear,
+    /// Blend between the keyframes along a cubic curve that eases in and out of
+    /// each keyframe.
+    CubicEase,
+}
+
+/// What an animation does once it runs past its last keyframe.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum PlaybackMode {
+    /// Start over from the first keyframe.
+    Loop,
+    /// Play backwards to the first keyframe, then forwards again.
+    PingPong,
+    /// Stop and hold the last keyframe.
+    OneShot,
+}
+
+/// A value that animation tracks know how to blend.
+pub trait Interpolate: Copy {
+    /// Blend from `self` at `amount == 0` to `other` at `amount == 1`.
+    fn interpolate(&self, other: &Self, amount: f32) -> Self;
+}
+
+impl Interpolate for f32 {
+    #[inline]
+    fn interpolate(&self, other: &f32, amount: f32) -> f32 {
+        self + (other - self) * amount
+    }
+}
+
+impl Interpolate for Vector3<f32> {
+    #[inline]
+    fn interpolate(&self, other: &Vector3<f32>, amount: f32) -> Vector3<f32> {
+        (*self) * (1.0 - amount) + (*other) * amount
+    }
+}
+
+impl Interpolate for Quaternion<f32> {
+    #[inline]
+    fn interpolate(&self, other: &Quaternion<f32>, amount: f32) -> Quaternion<f32> {
+        self.slerp(other, amount)
+    }
+}
+
+impl Interpolate for Transform {
+    #[inline]
+    fn interpolate(&self, other: &Transform, amount: f32) -> Transform {
+        Transform::new(
+            self.translation.interpolate(&other.translation, amount),
+            self.rotation.interpolate(&other.rotation, amount),
+            self.scale.interpolate(&other.scale, amount)
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T,
+}
+
+impl<T> Keyframe<T> {
+    #[inline]
+    pub fn new(time: f32, value: T) -> Keyframe<T> {
+        Keyframe {
+            time: time,
+            value: value,
+        }
+    }
+}
+
+/// A sequence of keyframes for one component of a transform, sorted by time.
+#[derive(Clone, Debug)]
+pub struct Track<T> {
+    keyframes: Vec<Keyframe<T>>,
+    interpolation: Interpolation,
+}
+
+impl<T: Interpolate> Track<T> {
+    /// Construct a track. The keyframes are sorted by time, so they can be given
+    /// in any order. A track needs at least one keyframe, and every keyframe time
+    /// has to be a finite number.
+    pub fn new(keyframes: Vec<Keyframe<T>>, interpolation: Interpolation) -> Result<Track<T>, String> {
+        if keyframes.is_empty() {
+            return Err(String::from("An animation track needs at least one keyframe."));
+        }
+        if let Some(keyframe) = keyframes.iter().find(|keyframe| !keyframe.time.is_finite()) {
+            return Err(format!("Invalid keyframe time: {}", keyframe.time));
+        }
+        let mut keyframes = keyframes;
+        // Finite times always compare.
+        keyframes.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+
+        Ok(Track {
+            keyframes: keyframes,
+            interpolation: interpolation,
+        })
+    }
+
+    /// The time of the last keyframe.
+    #[inline]
+    pub fn duration(&self) -> f32 {
+        self.keyframes[self.keyframes.len() - 1].time
+    }
+
+    /// Sample the track at a point in time. Times before the first keyframe or after
+    /// the last one hold the value of that keyframe.
+    pub fn sample(&self, time: f32) -> T {
+        let first = &self.keyframes[0];
+        let last = &self.keyframes[self.keyframes.len() - 1];
+        if time <= first.time {
+            return first.value;
+        }
+        if time >= last.time {
+            return last.value;
+        }
+
+        // The index of the first keyframe after `time`. It exists and is not the
+        // first keyframe because of the bounds checks above.
+        let next = self.keyframes.iter().position(|keyframe| keyframe.time > time).unwrap();
+        let k0 = &self.keyframes[next - 1];
+        let k1 = &self.keyframes[next];
+        let amount = (time - k0.time) / (k1.time - k0.time);
+        match self.interpolation {
+            Interpolation::Step => k0.value,
+            Interpolation::Linear => k0.value.interpolate(&k1.value, amount),
+            Interpolation::CubicEase => {
+                let eased = amount * amount * (3.0 - 2.0 * amount);
+                k0.value.interpolate(&k1.value, eased)
+            }
+        }
+    }
+}
+
+/// A set of keyframe tracks that animate a transform. Any component of the
+/// transform without a track is left alone.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub translation: Option<Track<Vector3<f32>>>,
+    pub rotation: Option<Track<Quaternion<f32>>>,
+    pub scale: Option<Track<Vector3<f32>>>,
+    pub mode: PlaybackMode,
+}
+
+impl Animation {
+    pub fn new(mode: PlaybackMode) -> Animation {
+        Animation {
+            translation: None,
+            rotation: None,
+            scale: None,
+            mode: mode,
+        }
+    }
+
+    pub fn with_translation(mut self, track: Track<Vector3<f32>>) -> Animation {
+        self.translation = Some(track);
+        self
+    }
+
+    pub fn with_rotation(mut self, track: Track<Quaternion<f32>>) -> Animation {
+        self.rotation = Some(track);
+        self
+    }
+
+    pub fn with_scale(mut self, track: Track<Vector3<f32>>) -> Animation {
+        self.scale = Some(track);
+        self
+    }
+
+    /// The length of the animation, which is the length of its longest track.
+    pub fn duration(&self) -> f32 {
+        let translation = self.translation.as_ref().map_or(0.0, |track| track.duration());
+        let rotation = self.rotation.as_ref().map_or(0.0, |track| track.duration());
+        let scale = self.scale.as_ref().map_or(0.0, |track| track.duration());
+
+        f32::max(translation, f32::max(rotation, scale))
+    }
+
+    /// Map the time since the animation started onto a time within the animation,
+    /// according to the playback mode.
+    pub fn local_time(&self, time: f32) -> f32 {
+        let duration = self.duration();
+        if duration <= 0.0 {
+            return 0.0;
+        }
+
+        match self.mode {
+            PlaybackMode::Loop => time % duration,
+            PlaybackMode::PingPong => {
+                let time = time % (2.0 * duration);
+                if time > duration {
+                    2.0 * duration - time
+                } else {
+                    time
+                }
+            }
+            PlaybackMode::OneShot => f32::min(time, duration),
+        }
+    }
+
+    /// Sample the animation at a time since it started, and write the result into
+    /// a transform.
+    pub fn apply(&self, time: f32, transform: &mut Transform) {
+        let time = self.local_time(time);
+        if let Some(ref track) = self.translation {
+            transform.translation = track.sample(time);
+        }
+        if let Some(ref track) = self.rotation {
+            transform.rotation = track.sample(time);
+        }
+        if let Some(ref track) = self.scale {
+            transform.scale = track.sample(time);
+        }
+    }
+
+    /// Determine whether the animation has nothing left to play at a time since it
+    /// started. Looping and ping-pong animations never finish.
+    #[inline]
+    pub fn is_finished(&self, time: f32) -> bool {
+        self.mode == PlaybackMode::OneShot && time >= self.duration()
+    }
+}
+
+/// An animation attached to a scene node, together with how far it has played.
+struct AnimationPlayer {
+    node: SceneNodeID,
+    animation: Animation,
+    time: f32,
+}
+
+/// Plays animations on the nodes of a scene graph.
+pub struct Animator {
+    players: Vec<AnimationPlayer>,
+}
+
+impl Animator {
+    pub fn new() -> Animator {
+        Animator {
+            players: vec![],
+        }
+    }
+
+    /// Start playing an animation on a scene node.
+    pub fn attach(&mut self, node: SceneNodeID, animation: Animation) {
+        self.attach_from(node, animation, 0.0);
+    }
+
+    /// Start playing an animation on a scene node as if it had already been playing
+    /// for `time` seconds.
+    pub fn attach_from(&mut self, node: SceneNodeID, animation: Animation, time: f32) {
+        self.players.push(AnimationPlayer {
+            node: node,
+            animation: animation,
+            time: time,
+        });
+    }
+
+    /// Advance every animation by `elapsed_seconds` and write the results into the
+    /// transforms of the scene graph.
+    pub fn update(&mut self, scene: &mut SceneGraph, elapsed_seconds: f32) {
+        for player in self.players.iter_mut() {
+            if player.animation.is_finished(player.time) {
+                continue;
+            }
+            player.time += elapsed_seconds;
+            let mut transform = scene.transform(player.node);
+            player.animation.apply(player.time, &mut transform);
+            scene.set_transform(player.node, transform);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn track(interpolation: Interpolation) -> Track<f32> {
+        Track::new(vec![
+            Keyframe::new(0.0, 0.0),
+            Keyframe::new(1.0, 10.0),
+            Keyframe::new(3.0, 30.0),
+        ], interpolation).unwrap()
+    }
+
+    fn animation(mode: PlaybackMode) -> Animation {
+        let scale = Track::new(vec![
+            Keyframe::new(0.0, Vector3::new(1.0, 1.0, 1.0)),
+            Keyframe::new(4.0, Vector3::new(2.0, 3.0, 4.0)),
+        ], Interpolation::Linear).unwrap();
+
+        Animation::new(mode).with_scale(scale)
+    }
+
+    fn approx_eq(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn test_track_needs_a_keyframe() {
+        assert!(Track::<f32>::new(vec![], Interpolation::Linear).is_err());
+    }
+
+    #[test]
+    fn test_track_rejects_non_finite_times() {
+        let nan = vec![Keyframe::new(0.0, 0.0), Keyframe::new(f32::NAN, 1.0)];
+        let infinity = vec![Keyframe::new(f32::INFINITY, 0.0)];
+
+        assert!(Track::new(nan, Interpolation::Linear).is_err());
+        assert!(Track::new(infinity, Interpolation::Step).is_err());
+    }
+
+    #[test]
+    fn test_track_sorts_its_keyframes() {
+        let track = Track::new(vec![
+            Keyframe::new(2.0, 20.0),
+            Keyframe::new(0.0, 0.0),
+        ], Interpolation::Linear).unwrap();
+
+        assert_eq!(track.duration(), 2.0);
+        assert!(approx_eq(track.sample(1.0), 10.0));
+    }
+
+    #[test]
+    fn test_sample_at_keyframes() {
+        for &interpolation in [Interpolation::Step, Interpolation::Linear, Interpolation::CubicEase].iter() {
+            let track = track(interpolation);
+
+            assert_eq!(track.sample(0.0), 0.0);
+            assert_eq!(track.sample(1.0), 10.0);
+            assert_eq!(track.sample(3.0), 30.0);
+        }
+    }
+
+    #[test]
+    fn test_sample_outside_the_track_holds_the_end_keyframes() {
+        let track = track(Interpolation::Linear);
+
+        assert_eq!(track.sample(-1.0), 0.0);
+        assert_eq!(track.sample(5.0), 30.0);
+    }
+
+    #[test]
+    fn test_step_holds_the_earlier_keyframe() {
+        let track = track(Interpolation::Step);
+
+        assert_eq!(track.sample(0.5), 0.0);
+        assert_eq!(track.sample(2.9), 10.0);
+    }
+
+    #[test]
+    fn test_linear_blends_at_a_constant_rate() {
+        let track = track(Interpolation::Linear);
+
+        assert!(approx_eq(track.sample(0.25), 2.5));
+        assert!(approx_eq(track.sample(2.0), 20.0));
+    }
+
+    #[test]
+    fn test_cubic_ease_eases_in_and_out() {
+        let track = track(Interpolation::CubicEase);
+
+        assert!(approx_eq(track.sample(0.25), 1.5625));
+        assert!(approx_eq(track.sample(0.5), 5.0));
+        assert!(approx_eq(track.sample(0.75), 8.4375));
+    }
+
+    #[test]
+    fn test_duration_is_the_longest_track() {
+        let translation = Track::new(vec![
+            Keyframe::new(0.0, Vector3::new(0.0, 0.0, 0.0)),
+            Keyframe::new(6.0, Vector3::new(1.0, 0.0, 0.0)),
+        ], Interpolation::Linear).unwrap();
+        let animation = animation(PlaybackMode::Loop).with_translation(translation);
+
+        assert_eq!(animation.duration(), 6.0);
+    }
+
+    #[test]
+    fn test_loop_starts_over_past_the_end() {
+        let animation = animation(PlaybackMode::Loop);
+
+        assert!(approx_eq(animation.local_time(1.0), 1.0));
+        assert!(approx_eq(animation.local_time(4.0), 0.0));
+        assert!(approx_eq(animation.local_time(9.5), 1.5));
+        assert!(!animation.is_finished(100.0));
+    }
+
+    #[test]
+    fn test_ping_pong_plays_backwards_past_the_end() {
+        let animation = animation(PlaybackMode::PingPong);
+
+        assert!(approx_eq(animation.local_time(3.0), 3.0));
+        assert!(approx_eq(animation.local_time(5.0), 3.0));
+        assert!(approx_eq(animation.local_time(8.0), 0.0));
+        assert!(approx_eq(animation.local_time(9.0), 1.0));
+        assert!(!animation.is_finished(100.0));
+    }
+
+    #[test]
+    fn test_one_shot_holds_the_end() {
+        let animation = animation(PlaybackMode::OneShot);
+        let mut transform = Transform::identity();
+        animation.apply(10.0, &mut transform);
+
+        assert!(approx_eq(animation.local_time(10.0), 4.0));
+        assert_eq!(transform.scale, Vector3::new(2.0, 3.0, 4.0));
+        assert!(!animation.is_finished(3.0));
+        assert!(animation.is_finished(4.0));
+    }
+
+    #[test]
+    fn test_attach_from_starts_partway_through() {
+        let mut scene = SceneGraph::new();
+        let node = scene.add_node("node", None, None, Transform::identity());
+        let mut animator = Animator::new();
+        animator.attach_from(node, animation(PlaybackMode::PingPong), 5.0);
+        animator.update(&mut scene, 1.0);
+
+        assert_eq!(scene.transform(node).scale, Vector3::new(1.5, 2.0, 2.5));
+    }
+}
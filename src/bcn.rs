@@ -0,0 +1,708 @@
+//! Software decoders for the BCn block compressed texture formats. These are the
+//! fallback path for drivers that cannot sample a compressed format directly; the
+//! decoders expand each block into RGBA8 texels so the image can be uploaded as an
+//! ordinary texture. BC6H holds high dynamic range colours that RGBA8 cannot represent,
+//! so it has no software decoder: BC6H textures only load on drivers that support BPTC.
+
+
+/// The block compression formats the demo knows how to load. Every format packs
+/// a 4x4 block of texels into a fixed number of bytes.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum BlockFormat {
+    /// RGB with optional one bit alpha in 8 bytes. Also known as DXT1.
+    Bc1,
+    /// RGB with explicit four bit alpha in 16 bytes. Also known as DXT3.
+    Bc2,
+    /// RGB with interpolated alpha in 16 bytes. Also known as DXT5.
+    Bc3,
+    /// A single unsigned channel in 8 bytes.
+    Bc4,
+    /// Two unsigned channels in 16 bytes.
+    Bc5,
+    /// Unsigned half precision floating point RGB in 16 bytes. The GPU has to decode
+    /// this format itself.
+    Bc6h,
+    /// High quality RGBA in 16 bytes.
+    Bc7,
+}
+
+impl BlockFormat {
+    /// The number of bytes in one 4x4 block.
+    #[inline]
+    pub fn block_size(self) -> usize {
+        match self {
+            BlockFormat::Bc1 | BlockFormat::Bc4 => 8,
+            _ => 16,
+        }
+    }
+
+    /// The number of bytes in an image of the given dimensions. The size saturates
+    /// at `usize::MAX` rather than overflowing, so no buffer is ever large enough for
+    /// an image with absurd dimensions.
+    #[inline]
+    pub fn image_size(self, width: usize, height: usize) -> usize {
+        let blocks_wide = width.div_ceil(4);
+        let blocks_high = height.div_ceil(4);
+
+        blocks_wide.saturating_mul(blocks_high).saturating_mul(self.block_size())
+    }
+
+    /// Determine whether `decode` can expand this format into RGBA8 texels.
+    #[inline]
+    pub fn has_software_decoder(self) -> bool {
+        self != BlockFormat::Bc6h
+    }
+}
+
+/// Decode a block compressed image into tightly packed RGBA8 texels, row by row.
+/// Single and dual channel formats fill the missing colour channels with zero, the way
+/// OpenGL samples them. This returns `None` if the format has no software decoder (see
+/// `has_software_decoder`) or the data is too short for the image.
+pub fn decode(format: BlockFormat, width: usize, height: usize, data: &[u8]) -> Option<Vec<u8>> {
+    if data.len() < format.image_size(width, height) {
+        return None;
+    }
+
+    let decode_block: fn(&[u8], &mut [[u8; 4]; 16]) = match format {
+        BlockFormat::Bc1 => decode_bc1_block,
+        BlockFormat::Bc2 => decode_bc2_block,
+        BlockFormat::Bc3 => decode_bc3_block,
+        BlockFormat::Bc4 => decode_bc4_block,
+        BlockFormat::Bc5 => decode_bc5_block,
+        BlockFormat::Bc7 => decode_bc7_block,
+        BlockFormat::Bc6h => return None,
+    };
+
+    let block_size = format.block_size();
+    let blocks_wide = width.div_ceil(4);
+    let blocks_high = height.div_ceil(4);
+    let mut pixels = vec![0_u8; 4 * width * height];
+    let mut texels = [[0_u8; 4]; 16];
+    for block_y in 0..blocks_high {
+        for block_x in 0..blocks_wide {
+            let offset = (block_y * blocks_wide + block_x) * block_size;
+            decode_block(&data[offset..(offset + block_size)], &mut texels);
+
+            // Blocks on the right and bottom edges hang over the image when its
+            // dimensions are not multiples of four.
+            for row in 0..4 {
+                let y = 4 * block_y + row;
+                if y >= height {
+                    break;
+                }
+                for col in 0..4 {
+                    let x = 4 * block_x + col;
+                    if x >= width {
+                        break;
+                    }
+                    let dst = 4 * (y * width + x);
+                    pixels[dst..(dst + 4)].copy_from_slice(&texels[4 * row + col]);
+                }
+            }
+        }
+    }
+
+    Some(pixels)
+}
+
+#[inline]
+fn read_u16(data: &[u8]) -> u16 {
+    (data[0] as u16) | ((data[1] as u16) << 8)
+}
+
+#[inline]
+fn read_u32(data: &[u8]) -> u32 {
+    (read_u16(data) as u32) | ((read_u16(&data[2..]) as u32) << 16)
+}
+
+/// Expand a 5:6:5 packed colour to 8 bits per channel.
+#[inline]
+fn unpack_rgb565(color: u16) -> [u32; 3] {
+    let r = ((color >> 11) & 0x1F) as u32;
+    let g = ((color >> 5) & 0x3F) as u32;
+    let b = (color & 0x1F) as u32;
+
+    [(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2)]
+}
+
+/// Decode the colour half of a BC1, BC2 or BC3 block. BC1 blocks switch to three
+/// colours plus transparent black when the first endpoint is not greater than the
+/// second; the colour blocks of BC2 and BC3 always use four colours.
+fn decode_color_block(block: &[u8], texels: &mut [[u8; 4]; 16], allow_punch_through: bool) {
+    let color0 = read_u16(&block[0..]);
+    let color1 = read_u16(&block[2..]);
+    let indices = read_u32(&block[4..]);
+    let c0 = unpack_rgb565(color0);
+    let c1 = unpack_rgb565(color1);
+
+    let mut palette = [[0_u32; 4]; 4];
+    palette[0] = [c0[0], c0[1], c0[2], 255];
+    palette[1] = [c1[0], c1[1], c1[2], 255];
+    if color0 > color1 || !allow_punch_through {
+        for c in 0..3 {
+            palette[2][c] = (2 * c0[c] + c1[c]) / 3;
+            palette[3][c] = (c0[c] + 2 * c1[c]) / 3;
+        }
+        palette[2][3] = 255;
+        palette[3][3] = 255;
+    } else {
+        for c in 0..3 {
+            palette[2][c] = (c0[c] + c1[c]) / 2;
+        }
+        palette[2][3] = 255;
+        palette[3] = [0, 0, 0, 0];
+    }
+
+    for (i, texel) in texels.iter_mut().enumerate() {
+        let index = ((indices >> (2 * i)) & 0x03) as usize;
+        for c in 0..4 {
+            texel[c] = palette[index][c] as u8;
+        }
+    }
+}
+
+/// Decode an interpolated single channel block, as used for the alpha of BC3 and the
+/// channels of BC4 and BC5.
+fn decode_channel_block(block: &[u8]) -> [u8; 16] {
+    let a0 = block[0] as u32;
+    let a1 = block[1] as u32;
+    let mut palette = [0_u32; 8];
+    palette[0] = a0;
+    palette[1] = a1;
+    if a0 > a1 {
+        for (i, value) in palette.iter_mut().enumerate().skip(2) {
+            *value = ((8 - i as u32) * a0 + (i as u32 - 1) * a1) / 7;
+        }
+    } else {
+        for (i, value) in palette.iter_mut().enumerate().take(6).skip(2) {
+            *value = ((6 - i as u32) * a0 + (i as u32 - 1) * a1) / 5;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    let mut indices: u64 = 0;
+    for i in 0..6 {
+        indices |= (block[2 + i] as u64) << (8 * i);
+    }
+
+    let mut values = [0_u8; 16];
+    for i in 0..16 {
+        values[i] = palette[((indices >> (3 * i)) & 0x07) as usize] as u8;
+    }
+
+    values
+}
+
+fn decode_bc1_block(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decode_color_block(block, texels, true);
+}
+
+fn decode_bc2_block(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decode_color_block(&block[8..], texels, false);
+    for i in 0..16 {
+        let alpha = (block[i / 2] >> (4 * (i % 2))) & 0x0F;
+        texels[i][3] = alpha * 17;
+    }
+}
+
+fn decode_bc3_block(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    decode_color_block(&block[8..], texels, false);
+    let alpha = decode_channel_block(&block[0..8]);
+    for i in 0..16 {
+        texels[i][3] = alpha[i];
+    }
+}
+
+fn decode_bc4_block(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    let red = decode_channel_block(&block[0..8]);
+    for i in 0..16 {
+        texels[i] = [red[i], 0, 0, 255];
+    }
+}
+
+fn decode_bc5_block(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    let red = decode_channel_block(&block[0..8]);
+    let green = decode_channel_block(&block[8..16]);
+    for i in 0..16 {
+        texels[i] = [red[i], green[i], 0, 255];
+    }
+}
+
+/// Reads a 128 bit block one field at a time, starting from the least significant bit.
+struct BitReader {
+    bits: u128,
+    position: u32,
+}
+
+impl BitReader {
+    fn new(block: &[u8]) -> BitReader {
+        let mut bits: u128 = 0;
+        for (i, &byte) in block.iter().take(16).enumerate() {
+            bits |= (byte as u128) << (8 * i);
+        }
+
+        BitReader { bits: bits, position: 0 }
+    }
+
+    #[inline]
+    fn read(&mut self, count: u32) -> u32 {
+        if count == 0 {
+            return 0;
+        }
+        let value = (self.bits >> self.position) & ((1 << count) - 1);
+        self.position += count;
+
+        value as u32
+    }
+}
+
+/// The layout of one of the eight BC7 block modes.
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    endpoint_pbits: bool,
+    shared_pbits: bool,
+    index_bits: u32,
+    index2_bits: u32,
+}
+
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0, endpoint_pbits: true,  shared_pbits: false, index_bits: 3, index2_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0, endpoint_pbits: false, shared_pbits: true,  index_bits: 3, index2_bits: 0 },
+    Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0, endpoint_pbits: false, shared_pbits: false, index_bits: 2, index2_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0, endpoint_pbits: true,  shared_pbits: false, index_bits: 2, index2_bits: 0 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6, endpoint_pbits: false, shared_pbits: false, index_bits: 2, index2_bits: 3 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8, endpoint_pbits: false, shared_pbits: false, index_bits: 2, index2_bits: 2 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7, endpoint_pbits: true,  shared_pbits: false, index_bits: 4, index2_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5, endpoint_pbits: true,  shared_pbits: false, index_bits: 2, index2_bits: 0 },
+];
+
+/// The two subset partitions. Bit `i` is set when texel `i` belongs to the second subset.
+const BC7_PARTITIONS_2: [u16; 64] = [
+    0xCCCC, 0x8888, 0xEEEE, 0xECC8, 0xC880, 0xFEEC, 0xFEC8, 0xEC80,
+    0xC800, 0xFFEC, 0xFE80, 0xE800, 0xFFE8, 0xFF00, 0xFFF0, 0xF000,
+    0xF710, 0x008E, 0x7100, 0x08CE, 0x008C, 0x7310, 0x3100, 0x8CCE,
+    0x088C, 0x3110, 0x6666, 0x366C, 0x17E8, 0x0FF0, 0x718E, 0x399C,
+    0xAAAA, 0xF0F0, 0x5A5A, 0x33CC, 0x3C3C, 0x55AA, 0x9696, 0xA55A,
+    0x73CE, 0x13C8, 0x324C, 0x3BDC, 0x6996, 0xC33C, 0x9966, 0x0660,
+    0x0272, 0x04E4, 0x4E40, 0x2720, 0xC936, 0x936C, 0x39C6, 0x639C,
+    0x9336, 0x9CC6, 0x817E, 0xE718, 0xCCF0, 0x0FCC, 0x7744, 0xEE22,
+];
+
+/// The three subset partitions, giving the subset of each texel.
+const BC7_PARTITIONS_3: [[u8; 16]; 64] = [
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 1, 2, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 2, 0, 0, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 1, 0, 1, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1],
+    [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2],
+    [0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2, 0, 1, 1, 2],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0, 2, 2, 2, 0],
+    [0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 1, 2, 1, 1, 2, 2],
+    [0, 1, 1, 1, 0, 0, 1, 1, 2, 0, 0, 1, 2, 2, 0, 0],
+    [0, 0, 0, 0, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 2, 2, 0, 0, 2, 2, 1, 1, 1, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2, 0, 2, 2, 2],
+    [0, 0, 0, 1, 0, 0, 0, 1, 2, 2, 2, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2],
+    [0, 0, 0, 0, 1, 1, 0, 0, 2, 2, 1, 0, 2, 2, 1, 0],
+    [0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0],
+    [0, 0, 1, 2, 0, 0, 1, 2, 1, 1, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1, 0, 1, 1, 0],
+    [0, 0, 0, 0, 0, 1, 1, 0, 1, 2, 2, 1, 1, 2, 2, 1],
+    [0, 0, 2, 2, 1, 1, 0, 2, 1, 1, 0, 2, 0, 0, 2, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 0, 0, 2, 2, 2, 2, 2],
+    [0, 0, 1, 1, 0, 1, 2, 2, 0, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 0, 0, 2, 0, 0, 0, 2, 2, 1, 1, 2, 2, 2, 1],
+    [0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 2, 2, 2],
+    [0, 2, 2, 2, 0, 0, 2, 2, 0, 0, 1, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 0, 0, 1, 2, 0, 0, 2, 2, 0, 2, 2, 2],
+    [0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0, 0, 1, 2, 0],
+    [0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0],
+    [0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0, 1, 2, 0],
+    [0, 1, 2, 0, 2, 0, 1, 2, 1, 2, 0, 1, 0, 1, 2, 0],
+    [0, 0, 1, 1, 2, 2, 0, 0, 1, 1, 2, 2, 0, 0, 1, 1],
+    [0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 0, 0, 0, 0, 1, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 0, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2, 1, 1, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1],
+    [0, 2, 2, 0, 1, 2, 2, 1, 0, 2, 2, 0, 1, 2, 2, 1],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 0, 1, 0, 1],
+    [0, 0, 0, 0, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1, 2, 1],
+    [0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1, 2, 2, 2, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 2, 2, 2, 0, 1, 1, 1],
+    [0, 0, 0, 2, 1, 1, 1, 2, 0, 0, 0, 2, 1, 1, 1, 2],
+    [0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 2, 2, 2, 0, 1, 1, 1, 0, 1, 1, 1, 0, 2, 2, 2],
+    [0, 0, 0, 2, 1, 1, 1, 2, 1, 1, 1, 2, 0, 0, 0, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2, 2, 1, 1, 2],
+    [0, 1, 1, 0, 0, 1, 1, 0, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 2, 2],
+    [0, 0, 2, 2, 1, 1, 2, 2, 1, 1, 2, 2, 0, 0, 2, 2],
+    [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 1, 1, 2],
+    [0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1],
+    [0, 2, 2, 2, 1, 2, 2, 2, 0, 2, 2, 2, 1, 2, 2, 2],
+    [0, 1, 0, 1, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2],
+    [0, 1, 1, 1, 2, 0, 1, 1, 2, 2, 0, 1, 2, 2, 2, 0],
+];
+
+/// The anchor texel of the second subset in the two subset partitions.
+const BC7_ANCHORS_2: [u8; 64] = [
+    15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,
+    15,  2,  8,  2,  2,  8,  8, 15,  2,  8,  2,  2,  8,  8,  2,  2,
+    15, 15,  6,  8,  2,  8, 15, 15,  2,  8,  2,  2,  2, 15, 15,  6,
+     6,  2,  6,  8, 15, 15,  2,  2, 15, 15, 15, 15, 15,  2,  2, 15,
+];
+
+/// The anchor texel of the second subset in the three subset partitions.
+const BC7_ANCHORS_3_SECOND: [u8; 64] = [
+     3,  3, 15, 15,  8,  3, 15, 15,  8,  8,  6,  6,  6,  5,  3,  3,
+     3,  3,  8, 15,  3,  3,  6, 10,  5,  8,  8,  6,  8,  5, 15, 15,
+     8, 15,  3,  5,  6, 10,  8, 15, 15,  3, 15,  5, 15, 15, 15, 15,
+     3, 15,  5,  5,  5,  8,  5, 10,  5, 10,  8, 13, 15, 12,  3,  3,
+];
+
+/// The anchor texel of the third subset in the three subset partitions.
+const BC7_ANCHORS_3_THIRD: [u8; 64] = [
+    15,  8,  8,  3, 15, 15,  3,  8, 15, 15, 15, 15, 15, 15, 15,  8,
+    15,  8, 15,  3, 15,  8, 15,  8,  3, 15,  6, 10, 15, 15, 10,  8,
+    15,  3, 15, 10, 10,  8,  9, 10,  6, 15,  8, 15,  3,  6,  6,  8,
+    15,  3, 15, 15, 15, 15, 15, 15, 15, 15, 15, 15,  3, 15, 15,  8,
+];
+
+const BC7_WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const BC7_WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+const BC7_WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+#[inline]
+fn bc7_interpolate(e0: u32, e1: u32, index: u32, index_bits: u32) -> u8 {
+    let weight = match index_bits {
+        2 => BC7_WEIGHTS_2[index as usize],
+        3 => BC7_WEIGHTS_3[index as usize],
+        _ => BC7_WEIGHTS_4[index as usize],
+    };
+
+    (((64 - weight) * e0 + weight * e1 + 32) >> 6) as u8
+}
+
+/// Expand an endpoint channel of `bits` bits to 8 bits by replicating its high bits.
+#[inline]
+fn bc7_unquantize(value: u32, bits: u32) -> u32 {
+    let value = value << (8 - bits);
+    value | (value >> bits)
+}
+
+fn decode_bc7_block(block: &[u8], texels: &mut [[u8; 4]; 16]) {
+    let mut reader = BitReader::new(block);
+    let mut mode_index = 0;
+    while mode_index < 8 && reader.read(1) == 0 {
+        mode_index += 1;
+    }
+    if mode_index == 8 {
+        // Reserved mode. The specification decodes these blocks to transparent black.
+        *texels = [[0; 4]; 16];
+        return;
+    }
+
+    let mode = &BC7_MODES[mode_index];
+    let partition = reader.read(mode.partition_bits) as usize;
+    let rotation = reader.read(mode.rotation_bits);
+    let index_selection = reader.read(mode.index_selection_bits);
+
+    // Endpoints are stored channel by channel: every red value, then every green,
+    // then every blue, then every alpha.
+    let num_endpoints = 2 * mode.subsets;
+    let mut endpoints = [[0_u32; 4]; 6];
+    for c in 0..3 {
+        for endpoint in endpoints.iter_mut().take(num_endpoints) {
+            endpoint[c] = reader.read(mode.color_bits);
+        }
+    }
+    for endpoint in endpoints.iter_mut().take(num_endpoints) {
+        endpoint[3] = reader.read(mode.alpha_bits);
+    }
+
+    let mut color_bits = mode.color_bits;
+    let mut alpha_bits = mode.alpha_bits;
+    if mode.endpoint_pbits || mode.shared_pbits {
+        let mut pbits = [0_u32; 6];
+        if mode.endpoint_pbits {
+            for pbit in pbits.iter_mut().take(num_endpoints) {
+                *pbit = reader.read(1);
+            }
+        } else {
+            for s in 0..mode.subsets {
+                let pbit = reader.read(1);
+                pbits[2 * s] = pbit;
+                pbits[2 * s + 1] = pbit;
+            }
+        }
+        for (endpoint, &pbit) in endpoints.iter_mut().zip(pbits.iter()).take(num_endpoints) {
+            for value in endpoint.iter_mut() {
+                *value = (*value << 1) | pbit;
+            }
+        }
+        color_bits += 1;
+        if alpha_bits > 0 {
+            alpha_bits += 1;
+        }
+    }
+
+    for endpoint in endpoints.iter_mut().take(num_endpoints) {
+        for value in endpoint.iter_mut().take(3) {
+            *value = bc7_unquantize(*value, color_bits);
+        }
+        endpoint[3] = if alpha_bits > 0 {
+            bc7_unquantize(endpoint[3], alpha_bits)
+        } else {
+            255
+        };
+    }
+
+    let mut subsets = [0_usize; 16];
+    let mut anchors = [0_usize; 3];
+    match mode.subsets {
+        2 => {
+            for (i, subset) in subsets.iter_mut().enumerate() {
+                *subset = ((BC7_PARTITIONS_2[partition] >> i) & 1) as usize;
+            }
+            anchors[1] = BC7_ANCHORS_2[partition] as usize;
+        }
+        3 => {
+            for i in 0..16 {
+                subsets[i] = BC7_PARTITIONS_3[partition][i] as usize;
+            }
+            anchors[1] = BC7_ANCHORS_3_SECOND[partition] as usize;
+            anchors[2] = BC7_ANCHORS_3_THIRD[partition] as usize;
+        }
+        _ => {}
+    }
+
+    // The anchor texel of each subset drops the high bit of its index, which the
+    // encoder guarantees is zero.
+    let mut indices = [0_u32; 16];
+    for i in 0..16 {
+        let is_anchor = anchors[subsets[i]] == i;
+        indices[i] = reader.read(mode.index_bits - is_anchor as u32);
+    }
+    let mut indices2 = [0_u32; 16];
+    if mode.index2_bits > 0 {
+        for (i, index) in indices2.iter_mut().enumerate() {
+            *index = reader.read(mode.index2_bits - (i == 0) as u32);
+        }
+    }
+
+    for i in 0..16 {
+        let e0 = &endpoints[2 * subsets[i]];
+        let e1 = &endpoints[2 * subsets[i] + 1];
+        let (color_index, color_index_bits, alpha_index, alpha_index_bits) = if mode.index2_bits == 0 {
+            (indices[i], mode.index_bits, indices[i], mode.index_bits)
+        } else if index_selection == 0 {
+            (indices[i], mode.index_bits, indices2[i], mode.index2_bits)
+        } else {
+            (indices2[i], mode.index2_bits, indices[i], mode.index_bits)
+        };
+
+        let mut texel = [0_u8; 4];
+        for c in 0..3 {
+            texel[c] = bc7_interpolate(e0[c], e1[c], color_index, color_index_bits);
+        }
+        texel[3] = bc7_interpolate(e0[3], e1[3], alpha_index, alpha_index_bits);
+
+        match rotation {
+            1 => texel.swap(0, 3),
+            2 => texel.swap(1, 3),
+            3 => texel.swap(2, 3),
+            _ => {}
+        }
+        texels[i] = texel;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// Packs the fields of a 128 bit block, starting from the least significant bit.
+    struct BitWriter {
+        bits: u128,
+        position: u32,
+    }
+
+    impl BitWriter {
+        fn new() -> BitWriter {
+            BitWriter { bits: 0, position: 0 }
+        }
+
+        fn write(&mut self, count: u32, value: u32) {
+            self.bits |= (value as u128) << self.position;
+            self.position += count;
+        }
+
+        fn block(&self) -> Vec<u8> {
+            (0..16).map(|i| (self.bits >> (8 * i)) as u8).collect()
+        }
+    }
+
+    fn decode_block(format: BlockFormat, block: &[u8]) -> Vec<[u8; 4]> {
+        let pixels = decode(format, 4, 4, block).unwrap();
+
+        pixels.chunks(4).map(|texel| [texel[0], texel[1], texel[2], texel[3]]).collect()
+    }
+
+    #[test]
+    fn test_image_size_rounds_up_to_whole_blocks() {
+        assert_eq!(BlockFormat::Bc1.image_size(4, 4), 8);
+        assert_eq!(BlockFormat::Bc1.image_size(5, 1), 16);
+        assert_eq!(BlockFormat::Bc7.image_size(8, 6), 64);
+    }
+
+    #[test]
+    fn test_image_size_saturates_instead_of_overflowing() {
+        assert_eq!(BlockFormat::Bc7.image_size(usize::MAX / 2, usize::MAX / 2), usize::MAX);
+    }
+
+    #[test]
+    fn test_bc1_four_colour_block() {
+        // Red and blue endpoints, with texels 0 to 3 using indices 0 to 3.
+        let block = [0x00, 0xF8, 0x1F, 0x00, 0xE4, 0x00, 0x00, 0x00];
+        let texels = decode_block(BlockFormat::Bc1, &block);
+
+        assert_eq!(texels[0], [255, 0, 0, 255]);
+        assert_eq!(texels[1], [0, 0, 255, 255]);
+        assert_eq!(texels[2], [170, 0, 85, 255]);
+        assert_eq!(texels[3], [85, 0, 170, 255]);
+        assert_eq!(texels[15], [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_bc1_punch_through_block() {
+        // The same endpoints the other way around switch the block to three colours
+        // plus transparent black.
+        let block = [0x1F, 0x00, 0x00, 0xF8, 0xE4, 0x00, 0x00, 0x00];
+        let texels = decode_block(BlockFormat::Bc1, &block);
+
+        assert_eq!(texels[0], [0, 0, 255, 255]);
+        assert_eq!(texels[1], [255, 0, 0, 255]);
+        assert_eq!(texels[2], [127, 0, 127, 255]);
+        assert_eq!(texels[3], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_bc2_explicit_alpha() {
+        let block = [
+            0xF0, 0x87, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let texels = decode_block(BlockFormat::Bc2, &block);
+
+        assert_eq!(texels[0], [255, 255, 255, 0]);
+        assert_eq!(texels[1], [255, 255, 255, 255]);
+        assert_eq!(texels[2], [255, 255, 255, 119]);
+        assert_eq!(texels[3], [255, 255, 255, 136]);
+    }
+
+    #[test]
+    fn test_bc3_interpolated_alpha() {
+        // Alpha endpoints 255 and 0, with texels 0 to 3 using indices 0, 1, 2 and 7.
+        let block = [
+            0xFF, 0x00, 0x88, 0x0E, 0x00, 0x00, 0x00, 0x00,
+            0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let texels = decode_block(BlockFormat::Bc3, &block);
+
+        assert_eq!(texels[0], [255, 255, 255, 255]);
+        assert_eq!(texels[1], [255, 255, 255, 0]);
+        assert_eq!(texels[2], [255, 255, 255, 218]);
+        assert_eq!(texels[3], [255, 255, 255, 36]);
+    }
+
+    #[test]
+    fn test_bc4_six_value_block() {
+        // Endpoints 10 and 60, with texels 0 to 2 using indices 2, 6 and 7, which
+        // hold an interpolated value, zero and one.
+        let block = [10, 60, 0xF2, 0x01, 0x00, 0x00, 0x00, 0x00];
+        let texels = decode_block(BlockFormat::Bc4, &block);
+
+        assert_eq!(texels[0], [20, 0, 0, 255]);
+        assert_eq!(texels[1], [0, 0, 0, 255]);
+        assert_eq!(texels[2], [255, 0, 0, 255]);
+        assert_eq!(texels[3], [10, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_bc5_two_channels() {
+        let block = [
+            200, 200, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            100, 0, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let texels = decode_block(BlockFormat::Bc5, &block);
+
+        assert_eq!(texels[0], [200, 0, 0, 255]);
+        assert_eq!(texels[1], [200, 100, 0, 255]);
+    }
+
+    #[test]
+    fn test_bc7_mode_6_block() {
+        let mut writer = BitWriter::new();
+        writer.write(7, 0x40);
+        for &value in [0x7F, 0x00, 0x00, 0x7F, 0x40, 0x40, 0x7F, 0x7F].iter() {
+            writer.write(7, value);
+        }
+        writer.write(1, 1);
+        writer.write(1, 1);
+        // Texel 0 is the anchor and has a three bit index.
+        writer.write(3, 0);
+        writer.write(4, 15);
+        writer.write(4, 8);
+        let texels = decode_block(BlockFormat::Bc7, &writer.block());
+
+        assert_eq!(texels[0], [255, 1, 129, 255]);
+        assert_eq!(texels[1], [1, 255, 129, 255]);
+        assert_eq!(texels[2], [120, 136, 129, 255]);
+        assert_eq!(texels[3], [255, 1, 129, 255]);
+    }
+
+    #[test]
+    fn test_bc7_reserved_mode_is_transparent_black() {
+        let texels = decode_block(BlockFormat::Bc7, &[0; 16]);
+
+        assert!(texels.iter().all(|&texel| texel == [0, 0, 0, 0]));
+    }
+
+    #[test]
+    fn test_partial_blocks_are_cropped() {
+        let block = [0x00, 0xF8, 0x1F, 0x00, 0xE4, 0x00, 0x00, 0x00];
+        let pixels = decode(BlockFormat::Bc1, 2, 1, &block).unwrap();
+
+        assert_eq!(pixels, vec![255, 0, 0, 255, 0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn test_short_data_is_rejected() {
+        assert!(decode(BlockFormat::Bc1, 8, 4, &[0; 8]).is_none());
+    }
+
+    #[test]
+    fn test_bc6h_has_no_software_decoder() {
+        assert!(!BlockFormat::Bc6h.has_software_decoder());
+        assert!(decode(BlockFormat::Bc6h, 4, 4, &[0; 16]).is_none());
+    }
+}
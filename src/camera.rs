@@ -3,11 +3,36 @@ use cglinalg::{
     Vector3, 
     Vector4, 
     Matrix4, 
-    Quaternion
+    Quaternion,
+    Unit,
 };
 use std::fmt;
 
 
+/// How the camera projects the scene onto the screen.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Projection {
+    /// Distant objects look smaller.
+    Perspective,
+    /// Objects look the same size at any distance, so parallel lines stay parallel.
+    Orthographic,
+}
+
+impl Projection {
+    /// Look up a projection by the name it has on the command line.
+    pub fn parse(name: &str) -> Option<Projection> {
+        match name {
+            "perspective" => Some(Projection::Perspective),
+            "orthographic" => Some(Projection::Orthographic),
+            _ => None,
+        }
+    }
+}
+
+/// The distance from the camera at which the orthographic projection shows the
+/// scene at the same size as the perspective projection does, by default.
+pub const DEFAULT_FOCUS_DISTANCE: f32 = 10.0;
+
 #[derive(Clone, Debug)]
 pub struct Camera {
     // Camera parameters.
@@ -15,6 +40,11 @@ pub struct Camera {
     pub far: f32,
     pub fov: Degrees<f32>,
     pub aspect: f32,
+    pub projection: Projection,
+    /// The distance at which the orthographic projection matches the size of the
+    /// perspective projection. The orthographic view is as tall as the perspective
+    /// view is at this distance, so the field of view zooms both projections.
+    pub focus_distance: f32,
 
     // Camera kinematics.
     pub speed: f32,
@@ -33,6 +63,7 @@ pub struct Camera {
 }
 
 impl Camera {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         near: f32, far: f32, fov: Degrees<f32>, aspect: f32,
         cam_speed: f32, cam_yaw_speed: f32, cam_pos: Vector3<f32>,
@@ -48,6 +79,8 @@ impl Camera {
             far: far,
             fov: fov,
             aspect: aspect,
+            projection: Projection::Perspective,
+            focus_distance: DEFAULT_FOCUS_DISTANCE,
 
             speed: cam_speed,
             yaw_speed: cam_yaw_speed,
@@ -63,6 +96,517 @@ impl Camera {
             view_mat: view_mat,
         }
     }
+
+    /// Rebuild the projection matrix from the camera parameters. Run this after
+    /// changing any of them.
+    pub fn update_projection(&mut self) {
+        self.proj_mat = match self.projection {
+            Projection::Perspective => {
+                Matrix4::from_perspective_fov(self.fov, self.aspect, self.near, self.far)
+            }
+            Projection::Orthographic => {
+                let top = self.focus_distance * f32::tan(self.fov.0.to_radians() / 2.0);
+                let right = top * self.aspect;
+                Matrix4::from_orthographic(-right, right, -top, top, self.near, self.far)
+            }
+        };
+    }
+
+    /// The position and orientation of the camera.
+    #[inline]
+    pub fn pose(&self) -> CameraPose {
+        CameraPose::new(self.pos, self.axis)
+    }
+
+    /// Move the camera to a new position and orientation, and recalculate its local
+    /// axes and view matrix to match.
+    pub fn set_pose(&mut self, pose: CameraPose) {
+        self.pos = pose.pos;
+        self.axis = pose.axis;
+
+        let rot_mat_inv = Matrix4::from(self.axis);
+        self.fwd = rot_mat_inv * Vector4::new(0.0, 0.0, -1.0, 0.0);
+        self.rgt = rot_mat_inv * Vector4::new(1.0, 0.0,  0.0, 0.0);
+        self.up  = rot_mat_inv * Vector4::new(0.0, 1.0,  0.0, 0.0);
+
+        self.rot_mat = rot_mat_inv.inverse().unwrap();
+        self.trans_mat = Matrix4::from_affine_translation(&(-self.pos));
+        self.view_mat = self.rot_mat * self.trans_mat;
+    }
+}
+
+/// The position and orientation of a camera, without any of its lens parameters.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CameraPose {
+    pub pos: Vector3<f32>,
+    pub axis: Quaternion<f32>,
+}
+
+impl CameraPose {
+    #[inline]
+    pub fn new(pos: Vector3<f32>, axis: Quaternion<f32>) -> CameraPose {
+        CameraPose {
+            pos: pos,
+            axis: axis,
+        }
+    }
+
+    /// The pose `amount` of the way from this pose to `other`. The position moves
+    /// in a straight line and the orientation turns at a constant rate.
+    pub fn interpolate(&self, other: &CameraPose, amount: f32) -> CameraPose {
+        let pos = self.pos * (1.0 - amount) + other.pos * amount;
+        let axis = self.axis.slerp(&other.axis, amount);
+
+        CameraPose::new(pos, axis)
+    }
+}
+
+/// How the camera responds to the controls.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum CameraMode {
+    /// The camera flies freely, moving along and turning about its own axes.
+    FreeFly,
+    /// The camera circles a target point and always faces it.
+    Orbit,
+}
+
+/// Rotate a vector by a quaternion.
+#[inline]
+fn rotate_vector(q: Quaternion<f32>, v: Vector3<f32>) -> Vector3<f32> {
+    (Matrix4::from(q) * Vector4::new(v.x, v.y, v.z, 0.0)).contract()
+}
+
+/// The closest an orbit camera can get to its target.
+pub const MIN_ORBIT_DISTANCE: f32 = 1.0;
+
+/// The farthest an orbit camera can get from its target.
+pub const MAX_ORBIT_DISTANCE: f32 = 50.0;
+
+/// The steepest an orbit camera can look down or up at its target, in degrees. The
+/// camera stops short of straight up or down so its up direction stays well defined.
+pub const MAX_ORBIT_ELEVATION: f32 = 89.0;
+
+/// A camera that circles a target point. Its position is given by its distance from
+/// the target, its azimuth around the target, and its elevation above it. The angles
+/// are measured in a reference frame, the orientation the camera has when both angles
+/// are zero. In that orientation the camera sits behind the target along its own
+/// backwards direction, and the azimuth turns about its up direction.
+#[derive(Copy, Clone, Debug)]
+pub struct OrbitCamera {
+    pub target: Vector3<f32>,
+    pub distance: f32,
+    /// The angle around the target in degrees.
+    pub azimuth: f32,
+    /// The angle above the target in degrees.
+    pub elevation: f32,
+    frame: Quaternion<f32>,
+}
+
+impl OrbitCamera {
+    pub fn new(target: Vector3<f32>, distance: f32, frame: Quaternion<f32>) -> OrbitCamera {
+        OrbitCamera {
+            target: target,
+            distance: distance,
+            azimuth: 0.0,
+            elevation: 0.0,
+            frame: frame,
+        }
+    }
+
+    /// Set the distance, azimuth and elevation so the camera sits at `pos`.
+    pub fn look_from(&mut self, pos: Vector3<f32>) {
+        let offset = pos - self.target;
+        let right = rotate_vector(self.frame, Vector3::new(1.0, 0.0, 0.0));
+        let up = rotate_vector(self.frame, Vector3::new(0.0, 1.0, 0.0));
+        let back = rotate_vector(self.frame, Vector3::new(0.0, 0.0, 1.0));
+        let distance = offset.magnitude();
+        if distance <= 0.0 {
+            return;
+        }
+        let x = offset.dot(&right);
+        let y = offset.dot(&up);
+        let z = offset.dot(&back);
+
+        self.azimuth = x.atan2(z).to_degrees();
+        // Rounding can push the sine just past one for a camera straight above or
+        // below the target.
+        self.elevation = (y / distance).clamp(-1.0, 1.0).asin().to_degrees();
+        self.elevation = self.elevation.clamp(-MAX_ORBIT_ELEVATION, MAX_ORBIT_ELEVATION);
+        self.distance = distance.clamp(MIN_ORBIT_DISTANCE, MAX_ORBIT_DISTANCE);
+    }
+
+    /// Circle the target by the given angles in degrees.
+    pub fn rotate(&mut self, azimuth: f32, elevation: f32) {
+        self.azimuth = (self.azimuth + azimuth) % 360.0;
+        self.elevation = (self.elevation + elevation).clamp(-MAX_ORBIT_ELEVATION, MAX_ORBIT_ELEVATION);
+    }
+
+    /// Move towards the target, or away from it for a negative amount.
+    pub fn zoom(&mut self, amount: f32) {
+        self.distance = (self.distance - amount).clamp(MIN_ORBIT_DISTANCE, MAX_ORBIT_DISTANCE);
+    }
+
+    /// Slide the target sideways and vertically relative to the view, carrying the
+    /// camera along with it.
+    pub fn pan(&mut self, right: f32, up: f32) {
+        let orientation = self.orientation();
+        self.target += rotate_vector(orientation, Vector3::new(1.0, 0.0, 0.0)) * right;
+        self.target += rotate_vector(orientation, Vector3::new(0.0, 1.0, 0.0)) * up;
+    }
+
+    /// The orientation of the camera. Raising the camera above the target pitches it
+    /// down to keep facing the target, so the elevation turns the camera about its
+    /// right direction the opposite way.
+    pub fn orientation(&self) -> Quaternion<f32> {
+        let up = Unit::from_value(rotate_vector(self.frame, Vector3::new(0.0, 1.0, 0.0)));
+        let right = Unit::from_value(rotate_vector(self.frame, Vector3::new(1.0, 0.0, 0.0)));
+        let q_azimuth = Quaternion::from_axis_angle(&up, Degrees(self.azimuth));
+        let q_elevation = Quaternion::from_axis_angle(&right, Degrees(-self.elevation));
+
+        q_azimuth * q_elevation * self.frame
+    }
+
+    /// The position and orientation of the camera.
+    pub fn pose(&self) -> CameraPose {
+        let orientation = self.orientation();
+        let back = rotate_vector(orientation, Vector3::new(0.0, 0.0, 1.0));
+
+        CameraPose::new(self.target + back * self.distance, orientation)
+    }
+}
+
+/// The length of a switch between camera modes in seconds.
+pub const CAMERA_TRANSITION_TIME: f32 = 0.5;
+
+/// A smooth move of the camera from one pose to another. The destination can keep
+/// moving while the camera is on its way, so the transition only remembers where it
+/// started and blends towards wherever the destination is.
+#[derive(Copy, Clone, Debug)]
+pub struct CameraTransition {
+    from: CameraPose,
+    elapsed: f32,
+    duration: f32,
+}
+
+impl CameraTransition {
+    pub fn new(from: CameraPose, duration: f32) -> CameraTransition {
+        CameraTransition {
+            from: from,
+            elapsed: 0.0,
+            duration: duration,
+        }
+    }
+
+    #[inline]
+    pub fn advance(&mut self, elapsed_seconds: f32) {
+        self.elapsed = f32::min(self.elapsed + elapsed_seconds, self.duration);
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// The pose of the camera on its way to `to`. The camera eases out of the
+    /// starting pose and into the destination.
+    pub fn blend(&self, to: &CameraPose) -> CameraPose {
+        let amount = if self.duration > 0.0 { self.elapsed / self.duration } else { 1.0 };
+        let eased = amount * amount * (3.0 - 2.0 * amount);
+
+        self.from.interpolate(to, eased)
+    }
+}
+
+/// What the controls ask of the camera during one update.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CameraInput {
+    /// How strongly to move along the right, up and backward directions of the
+    /// camera, each between -1 and 1.
+    pub movement: Vector3<f32>,
+    /// How strongly to yaw left, between -1 and 1.
+    pub yaw: f32,
+    /// How strongly to pitch up, between -1 and 1.
+    pub pitch: f32,
+    /// How strongly to roll right, between -1 and 1.
+    pub roll: f32,
+    /// A turn to the left in degrees on top of `yaw`, applied all at once rather than
+    /// at the yaw speed. This is how the mouse turns the camera.
+    pub look_yaw: f32,
+    /// A turn upwards in degrees on top of `pitch`, applied all at once.
+    pub look_pitch: f32,
+    /// How far to bring the orbit camera towards its target, as a fraction of its
+    /// distance from the target.
+    pub zoom: f32,
+}
+
+impl CameraInput {
+    pub fn new() -> CameraInput {
+        CameraInput {
+            movement: Vector3::new(0.0, 0.0, 0.0),
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            look_yaw: 0.0,
+            look_pitch: 0.0,
+            zoom: 0.0,
+        }
+    }
+
+    /// Determine whether the input asks the camera to do anything.
+    pub fn is_idle(&self) -> bool {
+        self.movement == Vector3::new(0.0, 0.0, 0.0) &&
+            self.yaw == 0.0 && self.pitch == 0.0 && self.roll == 0.0 &&
+            self.look_yaw == 0.0 && self.look_pitch == 0.0 && self.zoom == 0.0
+    }
+}
+
+/// Below this speed, in units or degrees per second, a coasting camera stops.
+const MIN_CAMERA_SPEED: f32 = 0.001;
+
+/// How the camera speeds up and slows down. While a control is held, the camera
+/// speeds up at a constant rate towards its top speed. Once the control is let go,
+/// the camera coasts to a stop, losing the same fraction of its speed every second.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct CameraDynamics {
+    /// How fast the camera speeds up, in units per second per second.
+    pub acceleration: f32,
+    /// The top speed of the camera in units per second.
+    pub max_speed: f32,
+    /// How fast the camera slows down when coasting. The speed falls by a factor of
+    /// `e` every `1 / damping` seconds.
+    pub damping: f32,
+    /// How fast the camera speeds up turning, in degrees per second per second.
+    pub turn_acceleration: f32,
+    /// The top turning speed of the camera in degrees per second.
+    pub max_turn_speed: f32,
+    /// How fast the camera stops turning when coasting.
+    pub turn_damping: f32,
+    /// Ease the camera into and out of motion. Without smoothing, the camera starts
+    /// and stops instantly and always moves at its top speed.
+    pub smooth: bool,
+}
+
+impl CameraDynamics {
+    pub fn new() -> CameraDynamics {
+        CameraDynamics {
+            acceleration: 20.0,
+            max_speed: 5.0,
+            damping: 6.0,
+            turn_acceleration: 300.0,
+            max_turn_speed: 50.0,
+            turn_damping: 8.0,
+            smooth: true,
+        }
+    }
+}
+
+/// Step one component of a velocity towards the velocity the control asks for,
+/// which is `strength` times the top speed. The velocity speeds up at `acceleration`
+/// when it has to get faster or change direction, and decays exponentially when it
+/// only has to get slower.
+fn step_velocity(
+    velocity: f32, strength: f32, acceleration: f32, max_speed: f32, damping: f32, elapsed_seconds: f32) -> f32 {
+
+    let target = strength * max_speed;
+    let slowing_down = target.abs() < velocity.abs() && target * velocity >= 0.0;
+    let velocity = if slowing_down {
+        target + (velocity - target) * f32::exp(-damping * elapsed_seconds)
+    } else {
+        let step = acceleration * elapsed_seconds;
+        if (target - velocity).abs() <= step {
+            target
+        } else {
+            velocity + step * (target - velocity).signum()
+        }
+    };
+
+    if target == 0.0 && velocity.abs() < MIN_CAMERA_SPEED {
+        0.0
+    } else {
+        velocity
+    }
+}
+
+/// Moves a camera according to the controls, in either the free-fly or the orbit
+/// mode, and glides it from one mode to the other when the mode changes. The camera
+/// has a velocity, so it eases into and out of motion instead of starting and
+/// stopping abruptly.
+#[derive(Copy, Clone, Debug)]
+pub struct CameraController {
+    mode: CameraMode,
+    orbit: OrbitCamera,
+    free_pose: CameraPose,
+    transition: Option<CameraTransition>,
+    dynamics: CameraDynamics,
+    /// The velocity along the right, up and backward directions of the camera.
+    velocity: Vector3<f32>,
+    /// The yaw, pitch and roll speeds in degrees per second.
+    turn_velocity: Vector3<f32>,
+}
+
+impl CameraController {
+    /// Construct a free-fly controller for a camera. The orbit camera measures its
+    /// angles from the orientation the camera has now.
+    pub fn new(camera: &Camera) -> CameraController {
+        CameraController {
+            mode: CameraMode::FreeFly,
+            orbit: OrbitCamera::new(camera.pos, 10.0, camera.axis),
+            free_pose: camera.pose(),
+            transition: None,
+            dynamics: CameraDynamics::new(),
+            velocity: Vector3::new(0.0, 0.0, 0.0),
+            turn_velocity: Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+
+    pub fn with_dynamics(mut self, dynamics: CameraDynamics) -> CameraController {
+        self.dynamics = dynamics;
+        self
+    }
+
+    /// Bring the camera to an immediate stop.
+    pub fn stop(&mut self) {
+        self.velocity = Vector3::new(0.0, 0.0, 0.0);
+        self.turn_velocity = Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    #[inline]
+    pub fn mode(&self) -> CameraMode {
+        self.mode
+    }
+
+    /// Switch between the free-fly and orbit modes. The orbit camera starts out
+    /// circling `target` from wherever the camera is, and the free-fly camera goes
+    /// back to where it was when it switched to orbiting.
+    pub fn toggle_mode(&mut self, camera: &Camera, target: Vector3<f32>) {
+        self.stop();
+        self.transition = Some(CameraTransition::new(camera.pose(), CAMERA_TRANSITION_TIME));
+        match self.mode {
+            CameraMode::FreeFly => {
+                self.free_pose = camera.pose();
+                self.orbit.target = target;
+                self.orbit.look_from(camera.pos);
+                self.mode = CameraMode::Orbit;
+            }
+            CameraMode::Orbit => {
+                self.mode = CameraMode::FreeFly;
+            }
+        }
+    }
+
+    /// Go straight back to free-fly mode, for when the camera itself gets reset.
+    pub fn reset(&mut self) {
+        self.stop();
+        self.mode = CameraMode::FreeFly;
+        self.transition = None;
+    }
+
+    /// Move the camera according to the input over a span of `elapsed_seconds`, and
+    /// return whether it moved. While switching modes the camera glides towards the
+    /// new mode and ignores the input until it gets there.
+    pub fn update(&mut self, camera: &mut Camera, input: &CameraInput, elapsed_seconds: f32) -> bool {
+        if let Some(mut transition) = self.transition {
+            transition.advance(elapsed_seconds);
+            let to = match self.mode {
+                CameraMode::FreeFly => self.free_pose,
+                CameraMode::Orbit => self.orbit.pose(),
+            };
+            camera.set_pose(transition.blend(&to));
+            self.transition = if transition.is_finished() { None } else { Some(transition) };
+
+            return true;
+        }
+
+        self.update_velocity(input, elapsed_seconds);
+        let stopped = self.velocity == Vector3::new(0.0, 0.0, 0.0) &&
+            self.turn_velocity == Vector3::new(0.0, 0.0, 0.0);
+        if stopped && input.is_idle() {
+            return false;
+        }
+
+        // The mouse turns the camera directly rather than through the turn velocity,
+        // since the hand on the mouse already moves smoothly.
+        let move_to = self.velocity * elapsed_seconds;
+        let cam_yaw = self.turn_velocity.x * elapsed_seconds + input.look_yaw;
+        let cam_pitch = self.turn_velocity.y * elapsed_seconds + input.look_pitch;
+        let cam_roll = self.turn_velocity.z * elapsed_seconds;
+        match self.mode {
+            CameraMode::FreeFly => {
+                CameraController::update_free_fly(camera, move_to, cam_yaw, cam_pitch, cam_roll);
+            }
+            CameraMode::Orbit => {
+                // The orbit camera turns around the target instead of around itself,
+                // zooms in and out instead of moving forward and back, and pans
+                // instead of moving sideways.
+                let distance = self.orbit.distance;
+                self.orbit.rotate(cam_yaw, -cam_pitch);
+                self.orbit.zoom(input.zoom * distance - move_to.z);
+                self.orbit.pan(move_to.x, move_to.y);
+                camera.set_pose(self.orbit.pose());
+            }
+        }
+
+        true
+    }
+
+    /// Speed up or slow down the camera according to how strongly the controls ask
+    /// it to move and turn.
+    fn update_velocity(&mut self, input: &CameraInput, elapsed_seconds: f32) {
+        let d = self.dynamics;
+        if !d.smooth {
+            // Snap straight to the velocity the controls ask for.
+            self.velocity = input.movement * d.max_speed;
+            self.turn_velocity = Vector3::new(input.yaw, input.pitch, input.roll) * d.max_turn_speed;
+            return;
+        }
+
+        let step = |velocity, strength| {
+            step_velocity(velocity, strength, d.acceleration, d.max_speed, d.damping, elapsed_seconds)
+        };
+        self.velocity = Vector3::new(
+            step(self.velocity.x, input.movement.x),
+            step(self.velocity.y, input.movement.y),
+            step(self.velocity.z, input.movement.z)
+        );
+
+        let turn_step = |velocity, strength| {
+            step_velocity(velocity, strength, d.turn_acceleration, d.max_turn_speed, d.turn_damping, elapsed_seconds)
+        };
+        self.turn_velocity = Vector3::new(
+            turn_step(self.turn_velocity.x, input.yaw),
+            turn_step(self.turn_velocity.y, input.pitch),
+            turn_step(self.turn_velocity.z, input.roll)
+        );
+    }
+
+    /// Turn the camera about its own axes, then move it along the turned axes. The
+    /// yaw, pitch and roll all turn about the axes the camera had before the update.
+    fn update_free_fly(camera: &mut Camera, move_to: Vector3<f32>, cam_yaw: f32, cam_pitch: f32, cam_roll: f32) {
+        // Update the axis of rotation of the camera.
+        let rot_mat = Matrix4::from(camera.axis);
+        let yaw_axis = Unit::from_value((rot_mat * Vector4::new(0.0, 1.0, 0.0, 0.0)).contract());
+        let pitch_axis = Unit::from_value((rot_mat * Vector4::new(1.0, 0.0, 0.0, 0.0)).contract());
+        let roll_axis = Unit::from_value((rot_mat * Vector4::new(0.0, 0.0, -1.0, 0.0)).contract());
+        let q_yaw = Quaternion::from_axis_angle(&yaw_axis, Degrees(cam_yaw));
+        let mut axis = q_yaw * camera.axis;
+        let q_pitch = Quaternion::from_axis_angle(&pitch_axis, Degrees(cam_pitch));
+        axis = q_pitch * axis;
+        let q_roll = Quaternion::from_axis_angle(&roll_axis, Degrees(cam_roll));
+        axis = q_roll * axis;
+
+        // Recalculate local axes so we can move fwd in the direction the camera is pointing.
+        let rot_mat_inv = Matrix4::from(axis);
+        let fwd = rot_mat_inv * Vector4::new(0.0, 0.0, -1.0, 0.0);
+        let rgt = rot_mat_inv * Vector4::new(1.0, 0.0,  0.0, 0.0);
+        let up  = rot_mat_inv * Vector4::new(0.0, 1.0,  0.0, 0.0);
+
+        let mut pos = camera.pos;
+        pos += fwd.contract() * -move_to.z;
+        pos += up.contract()  *  move_to.y;
+        pos += rgt.contract() *  move_to.x;
+
+        camera.set_pose(CameraPose::new(pos, axis));
+    }
 }
 
 impl fmt::Display for Camera {
@@ -71,6 +615,8 @@ impl fmt::Display for Camera {
         writeln!(f, "near: {}", self.near).unwrap();
         writeln!(f, "far: {}", self.far).unwrap();
         writeln!(f, "aspect: {}", self.aspect).unwrap();
+        writeln!(f, "projection: {:?}", self.projection).unwrap();
+        writeln!(f, "focus_distance: {}", self.focus_distance).unwrap();
         writeln!(f, "speed: {}", self.speed).unwrap();
         writeln!(f, "yaw_speed: {}", self.yaw_speed).unwrap();
         writeln!(f, "pos: {}", self.pos).unwrap();
@@ -85,3 +631,278 @@ impl fmt::Display for Camera {
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    const EPSILON: f32 = 1e-4;
+
+    /// A camera at the origin looking down the -z axis with +y up. It moves one unit
+    /// and turns 90 degrees per second, so one second of input at full strength
+    /// gives round numbers.
+    fn camera() -> Camera {
+        Camera::new(
+            0.1, 100.0, Degrees(67.0), 1.0, 1.0, 90.0,
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector4::new(0.0, 0.0, -1.0, 0.0),
+            Vector4::new(1.0, 0.0, 0.0, 0.0),
+            Vector4::new(0.0, 1.0, 0.0, 0.0),
+            Quaternion::identity()
+        )
+    }
+
+    fn assert_close(result: Vector3<f32>, expected: Vector3<f32>) {
+        let difference = result - expected;
+        assert!(
+            difference.x.abs() < EPSILON && difference.y.abs() < EPSILON && difference.z.abs() < EPSILON,
+            "expected {}, got {}", expected, result
+        );
+    }
+
+    /// Dynamics without any smoothing. The camera starts and stops instantly, and
+    /// always moves at its top speed.
+    fn immediate(max_speed: f32, max_turn_speed: f32) -> CameraDynamics {
+        CameraDynamics {
+            max_speed: max_speed,
+            max_turn_speed: max_turn_speed,
+            smooth: false,
+            ..CameraDynamics::new()
+        }
+    }
+
+    /// A controller for `camera()` that moves at a constant speed, so the movement
+    /// maths can be checked on its own.
+    fn controller(camera: &Camera) -> CameraController {
+        CameraController::new(camera).with_dynamics(immediate(1.0, 90.0))
+    }
+
+    fn update(controller: &mut CameraController, camera: &mut Camera, input: CameraInput) {
+        assert!(controller.update(camera, &input, 1.0));
+    }
+
+    #[test]
+    fn test_idle_input_does_not_move_the_camera() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+
+        assert!(!controller.update(&mut camera, &CameraInput::new(), 1.0));
+        assert_close(camera.pos, Vector3::new(0.0, 0.0, 0.0));
+        assert_close(camera.fwd.contract(), Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_yaw_left_turns_forward_to_the_left() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+        update(&mut controller, &mut camera, CameraInput { yaw: 1.0, ..CameraInput::new() });
+
+        assert_close(camera.fwd.contract(), Vector3::new(-1.0, 0.0, 0.0));
+        assert_close(camera.rgt.contract(), Vector3::new(0.0, 0.0, -1.0));
+        assert_close(camera.up.contract(), Vector3::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn test_pitch_up_turns_forward_upwards() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+        update(&mut controller, &mut camera, CameraInput { pitch: 1.0, ..CameraInput::new() });
+
+        assert_close(camera.fwd.contract(), Vector3::new(0.0, 1.0, 0.0));
+        assert_close(camera.rgt.contract(), Vector3::new(1.0, 0.0, 0.0));
+        assert_close(camera.up.contract(), Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_roll_right_tilts_up_to_the_right() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+        update(&mut controller, &mut camera, CameraInput { roll: 1.0, ..CameraInput::new() });
+
+        assert_close(camera.fwd.contract(), Vector3::new(0.0, 0.0, -1.0));
+        assert_close(camera.rgt.contract(), Vector3::new(0.0, -1.0, 0.0));
+        assert_close(camera.up.contract(), Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_pitch_after_yaw_turns_about_the_new_right_axis() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+        update(&mut controller, &mut camera, CameraInput { yaw: 1.0, ..CameraInput::new() });
+        update(&mut controller, &mut camera, CameraInput { pitch: 1.0, ..CameraInput::new() });
+
+        assert_close(camera.fwd.contract(), Vector3::new(0.0, 1.0, 0.0));
+        assert_close(camera.rgt.contract(), Vector3::new(0.0, 0.0, -1.0));
+        assert_close(camera.up.contract(), Vector3::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_yaw_after_pitch_turns_about_the_new_up_axis() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+        update(&mut controller, &mut camera, CameraInput { pitch: 1.0, ..CameraInput::new() });
+        update(&mut controller, &mut camera, CameraInput { yaw: 1.0, ..CameraInput::new() });
+
+        assert_close(camera.fwd.contract(), Vector3::new(-1.0, 0.0, 0.0));
+        assert_close(camera.rgt.contract(), Vector3::new(0.0, 1.0, 0.0));
+        assert_close(camera.up.contract(), Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_look_turns_apply_without_the_yaw_speed() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+        let input = CameraInput { look_yaw: 90.0, ..CameraInput::new() };
+        assert!(controller.update(&mut camera, &input, 0.001));
+
+        assert_close(camera.fwd.contract(), Vector3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_move_forward_follows_fwd() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+        update(&mut controller, &mut camera, CameraInput { yaw: 1.0, ..CameraInput::new() });
+        let movement = Vector3::new(0.0, 0.0, -1.0);
+        update(&mut controller, &mut camera, CameraInput { movement: movement, ..CameraInput::new() });
+
+        assert_close(camera.pos, Vector3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_move_right_follows_rgt() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+        update(&mut controller, &mut camera, CameraInput { yaw: 1.0, ..CameraInput::new() });
+        let movement = Vector3::new(1.0, 0.0, 0.0);
+        update(&mut controller, &mut camera, CameraInput { movement: movement, ..CameraInput::new() });
+
+        assert_close(camera.pos, Vector3::new(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn test_move_up_follows_up() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+        update(&mut controller, &mut camera, CameraInput { pitch: 1.0, ..CameraInput::new() });
+        let movement = Vector3::new(0.0, 1.0, 0.0);
+        update(&mut controller, &mut camera, CameraInput { movement: movement, ..CameraInput::new() });
+
+        assert_close(camera.pos, Vector3::new(0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_orbit_yaw_circles_the_target() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+        controller.toggle_mode(&camera, Vector3::new(0.0, 0.0, -5.0));
+        // Finish gliding into orbit mode.
+        update(&mut controller, &mut camera, CameraInput::new());
+
+        assert_eq!(controller.mode(), CameraMode::Orbit);
+        assert_close(camera.pos, Vector3::new(0.0, 0.0, 0.0));
+
+        update(&mut controller, &mut camera, CameraInput { yaw: 1.0, ..CameraInput::new() });
+
+        assert_close(camera.pos, Vector3::new(5.0, 0.0, -5.0));
+        assert_close(camera.fwd.contract(), Vector3::new(-1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_view_matrix_maps_the_camera_position_to_the_origin() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+        update(&mut controller, &mut camera, CameraInput { yaw: 0.5, pitch: 0.25, ..CameraInput::new() });
+        let movement = Vector3::new(0.3, -0.2, -1.0);
+        update(&mut controller, &mut camera, CameraInput { movement: movement, ..CameraInput::new() });
+        let pos = camera.pos;
+        let origin = camera.view_mat * Vector4::new(pos.x, pos.y, pos.z, 1.0);
+
+        assert_close(origin.contract(), Vector3::new(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_pose_interpolation_moves_and_turns_part_of_the_way() {
+        let up = Unit::from_value(Vector3::new(0.0, 1.0, 0.0));
+        let from = CameraPose::new(Vector3::new(0.0, 0.0, 0.0), Quaternion::identity());
+        let to = CameraPose::new(Vector3::new(4.0, 0.0, -2.0), Quaternion::from_axis_angle(&up, Degrees(90.0)));
+        let mut camera = camera();
+        camera.set_pose(from.interpolate(&to, 0.5));
+
+        assert_close(camera.pos, Vector3::new(2.0, 0.0, -1.0));
+        assert_close(camera.fwd.contract(), Vector3::new(-f32::sqrt(0.5), 0.0, -f32::sqrt(0.5)));
+        assert_close(from.interpolate(&to, 0.0).pos, from.pos);
+        assert_close(from.interpolate(&to, 1.0).pos, to.pos);
+    }
+
+    #[test]
+    fn test_velocity_speeds_up_at_the_acceleration() {
+        let velocity = step_velocity(0.0, 1.0, 10.0, 5.0, 4.0, 0.1);
+
+        assert!((velocity - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_velocity_never_exceeds_the_top_speed() {
+        let mut velocity = 0.0;
+        for _ in 0..100 {
+            velocity = step_velocity(velocity, 1.0, 10.0, 5.0, 4.0, 0.1);
+        }
+
+        assert!((velocity - 5.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_partial_strength_limits_the_top_speed() {
+        let mut velocity = 0.0;
+        for _ in 0..100 {
+            velocity = step_velocity(velocity, -0.5, 10.0, 5.0, 4.0, 0.1);
+        }
+
+        assert!((velocity + 2.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_released_velocity_decays_exponentially() {
+        let velocity = step_velocity(5.0, 0.0, 10.0, 5.0, 4.0, 0.25);
+
+        assert!((velocity - 5.0 * f32::exp(-1.0)).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_released_velocity_comes_to_a_stop() {
+        let mut velocity = 5.0;
+        for _ in 0..1000 {
+            velocity = step_velocity(velocity, 0.0, 10.0, 5.0, 4.0, 0.1);
+        }
+
+        assert_eq!(velocity, 0.0);
+    }
+
+    #[test]
+    fn test_reversing_direction_accelerates_through_zero() {
+        let velocity = step_velocity(1.0, -1.0, 10.0, 5.0, 4.0, 0.15);
+
+        assert!((velocity + 0.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_unsmoothed_camera_snaps_to_the_requested_velocity() {
+        let mut camera = camera();
+        let mut controller = controller(&camera);
+        let mut input = CameraInput::new();
+        input.movement = Vector3::new(0.5, 0.0, -1.0);
+        input.yaw = 1.0;
+        controller.update(&mut camera, &input, 0.0);
+
+        assert_close(controller.velocity, Vector3::new(0.5, 0.0, -1.0));
+        assert_close(controller.turn_velocity, Vector3::new(90.0, 0.0, 0.0));
+        assert_close(camera.pos, Vector3::new(0.0, 0.0, 0.0));
+
+        controller.update(&mut camera, &CameraInput::new(), 0.0);
+
+        assert_close(controller.velocity, Vector3::new(0.0, 0.0, 0.0));
+        assert_close(controller.turn_velocity, Vector3::new(0.0, 0.0, 0.0));
+    }
+}
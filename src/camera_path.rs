@@ -0,0 +1,445 @@
+//! Camera paths. A camera path is a sequence of camera poses over time, either
+//! recorded from the camera as it flies around or written by hand, and played back
+//! to fly the camera along the same route every time. Playback runs a spline through
+//! the recorded positions and slerps between the recorded orientations, so the camera
+//! moves smoothly even when the poses are far apart.
+//!
+//! A camera path file holds one pose per line, as the time in seconds, the position,
+//! and the orientation quaternion, scalar part first:
+//! ```text
+//! # time  x y z  s qx qy qz
+//! 0.0  0.0 0.0 10.0  0.0 0.0 0.0 -1.0
+//! ```
+//! Quaternions are awkward to write by hand, so a line can give the point the camera
+//! looks at instead:
+//! ```text
+//! 4.0  5.0 0.0 8.0  look_at 0.0 0.0 0.0
+//! ```
+use crate::camera::{
+    CameraPose,
+    OrbitCamera,
+};
+use cglinalg::{
+    Quaternion,
+    Vector3,
+};
+
+use std::fs;
+use std::path::Path;
+
+
+/// A camera pose at a point in time.
+#[derive(Copy, Clone, Debug)]
+pub struct CameraKeyframe {
+    pub time: f32,
+    pub pose: CameraPose,
+}
+
+impl CameraKeyframe {
+    #[inline]
+    pub fn new(time: f32, pose: CameraPose) -> CameraKeyframe {
+        CameraKeyframe {
+            time: time,
+            pose: pose,
+        }
+    }
+}
+
+/// A sequence of camera poses sorted by time.
+#[derive(Clone, Debug)]
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>,
+}
+
+/// Blend four points along a Catmull-Rom spline. The spline passes through `p1` at
+/// `amount == 0` and through `p2` at `amount == 1`, and the outer points set the
+/// direction it passes through them in.
+fn catmull_rom(
+    p0: Vector3<f32>, p1: Vector3<f32>, p2: Vector3<f32>, p3: Vector3<f32>, amount: f32) -> Vector3<f32> {
+
+    let t = amount;
+    let t2 = t * t;
+    let t3 = t2 * t;
+
+    (p1 * 2.0 +
+        (p2 - p0) * t +
+        (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2 +
+        (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3) * 0.5
+}
+
+/// Parse a number from a camera path file. Infinities and NaNs are rejected, since
+/// they cannot be sorted or blended into a pose.
+fn parse_number(value: Option<&str>) -> Result<f32, String> {
+    match value {
+        Some(value) => match value.parse::<f32>() {
+            Ok(number) if number.is_finite() => Ok(number),
+            _ => Err(format!("Invalid number `{}`", value)),
+        },
+        None => Err(String::from("Expected more numbers")),
+    }
+}
+
+impl CameraPath {
+    pub fn new() -> CameraPath {
+        CameraPath {
+            keyframes: vec![],
+        }
+    }
+
+    /// Add a pose to the end of the path. Poses have to be added in time order.
+    pub fn push(&mut self, keyframe: CameraKeyframe) {
+        if let Some(last) = self.keyframes.last() {
+            assert!(keyframe.time >= last.time);
+        }
+        self.keyframes.push(keyframe);
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.keyframes.is_empty()
+    }
+
+    #[inline]
+    pub fn keyframes(&self) -> &[CameraKeyframe] {
+        &self.keyframes
+    }
+
+    /// The time of the last pose.
+    #[inline]
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// Sample the path at a point in time. Times before the first pose or after the
+    /// last one hold that pose. A path needs at least one pose to be sampled.
+    pub fn sample(&self, time: f32) -> CameraPose {
+        assert!(!self.keyframes.is_empty());
+        let first = &self.keyframes[0];
+        let last = &self.keyframes[self.keyframes.len() - 1];
+        if time <= first.time {
+            return first.pose;
+        }
+        if time >= last.time {
+            return last.pose;
+        }
+
+        // The index of the first pose after `time`. It exists and is not the first
+        // pose because of the bounds checks above. The end poses stand in for the
+        // missing outer points of the spline at either end of the path.
+        let next = self.keyframes.iter().position(|keyframe| keyframe.time > time).unwrap();
+        let k0 = &self.keyframes[next - 1];
+        let k1 = &self.keyframes[next];
+        let before = &self.keyframes[next.saturating_sub(2)];
+        let after = &self.keyframes[usize::min(next + 1, self.keyframes.len() - 1)];
+        let amount = (time - k0.time) / (k1.time - k0.time);
+        let pos = catmull_rom(before.pose.pos, k0.pose.pos, k1.pose.pos, after.pose.pos, amount);
+        let axis = k0.pose.axis.slerp(&k1.pose.axis, amount);
+
+        CameraPose::new(pos, axis)
+    }
+
+    /// Parse a camera path file. Blank lines and lines starting with `#` are ignored.
+    /// The orientations of the poses given with `look_at` keep the up direction of
+    /// the orientation `frame`, and the quaternions given outright are normalised.
+    /// The poses can appear in any order.
+    pub fn parse(text: &str, frame: Quaternion<f32>) -> Result<CameraPath, String> {
+        let mut keyframes = vec![];
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let keyframe = CameraPath::parse_keyframe(line, frame).map_err(|e| {
+                format!("Line {}: {}", line_number + 1, e)
+            })?;
+            keyframes.push(keyframe);
+        }
+        if keyframes.is_empty() {
+            return Err(String::from("The camera path has no poses"));
+        }
+        // Finite times always compare.
+        keyframes.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+
+        Ok(CameraPath {
+            keyframes: keyframes,
+        })
+    }
+
+    fn parse_keyframe(line: &str, frame: Quaternion<f32>) -> Result<CameraKeyframe, String> {
+        let mut fields = line.split_whitespace();
+        let time = parse_number(fields.next())?;
+        let x = parse_number(fields.next())?;
+        let y = parse_number(fields.next())?;
+        let z = parse_number(fields.next())?;
+        let pos = Vector3::new(x, y, z);
+        let mut rest = fields.clone();
+        let axis = if rest.next() == Some("look_at") {
+            fields = rest;
+            let x = parse_number(fields.next())?;
+            let y = parse_number(fields.next())?;
+            let z = parse_number(fields.next())?;
+            let mut orbit = OrbitCamera::new(Vector3::new(x, y, z), 1.0, frame);
+            orbit.look_from(pos);
+
+            orbit.orientation()
+        } else {
+            let s = parse_number(fields.next())?;
+            let qx = parse_number(fields.next())?;
+            let qy = parse_number(fields.next())?;
+            let qz = parse_number(fields.next())?;
+            let magnitude = f32::sqrt(s * s + qx * qx + qy * qy + qz * qz);
+            if !(magnitude > 0.0 && magnitude.is_finite()) {
+                return Err(format!("Cannot normalise the orientation `{} {} {} {}`", s, qx, qy, qz));
+            }
+
+            Quaternion::new(s / magnitude, qx / magnitude, qy / magnitude, qz / magnitude)
+        };
+        if let Some(field) = fields.next() {
+            return Err(format!("Unexpected `{}` at the end of the line", field));
+        }
+
+        Ok(CameraKeyframe::new(time, CameraPose::new(pos, axis)))
+    }
+
+    /// Write the path out in the camera path file format.
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# time  x y z  s qx qy qz\n");
+        for keyframe in self.keyframes.iter() {
+            let pos = keyframe.pose.pos;
+            let axis = keyframe.pose.axis;
+            text.push_str(&format!(
+                "{}  {} {} {}  {} {} {} {}\n",
+                keyframe.time, pos.x, pos.y, pos.z, axis.s, axis.v.x, axis.v.y, axis.v.z
+            ));
+        }
+
+        text
+    }
+
+    /// Load a camera path from a file.
+    pub fn load_file<P: AsRef<Path>>(path: P, frame: Quaternion<f32>) -> Result<CameraPath, String> {
+        let text = match fs::read_to_string(&path) {
+            Ok(val) => val,
+            Err(e) => return Err(format!("Could not read {}: {}", path.as_ref().display(), e)),
+        };
+
+        CameraPath::parse(&text, frame).map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+    }
+
+    /// Save the camera path to a file.
+    pub fn save_file<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        fs::write(&path, self.to_text()).map_err(|e| {
+            format!("Could not write {}: {}", path.as_ref().display(), e)
+        })
+    }
+}
+
+/// Records the pose of the camera at regular intervals.
+#[derive(Clone, Debug)]
+pub struct CameraRecorder {
+    path: CameraPath,
+    interval: f32,
+    time: f32,
+    next_sample: f32,
+    last_pose: Option<(f32, CameraPose)>,
+}
+
+impl CameraRecorder {
+    /// Construct a recorder that keeps a pose every `interval` seconds. Playback
+    /// smooths out the path between the poses, so they can be far apart.
+    pub fn new(interval: f32) -> CameraRecorder {
+        assert!(interval > 0.0);
+
+        CameraRecorder {
+            path: CameraPath::new(),
+            interval: interval,
+            time: 0.0,
+            next_sample: 0.0,
+            last_pose: None,
+        }
+    }
+
+    /// Record the pose of the camera, then advance the clock by `elapsed_seconds`.
+    pub fn record(&mut self, pose: CameraPose, elapsed_seconds: f32) {
+        if self.time >= self.next_sample {
+            self.path.push(CameraKeyframe::new(self.time, pose));
+            self.next_sample += self.interval;
+        }
+        self.last_pose = Some((self.time, pose));
+        self.time += elapsed_seconds;
+    }
+
+    /// Stop recording and return the path. The last pose is always kept, so the path
+    /// ends where the camera stopped.
+    pub fn finish(mut self) -> CameraPath {
+        if let Some((time, pose)) = self.last_pose {
+            if time > self.path.duration() {
+                self.path.push(CameraKeyframe::new(time, pose));
+            }
+        }
+
+        self.path
+    }
+}
+
+/// Plays a camera path back.
+#[derive(Clone, Debug)]
+pub struct CameraPlayback {
+    path: CameraPath,
+    time: f32,
+}
+
+impl CameraPlayback {
+    pub fn new(path: CameraPath) -> CameraPlayback {
+        assert!(!path.is_empty());
+
+        CameraPlayback {
+            path: path,
+            time: 0.0,
+        }
+    }
+
+    /// The pose of the camera after `elapsed_seconds` more of playback.
+    pub fn advance(&mut self, elapsed_seconds: f32) -> CameraPose {
+        self.time += elapsed_seconds;
+        self.path.sample(self.time)
+    }
+
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.time >= self.path.duration()
+    }
+
+    /// Stop playing, and hand the path back.
+    #[inline]
+    pub fn into_path(self) -> CameraPath {
+        self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cglinalg::{
+        Degrees,
+        Matrix4,
+        Unit,
+        Vector4,
+    };
+
+
+    const EPSILON: f32 = 1e-4;
+
+    fn yaw(degrees: f32) -> Quaternion<f32> {
+        Quaternion::from_axis_angle(&Unit::from_value(Vector3::new(0.0, 1.0, 0.0)), Degrees(degrees))
+    }
+
+    fn forward(axis: Quaternion<f32>) -> Vector3<f32> {
+        (Matrix4::from(axis) * Vector4::new(0.0, 0.0, -1.0, 0.0)).contract()
+    }
+
+    fn assert_close(result: Vector3<f32>, expected: Vector3<f32>) {
+        assert!((result - expected).magnitude() < EPSILON, "expected {}, got {}", expected, result);
+    }
+
+    fn assert_same_orientation(result: Quaternion<f32>, expected: Quaternion<f32>) {
+        let difference = [
+            result.s - expected.s, result.v.x - expected.v.x, result.v.y - expected.v.y, result.v.z - expected.v.z
+        ];
+        assert!(difference.iter().all(|d| d.abs() < EPSILON), "expected {:?}, got {:?}", expected, result);
+    }
+
+    fn path() -> CameraPath {
+        let mut path = CameraPath::new();
+        path.push(CameraKeyframe::new(0.0, CameraPose::new(Vector3::new(0.0, 0.0, 0.0), Quaternion::identity())));
+        path.push(CameraKeyframe::new(2.0, CameraPose::new(Vector3::new(2.0, 0.0, 0.0), yaw(90.0))));
+
+        path
+    }
+
+    #[test]
+    fn test_text_round_trip_keeps_the_poses() {
+        let path = path();
+        let parsed = CameraPath::parse(&path.to_text(), Quaternion::identity()).unwrap();
+
+        assert_eq!(parsed.keyframes().len(), path.keyframes().len());
+        for (result, expected) in parsed.keyframes().iter().zip(path.keyframes().iter()) {
+            assert_eq!(result.time, expected.time);
+            assert_close(result.pose.pos, expected.pose.pos);
+            assert_same_orientation(result.pose.axis, expected.pose.axis);
+        }
+    }
+
+    #[test]
+    fn test_parse_sorts_the_poses_by_time() {
+        let text = "# time  x y z  s qx qy qz\n\n2.0  1.0 0.0 0.0  1.0 0.0 0.0 0.0\n0.5  0.0 0.0 0.0  1.0 0.0 0.0 0.0\n";
+        let path = CameraPath::parse(text, Quaternion::identity()).unwrap();
+
+        assert_eq!(path.keyframes()[0].time, 0.5);
+        assert_eq!(path.keyframes()[1].time, 2.0);
+        assert_eq!(path.duration(), 2.0);
+    }
+
+    #[test]
+    fn test_parse_normalises_the_orientation() {
+        let path = CameraPath::parse("0.0  0.0 0.0 0.0  2.0 0.0 0.0 0.0", Quaternion::identity()).unwrap();
+
+        assert_same_orientation(path.keyframes()[0].pose.axis, Quaternion::identity());
+        assert!(CameraPath::parse("0.0  0.0 0.0 0.0  0.0 0.0 0.0 0.0", Quaternion::identity()).is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_non_finite_numbers() {
+        for text in ["NaN  0.0 0.0 0.0  1.0 0.0 0.0 0.0", "0.0  inf 0.0 0.0  1.0 0.0 0.0 0.0",
+                     "0.0  0.0 0.0 0.0  look_at 0.0 -inf 0.0"].iter() {
+            assert!(CameraPath::parse(text, Quaternion::identity()).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_lines() {
+        assert!(CameraPath::parse("", Quaternion::identity()).is_err());
+        assert!(CameraPath::parse("0.0  0.0 0.0 0.0  1.0 0.0", Quaternion::identity()).is_err());
+        assert!(CameraPath::parse("0.0  0.0 0.0 0.0  1.0 0.0 0.0 0.0 5.0", Quaternion::identity()).is_err());
+    }
+
+    #[test]
+    fn test_look_at_faces_the_target() {
+        let text = "0.0  0.0 0.0 10.0  look_at 0.0 0.0 0.0\n1.0  10.0 0.0 0.0  look_at 0.0 0.0 0.0\n";
+        let path = CameraPath::parse(text, Quaternion::identity()).unwrap();
+
+        assert_close(forward(path.keyframes()[0].pose.axis), Vector3::new(0.0, 0.0, -1.0));
+        assert_close(forward(path.keyframes()[1].pose.axis), Vector3::new(-1.0, 0.0, 0.0));
+        assert_close(path.keyframes()[1].pose.pos, Vector3::new(10.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_sample_holds_the_end_poses() {
+        let path = path();
+
+        assert_close(path.sample(-1.0).pos, Vector3::new(0.0, 0.0, 0.0));
+        assert_close(path.sample(0.0).pos, Vector3::new(0.0, 0.0, 0.0));
+        assert_close(path.sample(2.0).pos, Vector3::new(2.0, 0.0, 0.0));
+        assert_close(path.sample(5.0).pos, Vector3::new(2.0, 0.0, 0.0));
+        assert_same_orientation(path.sample(5.0).axis, yaw(90.0));
+    }
+
+    #[test]
+    fn test_sample_blends_between_the_poses() {
+        let pose = path().sample(1.0);
+
+        assert_close(pose.pos, Vector3::new(1.0, 0.0, 0.0));
+        assert_same_orientation(pose.axis, yaw(45.0));
+    }
+
+    #[test]
+    fn test_sample_passes_through_every_pose() {
+        let mut path = path();
+        path.push(CameraKeyframe::new(3.0, CameraPose::new(Vector3::new(2.0, 4.0, 0.0), yaw(180.0))));
+        let pose = path.sample(2.0);
+
+        assert_close(pose.pos, Vector3::new(2.0, 0.0, 0.0));
+        assert_same_orientation(pose.axis, yaw(90.0));
+    }
+}
@@ -1,3 +1,9 @@
+use crate::gl;
+use cglinalg::{
+    Matrix4,
+    Quaternion,
+    Vector3,
+};
 use std::collections::HashMap;
 
 
@@ -15,10 +21,10 @@ impl From<i32> for ShaderUniformHandle {
     }
 }
 
-impl Into<i32> for ShaderUniformHandle {
+impl From<ShaderUniformHandle> for i32 {
     #[inline]
-    fn into(self) -> i32 {
-        self.inner
+    fn from(handle: ShaderUniformHandle) -> i32 {
+        handle.inner
     }
 }
 
@@ -36,13 +42,15 @@ impl From<u32> for ShaderProgramHandle {
     }
 }
 
-impl Into<u32> for ShaderProgramHandle {
+impl From<ShaderProgramHandle> for u32 {
     #[inline]
-    fn into(self) -> u32 {
-        self.inner
+    fn from(handle: ShaderProgramHandle) -> u32 {
+        handle.inner
     }
 }
 
+/// A compiled and linked shader program together with the locations of its uniforms.
+/// The program owns its GL program object and deletes it when dropped.
 pub struct ShaderProgram {
     pub handle: ShaderProgramHandle,
     pub uniforms: HashMap<String, ShaderUniformHandle>,
@@ -58,19 +66,58 @@ impl ShaderProgram {
     }
 }
 
-#[derive(Copy, Clone, Hash, Eq, PartialEq)]
-pub struct BufferHandle {
+impl Drop for ShaderProgram {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteProgram(self.handle.into());
+        }
+    }
+}
+
+/// An owning wrapper around a GL buffer object. The buffer is deleted when dropped.
+#[derive(Hash, Eq, PartialEq)]
+pub struct Buffer {
     pub vbo: u32,
+}
+
+impl Buffer {
+    #[inline]
+    pub fn new(vbo: u32) -> Buffer {
+        Buffer { vbo }
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+        }
+    }
+}
+
+/// An owning wrapper around a GL vertex array object. The vertex array is deleted
+/// when dropped. It does not own the buffers it reads from.
+#[derive(Hash, Eq, PartialEq)]
+pub struct VertexArray {
     pub vao: u32,
 }
 
-impl BufferHandle {
+impl VertexArray {
     #[inline]
-    pub fn new(vbo: u32, vao: u32) -> BufferHandle {
-        BufferHandle { vbo, vao }
+    pub fn new(vao: u32) -> VertexArray {
+        VertexArray { vao }
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
     }
 }
 
+/// A non-owning reference to a GL texture object.
 #[derive(Copy, Clone, Hash, Eq, PartialEq)]
 pub struct TextureHandle {
     pub inner: u32,
@@ -83,10 +130,76 @@ impl TextureHandle {
     }
 }
 
-impl Into<u32> for TextureHandle {
+impl From<TextureHandle> for u32 {
+    #[inline]
+    fn from(handle: TextureHandle) -> u32 {
+        handle.inner
+    }
+}
+
+/// An owning wrapper around a GL texture object. The texture is deleted when dropped.
+#[derive(Hash, Eq, PartialEq)]
+pub struct Texture {
+    handle: TextureHandle,
+}
+
+impl Texture {
+    #[inline]
+    pub fn new(handle: TextureHandle) -> Texture {
+        Texture { handle }
+    }
+
+    #[inline]
+    pub fn handle(&self) -> TextureHandle {
+        self.handle
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.handle.inner);
+        }
+    }
+}
+
+/// The placement of an object relative to its parent, stored as a translation, a
+/// rotation, and a scale along each axis rather than as a matrix. The model matrix is derived
+/// from these on demand, so repeated updates never accumulate floating point error
+/// in a stored matrix, and the position can always be read straight back out.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Transform {
+    pub translation: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Transform {
+    #[inline]
+    pub fn new(translation: Vector3<f32>, rotation: Quaternion<f32>, scale: Vector3<f32>) -> Transform {
+        Transform {
+            translation: translation,
+            rotation: rotation,
+            scale: scale,
+        }
+    }
+
+    #[inline]
+    pub fn identity() -> Transform {
+        Transform::new(Vector3::new(0.0, 0.0, 0.0), Quaternion::identity(), Vector3::new(1.0, 1.0, 1.0))
+    }
+
+    #[inline]
+    pub fn from_translation(translation: Vector3<f32>) -> Transform {
+        Transform::new(translation, Quaternion::identity(), Vector3::new(1.0, 1.0, 1.0))
+    }
+
+    /// The matrix that scales, then rotates, then translates.
     #[inline]
-    fn into(self) -> u32 {
-        self.inner
+    pub fn to_matrix(self) -> Matrix4<f32> {
+        Matrix4::from_affine_translation(&self.translation) *
+            Matrix4::from(self.rotation) *
+            Matrix4::from_affine_nonuniform_scale(self.scale.x, self.scale.y, self.scale.z)
     }
 }
 
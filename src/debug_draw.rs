@@ -0,0 +1,286 @@
+//! Immediate debug drawing. Lines are added in world space over the course of a
+//! frame, all of them go to the GPU in a single vertex buffer, and the list starts
+//! over the next frame. Shapes are drawn as wireframes made of lines, so they show
+//! where things are without hiding what is behind them.
+use crate::frustum::Aabb;
+use cglinalg::{
+    Matrix4,
+    Vector3,
+    Vector4,
+};
+
+use std::f32::consts;
+
+
+/// The number of line segments in each circle of a sphere.
+const SPHERE_SEGMENTS: usize = 24;
+
+/// The colors of the x, y and z axes.
+pub const X_AXIS_COLOR: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+pub const Y_AXIS_COLOR: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+pub const Z_AXIS_COLOR: [f32; 4] = [0.0, 0.0, 1.0, 1.0];
+
+/// A vertex of a debug line, laid out the way the line shader reads it.
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct LineVertex {
+    pub pos: [f32; 3],
+    pub color: [f32; 4],
+}
+
+/// The lines drawn so far this frame, two vertices per line.
+#[derive(Clone, Debug)]
+pub struct DebugLines {
+    vertices: Vec<LineVertex>,
+}
+
+impl DebugLines {
+    pub fn new() -> DebugLines {
+        DebugLines {
+            vertices: vec![],
+        }
+    }
+
+    /// Forget every line, ready for the next frame.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    #[inline]
+    pub fn vertices(&self) -> &[LineVertex] {
+        &self.vertices
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn draw_line(&mut self, start: &Vector3<f32>, end: &Vector3<f32>, color: [f32; 4]) {
+        self.vertices.push(LineVertex { pos: [start.x, start.y, start.z], color: color });
+        self.vertices.push(LineVertex { pos: [end.x, end.y, end.z], color: color });
+    }
+
+    /// Draw the twelve edges of a box.
+    pub fn draw_aabb(&mut self, aabb: &Aabb, color: [f32; 4]) {
+        self.draw_box(&aabb.corners(), color);
+    }
+
+    /// Draw a sphere as three circles around its center, one in each of the planes
+    /// through the center parallel to the coordinate planes.
+    pub fn draw_sphere(&mut self, center: &Vector3<f32>, radius: f32, color: [f32; 4]) {
+        let circle_point = |axis: usize, angle: f32| {
+            let (sin_angle, cos_angle) = angle.sin_cos();
+            let offset = match axis {
+                0 => Vector3::new(0.0, cos_angle, sin_angle),
+                1 => Vector3::new(sin_angle, 0.0, cos_angle),
+                _ => Vector3::new(cos_angle, sin_angle, 0.0),
+            };
+
+            *center + offset * radius
+        };
+        for axis in 0..3 {
+            for i in 0..SPHERE_SEGMENTS {
+                let start_angle = 2.0 * consts::PI * i as f32 / SPHERE_SEGMENTS as f32;
+                let end_angle = 2.0 * consts::PI * (i + 1) as f32 / SPHERE_SEGMENTS as f32;
+                self.draw_line(&circle_point(axis, start_angle), &circle_point(axis, end_angle), color);
+            }
+        }
+    }
+
+    /// Draw the axes of the coordinate frame a matrix maps to, each one `length`
+    /// units long in the frame's own units. The x axis is red, the y axis green
+    /// and the z axis blue.
+    pub fn draw_axes(&mut self, matrix: &Matrix4<f32>, length: f32) {
+        let origin = transform_point(matrix, &Vector3::new(0.0, 0.0, 0.0));
+        let axes = [
+            (Vector3::new(length, 0.0, 0.0), X_AXIS_COLOR),
+            (Vector3::new(0.0, length, 0.0), Y_AXIS_COLOR),
+            (Vector3::new(0.0, 0.0, length), Z_AXIS_COLOR),
+        ];
+        for &(axis, color) in axes.iter() {
+            self.draw_line(&origin, &transform_point(matrix, &axis), color);
+        }
+    }
+
+    /// Draw the edges of the view frustum of a combined projection and view matrix.
+    /// Nothing is drawn if the matrix cannot be inverted.
+    pub fn draw_frustum(&mut self, proj_view: &Matrix4<f32>, color: [f32; 4]) {
+        let proj_view_inv = match proj_view.inverse() {
+            Some(proj_view_inv) => proj_view_inv,
+            None => return,
+        };
+        // The corners of the clip space cube, in the same order as the corners of
+        // a bounding box.
+        let mut corners = [Vector3::new(0.0, 0.0, 0.0); 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            let ndc = Vector4::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { -1.0 } else { 1.0 },
+                if i & 4 == 0 { -1.0 } else { 1.0 },
+                1.0
+            );
+            let point = proj_view_inv * ndc;
+            *corner = Vector3::new(point.x / point.w, point.y / point.w, point.z / point.w);
+        }
+
+        self.draw_box(&corners, color);
+    }
+
+    /// Draw the normals of a mesh as lines `length` units long, starting at the
+    /// points they belong to. The points and normals are carried into world space
+    /// by the model matrix of the mesh.
+    pub fn draw_normals(
+        &mut self, points: &[[f32; 3]], normals: &[[f32; 3]], model_mat: &Matrix4<f32>,
+        length: f32, color: [f32; 4]) {
+
+        for (point, normal) in points.iter().zip(normals.iter()) {
+            let start = transform_point(model_mat, &Vector3::new(point[0], point[1], point[2]));
+            let direction = *model_mat * Vector4::new(normal[0], normal[1], normal[2], 0.0);
+            let direction = Vector3::new(direction.x, direction.y, direction.z);
+            let magnitude = direction.magnitude();
+            if magnitude > 0.0 {
+                let end = start + direction * (length / magnitude);
+                self.draw_line(&start, &end, color);
+            }
+        }
+    }
+
+    /// Draw the edges of a box given by its corners, where bit 0, 1 and 2 of the
+    /// index of a corner say which side of the box it lies on along x, y and z.
+    fn draw_box(&mut self, corners: &[Vector3<f32>; 8], color: [f32; 4]) {
+        for i in 0..8 {
+            for &bit in [1, 2, 4].iter() {
+                if i & bit == 0 {
+                    self.draw_line(&corners[i], &corners[i | bit], color);
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+fn transform_point(matrix: &Matrix4<f32>, point: &Vector3<f32>) -> Vector3<f32> {
+    let point = *matrix * Vector4::new(point.x, point.y, point.z, 1.0);
+
+    Vector3::new(point.x, point.y, point.z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    const COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+    fn position(vertex: &LineVertex) -> Vector3<f32> {
+        Vector3::new(vertex.pos[0], vertex.pos[1], vertex.pos[2])
+    }
+
+    fn approx_eq(a: &Vector3<f32>, b: &Vector3<f32>) -> bool {
+        (*a - *b).magnitude() < 1e-4
+    }
+
+    #[test]
+    fn test_line_adds_two_vertices() {
+        let mut lines = DebugLines::new();
+        lines.draw_line(&Vector3::new(1.0, 2.0, 3.0), &Vector3::new(4.0, 5.0, 6.0), COLOR);
+
+        assert_eq!(lines.vertices().len(), 2);
+        assert_eq!(lines.vertices()[0].pos, [1.0, 2.0, 3.0]);
+        assert_eq!(lines.vertices()[1].pos, [4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn test_clear_forgets_every_line() {
+        let mut lines = DebugLines::new();
+        lines.draw_line(&Vector3::new(1.0, 2.0, 3.0), &Vector3::new(4.0, 5.0, 6.0), COLOR);
+        lines.clear();
+
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn test_aabb_edges_run_along_the_axes() {
+        let mut lines = DebugLines::new();
+        let aabb = Aabb::new(Vector3::new(-1.0, -2.0, -3.0), Vector3::new(1.0, 2.0, 3.0));
+        lines.draw_aabb(&aabb, COLOR);
+        let vertices = lines.vertices();
+
+        assert_eq!(vertices.len(), 24);
+        for edge in vertices.chunks(2) {
+            let delta = position(&edge[1]) - position(&edge[0]);
+            let changed = [delta.x, delta.y, delta.z].iter().filter(|&&d| d != 0.0).count();
+            assert_eq!(changed, 1);
+        }
+    }
+
+    #[test]
+    fn test_sphere_points_lie_on_the_sphere() {
+        let mut lines = DebugLines::new();
+        let center = Vector3::new(1.0, 2.0, 3.0);
+        lines.draw_sphere(&center, 2.0, COLOR);
+
+        assert_eq!(lines.vertices().len(), 3 * 2 * SPHERE_SEGMENTS);
+        for vertex in lines.vertices().iter() {
+            assert!(((position(vertex) - center).magnitude() - 2.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_axes_follow_the_matrix() {
+        let mut lines = DebugLines::new();
+        let matrix = Matrix4::from_affine_translation(&Vector3::new(1.0, 2.0, 3.0));
+        lines.draw_axes(&matrix, 2.0);
+        let vertices = lines.vertices();
+
+        assert_eq!(vertices.len(), 6);
+        assert!(approx_eq(&position(&vertices[0]), &Vector3::new(1.0, 2.0, 3.0)));
+        assert!(approx_eq(&position(&vertices[1]), &Vector3::new(3.0, 2.0, 3.0)));
+        assert!(approx_eq(&position(&vertices[5]), &Vector3::new(1.0, 2.0, 5.0)));
+        assert_eq!(vertices[1].color, X_AXIS_COLOR);
+        assert_eq!(vertices[3].color, Y_AXIS_COLOR);
+        assert_eq!(vertices[5].color, Z_AXIS_COLOR);
+    }
+
+    #[test]
+    fn test_frustum_of_an_orthographic_projection_is_its_box() {
+        let mut lines = DebugLines::new();
+        let proj_mat = Matrix4::from_orthographic(-2.0, 2.0, -1.0, 1.0, 0.5, 10.0);
+        lines.draw_frustum(&proj_mat, COLOR);
+        let vertices = lines.vertices();
+
+        assert_eq!(vertices.len(), 24);
+        // The camera looks down the negative z axis.
+        for vertex in vertices.iter() {
+            let point = position(vertex);
+            assert!((point.x.abs() - 2.0).abs() < 1e-4);
+            assert!((point.y.abs() - 1.0).abs() < 1e-4);
+            assert!((point.z + 0.5).abs() < 1e-4 || (point.z + 10.0).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_normals_have_the_given_length() {
+        let mut lines = DebugLines::new();
+        let points = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]];
+        let normals = [[0.0, 0.0, 3.0], [0.0, 1.0, 0.0]];
+        let model_mat = Matrix4::from_affine_scale(2.0);
+        lines.draw_normals(&points, &normals, &model_mat, 0.5, COLOR);
+        let vertices = lines.vertices();
+
+        assert_eq!(vertices.len(), 4);
+        assert!(approx_eq(&position(&vertices[1]), &Vector3::new(0.0, 0.0, 0.5)));
+        assert!(approx_eq(&position(&vertices[2]), &Vector3::new(2.0, 0.0, 0.0)));
+        assert!(approx_eq(&position(&vertices[3]), &Vector3::new(2.0, 0.5, 0.0)));
+    }
+
+    #[test]
+    fn test_zero_normals_are_skipped() {
+        let mut lines = DebugLines::new();
+        lines.draw_normals(&[[0.0, 0.0, 0.0]], &[[0.0, 0.0, 0.0]], &Matrix4::identity(), 1.0, COLOR);
+
+        assert!(lines.is_empty());
+    }
+}
@@ -0,0 +1,293 @@
+//! View frustum culling. Every entity carries an axis-aligned bounding box around
+//! its mesh. Each frame the box is carried into world space by the model matrix of
+//! the entity and tested against the six planes of the view frustum, and entities
+//! whose boxes lie entirely outside the frustum are not drawn.
+use cglinalg::{
+    Matrix4,
+    Vector3,
+    Vector4,
+};
+
+
+/// An axis-aligned bounding box.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    #[inline]
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Aabb {
+        Aabb {
+            min: min,
+            max: max,
+        }
+    }
+
+    /// The smallest box containing every point, or `None` if there are no points.
+    pub fn from_points(points: &[[f32; 3]]) -> Option<Aabb> {
+        let first = points.first()?;
+        let mut min = Vector3::new(first[0], first[1], first[2]);
+        let mut max = min;
+        for point in points.iter() {
+            min.x = f32::min(min.x, point[0]);
+            min.y = f32::min(min.y, point[1]);
+            min.z = f32::min(min.z, point[2]);
+            max.x = f32::max(max.x, point[0]);
+            max.y = f32::max(max.y, point[1]);
+            max.z = f32::max(max.z, point[2]);
+        }
+
+        Some(Aabb::new(min, max))
+    }
+
+    /// The eight corners of the box.
+    pub fn corners(&self) -> [Vector3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Vector3::new(min.x, min.y, min.z), Vector3::new(max.x, min.y, min.z),
+            Vector3::new(min.x, max.y, min.z), Vector3::new(max.x, max.y, min.z),
+            Vector3::new(min.x, min.y, max.z), Vector3::new(max.x, min.y, max.z),
+            Vector3::new(min.x, max.y, max.z), Vector3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// The smallest axis-aligned box containing this box after an affine transform.
+    /// A rotated box no longer lines up with the axes, so the result can be larger
+    /// than the box itself.
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Aabb {
+        let corners = self.corners();
+        let transformed: Vec<[f32; 3]> = corners.iter().map(|corner| {
+            let point = *matrix * Vector4::new(corner.x, corner.y, corner.z, 1.0);
+            [point.x, point.y, point.z]
+        }).collect();
+
+        Aabb::from_points(&transformed).unwrap()
+    }
+}
+
+/// A plane, given as the points `p` where `normal.dot(p) + distance == 0`. The
+/// normal points to the inside of the frustum.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Plane {
+    pub normal: Vector3<f32>,
+    pub distance: f32,
+}
+
+impl Plane {
+    /// Construct a plane from the coefficients of its equation, scaled so the
+    /// normal has unit length. The signed distance of a point from the plane is
+    /// then a true distance.
+    pub fn from_coefficients(coefficients: Vector4<f32>) -> Plane {
+        let normal = Vector3::new(coefficients.x, coefficients.y, coefficients.z);
+        let length = normal.magnitude();
+
+        Plane {
+            normal: normal / length,
+            distance: coefficients.w / length,
+        }
+    }
+
+    /// The distance of a point from the plane. It is positive on the side the normal
+    /// points to, and negative on the other side.
+    #[inline]
+    pub fn signed_distance(&self, point: &Vector3<f32>) -> f32 {
+        self.normal.dot(point) + self.distance
+    }
+}
+
+/// The region of space a camera can see, bounded by six planes.
+#[derive(Copy, Clone, Debug)]
+pub struct Frustum {
+    /// The left, right, bottom, top, near and far planes.
+    pub planes: [Plane; 6],
+}
+
+impl Frustum {
+    /// Extract the frustum planes from a combined projection and view matrix, as in
+    /// `proj_mat * view_mat`. A point is inside the frustum when its clip space
+    /// coordinates satisfy `-w <= x, y, z <= w`, and each of the six inequalities is a
+    /// plane in world space made from the rows of the matrix. This works for
+    /// perspective and orthographic projections alike.
+    pub fn from_matrix(matrix: &Matrix4<f32>) -> Frustum {
+        // The columns of the matrix are its images of the basis vectors.
+        let c0 = *matrix * Vector4::new(1.0, 0.0, 0.0, 0.0);
+        let c1 = *matrix * Vector4::new(0.0, 1.0, 0.0, 0.0);
+        let c2 = *matrix * Vector4::new(0.0, 0.0, 1.0, 0.0);
+        let c3 = *matrix * Vector4::new(0.0, 0.0, 0.0, 1.0);
+        let row0 = Vector4::new(c0.x, c1.x, c2.x, c3.x);
+        let row1 = Vector4::new(c0.y, c1.y, c2.y, c3.y);
+        let row2 = Vector4::new(c0.z, c1.z, c2.z, c3.z);
+        let row3 = Vector4::new(c0.w, c1.w, c2.w, c3.w);
+
+        Frustum {
+            planes: [
+                Plane::from_coefficients(row3 + row0),
+                Plane::from_coefficients(row3 - row0),
+                Plane::from_coefficients(row3 + row1),
+                Plane::from_coefficients(row3 - row1),
+                Plane::from_coefficients(row3 + row2),
+                Plane::from_coefficients(row3 - row2),
+            ],
+        }
+    }
+
+    /// Determine whether any part of a box might lie inside the frustum. The test
+    /// only rejects a box lying entirely behind one of the planes, so a box near a
+    /// corner of the frustum can pass without being visible. That only costs a
+    /// wasted draw, while rejecting a visible box would make it vanish.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner of the box farthest along the plane normal.
+            let farthest = Vector3::new(
+                if plane.normal.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if plane.normal.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if plane.normal.z >= 0.0 { aabb.max.z } else { aabb.min.z }
+            );
+
+            plane.signed_distance(&farthest) >= 0.0
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cglinalg::Degrees;
+
+
+    const EPSILON: f32 = 1e-4;
+
+    /// A camera at the origin looking down the -z axis with a 90 degree field of
+    /// view, so the side planes meet the plane `z == -d` at `x, y == +-d`.
+    fn perspective() -> Frustum {
+        let proj_mat = Matrix4::from_perspective_fov(Degrees(90.0), 1.0, 0.1, 100.0);
+        Frustum::from_matrix(&proj_mat)
+    }
+
+    fn contains_point(frustum: &Frustum, point: &Vector3<f32>) -> bool {
+        frustum.planes.iter().all(|plane| plane.signed_distance(point) >= 0.0)
+    }
+
+    fn aabb(min: [f32; 3], max: [f32; 3]) -> Aabb {
+        Aabb::new(Vector3::new(min[0], min[1], min[2]), Vector3::new(max[0], max[1], max[2]))
+    }
+
+    #[test]
+    fn test_planes_are_normalized() {
+        for plane in perspective().planes.iter() {
+            assert!((plane.normal.magnitude() - 1.0).abs() < EPSILON);
+        }
+    }
+
+    #[test]
+    fn test_near_and_far_plane_distances() {
+        let frustum = perspective();
+        let near = &frustum.planes[4];
+        let far = &frustum.planes[5];
+
+        assert!(near.signed_distance(&Vector3::new(0.0, 0.0, -0.1)).abs() < EPSILON);
+        assert!(far.signed_distance(&Vector3::new(0.0, 0.0, -100.0)).abs() < 1e-2);
+        assert!((near.signed_distance(&Vector3::new(0.0, 0.0, -1.1)) - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_point_in_front_of_the_camera_is_inside() {
+        assert!(contains_point(&perspective(), &Vector3::new(0.0, 0.0, -10.0)));
+        assert!(contains_point(&perspective(), &Vector3::new(9.0, -9.0, -10.0)));
+    }
+
+    #[test]
+    fn test_point_behind_the_camera_is_outside() {
+        assert!(!contains_point(&perspective(), &Vector3::new(0.0, 0.0, 10.0)));
+    }
+
+    #[test]
+    fn test_point_closer_than_the_near_plane_is_outside() {
+        assert!(!contains_point(&perspective(), &Vector3::new(0.0, 0.0, -0.05)));
+    }
+
+    #[test]
+    fn test_point_beyond_the_far_plane_is_outside() {
+        assert!(!contains_point(&perspective(), &Vector3::new(0.0, 0.0, -200.0)));
+    }
+
+    #[test]
+    fn test_points_past_the_side_planes_are_outside() {
+        let frustum = perspective();
+
+        assert!(!contains_point(&frustum, &Vector3::new(-11.0, 0.0, -10.0)));
+        assert!(!contains_point(&frustum, &Vector3::new(11.0, 0.0, -10.0)));
+        assert!(!contains_point(&frustum, &Vector3::new(0.0, -11.0, -10.0)));
+        assert!(!contains_point(&frustum, &Vector3::new(0.0, 11.0, -10.0)));
+    }
+
+    #[test]
+    fn test_box_inside_the_frustum_intersects() {
+        assert!(perspective().intersects_aabb(&aabb([-1.0, -1.0, -11.0], [1.0, 1.0, -9.0])));
+    }
+
+    #[test]
+    fn test_box_straddling_a_plane_intersects() {
+        assert!(perspective().intersects_aabb(&aabb([-15.0, -1.0, -11.0], [-9.0, 1.0, -9.0])));
+    }
+
+    #[test]
+    fn test_box_surrounding_the_camera_intersects() {
+        assert!(perspective().intersects_aabb(&aabb([-50.0, -50.0, -50.0], [50.0, 50.0, 50.0])));
+    }
+
+    #[test]
+    fn test_box_behind_the_camera_is_culled() {
+        assert!(!perspective().intersects_aabb(&aabb([-1.0, -1.0, 5.0], [1.0, 1.0, 7.0])));
+    }
+
+    #[test]
+    fn test_box_off_to_the_side_is_culled() {
+        assert!(!perspective().intersects_aabb(&aabb([20.0, -1.0, -11.0], [22.0, 1.0, -9.0])));
+    }
+
+    #[test]
+    fn test_view_matrix_moves_the_frustum_with_the_camera() {
+        // The camera sits at z == 10, so the origin is in front of it and z == 20
+        // is behind it.
+        let proj_mat = Matrix4::from_perspective_fov(Degrees(90.0), 1.0, 0.1, 100.0);
+        let view_mat = Matrix4::from_affine_translation(&Vector3::new(0.0, 0.0, -10.0));
+        let frustum = Frustum::from_matrix(&(proj_mat * view_mat));
+
+        assert!(frustum.intersects_aabb(&aabb([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0])));
+        assert!(!frustum.intersects_aabb(&aabb([-1.0, -1.0, 19.0], [1.0, 1.0, 21.0])));
+    }
+
+    #[test]
+    fn test_orthographic_frustum_has_parallel_sides() {
+        let proj_mat = Matrix4::from_orthographic(-2.0, 2.0, -1.0, 1.0, 0.1, 100.0);
+        let frustum = Frustum::from_matrix(&proj_mat);
+
+        assert!(contains_point(&frustum, &Vector3::new(1.9, 0.9, -1.0)));
+        assert!(contains_point(&frustum, &Vector3::new(1.9, 0.9, -99.0)));
+        assert!(!contains_point(&frustum, &Vector3::new(2.1, 0.0, -50.0)));
+        assert!(!contains_point(&frustum, &Vector3::new(0.0, -1.1, -50.0)));
+    }
+
+    #[test]
+    fn test_aabb_from_points() {
+        let points = [[1.0, -2.0, 3.0], [-1.0, 4.0, 0.0], [0.5, 0.0, -5.0]];
+        let result = Aabb::from_points(&points).unwrap();
+
+        assert_eq!(result, aabb([-1.0, -2.0, -5.0], [1.0, 4.0, 3.0]));
+        assert!(Aabb::from_points(&[]).is_none());
+    }
+
+    #[test]
+    fn test_transformed_aabb_follows_the_model_matrix() {
+        let model_mat = Matrix4::from_affine_translation(&Vector3::new(5.0, 0.0, 0.0)) *
+            Matrix4::from_affine_scale(2.0);
+        let result = aabb([-1.0, -1.0, -1.0], [1.0, 1.0, 1.0]).transform(&model_mat);
+        let expected = aabb([3.0, -2.0, -2.0], [7.0, 2.0, 2.0]);
+
+        assert!((result.min - expected.min).magnitude() < EPSILON);
+        assert!((result.max - expected.max).magnitude() < EPSILON);
+    }
+}
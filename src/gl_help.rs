@@ -3,7 +3,6 @@ use crate::gl;
 use crate::gl::types::{
     GLboolean, GLchar, GLenum, GLfloat, GLint, GLubyte, GLuint
 };
-use glfw;
 use glfw::{Context, Glfw};
 
 use std::ffi::{CStr, CString};
@@ -21,6 +20,19 @@ use log::{info, error};
 // 256 Kilobytes.
 const MAX_SHADER_LENGTH: usize = 262144;
 
+// OpenGL extension constants.
+pub const GL_TEXTURE_MAX_ANISOTROPY_EXT: u32 = 0x84FE;
+pub const GL_MAX_TEXTURE_MAX_ANISOTROPY_EXT: u32 = 0x84FF;
+pub const GL_COMPRESSED_RGBA_S3TC_DXT1_EXT: u32 = 0x83F1;
+pub const GL_COMPRESSED_RGBA_S3TC_DXT3_EXT: u32 = 0x83F2;
+pub const GL_COMPRESSED_RGBA_S3TC_DXT5_EXT: u32 = 0x83F3;
+pub const GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT: u32 = 0x8C4D;
+pub const GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT: u32 = 0x8C4E;
+pub const GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT: u32 = 0x8C4F;
+pub const GL_COMPRESSED_RGBA_BPTC_UNORM: u32 = 0x8E8C;
+pub const GL_COMPRESSED_SRGB_ALPHA_BPTC_UNORM: u32 = 0x8E8D;
+pub const GL_COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT: u32 = 0x8E8F;
+
 
 #[inline]
 pub fn glubyte_ptr_to_string(cstr: *const GLubyte) -> String {
@@ -44,7 +56,7 @@ struct GLParameters {
 impl fmt::Display for GLParameters {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         writeln!(f, "GL Context Params:").unwrap();
-        for &(ref param, ref value) in self.params.iter() {
+        for (param, value) in self.params.iter() {
             writeln!(f, "{} = {}", param, value).unwrap();
         }
         writeln!(f)
@@ -89,19 +101,19 @@ fn gl_params() -> GLParameters {
         unsafe { 
             gl::GetIntegerv(params[i], &mut v);
         }
-        vec.push((format!("{}", names[i]), format!("{}", v)));
+        vec.push((names[i].to_string(), v.to_string()));
     }
     // others
     let mut v: [GLint; 2] = [0; 2];
     unsafe {    
         gl::GetIntegerv(params[10], &mut v[0]);
     }
-    vec.push((format!("{}", names[10]), format!("{} {}", v[0], v[1])));
+    vec.push((names[10].to_string(), format!("{} {}", v[0], v[1])));
     let mut s = 0;
     unsafe {
         gl::GetBooleanv(params[11], &mut s);
     }
-    vec.push((format!("{}", names[11]), format!("{}", s as usize)));
+    vec.push((names[11].to_string(), (s as usize).to_string()));
 
     GLParameters {
         params: vec,
@@ -111,19 +123,19 @@ fn gl_params() -> GLParameters {
 /// Helper function to convert GLSL types to storage sizes
 fn type_size(gl_type: GLenum) -> usize {
     match gl_type {
-        gl::FLOAT             => 1 * mem::size_of::<GLfloat>(),
+        gl::FLOAT             =>     mem::size_of::<GLfloat>(),
         gl::FLOAT_VEC2        => 2 * mem::size_of::<GLfloat>(),
         gl::FLOAT_VEC3        => 3 * mem::size_of::<GLfloat>(),
         gl::FLOAT_VEC4        => 4 * mem::size_of::<GLfloat>(),
-        gl::INT               => 1 * mem::size_of::<GLint>(),
+        gl::INT               =>     mem::size_of::<GLint>(),
         gl::INT_VEC2          => 2 * mem::size_of::<GLint>(),
         gl::INT_VEC3          => 3 * mem::size_of::<GLint>(),
         gl::INT_VEC4          => 4 * mem::size_of::<GLint>(),
-        gl::UNSIGNED_INT      => 1 * mem::size_of::<GLuint>(),
+        gl::UNSIGNED_INT      =>     mem::size_of::<GLuint>(),
         gl::UNSIGNED_INT_VEC2 => 2 * mem::size_of::<GLuint>(),
         gl::UNSIGNED_INT_VEC3 => 3 * mem::size_of::<GLuint>(),
         gl::UNSIGNED_INT_VEC4 => 4 * mem::size_of::<GLuint>(),
-        gl::BOOL              => 1 * mem::size_of::<GLboolean>(),
+        gl::BOOL              =>     mem::size_of::<GLboolean>(),
         gl::BOOL_VEC2         => 2 * mem::size_of::<GLboolean>(),
         gl::BOOL_VEC3         => 3 * mem::size_of::<GLboolean>(),
         gl::BOOL_VEC4         => 4 * mem::size_of::<GLboolean>(),
@@ -140,6 +152,108 @@ fn type_size(gl_type: GLenum) -> usize {
     }
 }
 
+/// Determine whether the current OpenGL context is at least version `major.minor`.
+pub fn has_version(major: i32, minor: i32) -> bool {
+    let mut context_major = 0;
+    let mut context_minor = 0;
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut context_major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut context_minor);
+    }
+
+    (context_major, context_minor) >= (major, minor)
+}
+
+/// Determine whether the current OpenGL context supports the extension `name`.
+pub fn has_extension(name: &str) -> bool {
+    let mut count = 0;
+    unsafe {
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+    }
+    for i in 0..count {
+        let extension = glubyte_ptr_to_string(unsafe { gl::GetStringi(gl::EXTENSIONS, i as GLuint) });
+        if extension == name {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// The graphics settings requested by the application. What the application
+/// actually gets depends on what the OpenGL context supports, so the settings in
+/// effect are recorded in the `GLState` after the context starts.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GraphicsSettings {
+    /// The number of samples per pixel for multisample anti-aliasing. Zero disables MSAA.
+    pub msaa_samples: u32,
+    /// The anisotropic filtering level for textures. A level of one disables it.
+    pub anisotropy: f32,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> GraphicsSettings {
+        GraphicsSettings {
+            msaa_samples: 4,
+            anisotropy: 16.0,
+        }
+    }
+}
+
+/// Determine the number of MSAA samples the default framebuffer actually got for the
+/// sample count the window was created with, and switch multisampling on or off to match.
+fn configure_msaa(requested_samples: u32) -> u32 {
+    let mut max_samples = 0;
+    let mut samples = 0;
+    unsafe {
+        gl::GetIntegerv(gl::MAX_SAMPLES, &mut max_samples);
+        gl::GetIntegerv(gl::SAMPLES, &mut samples);
+    }
+    let samples = samples.max(0) as u32;
+    if samples != requested_samples {
+        info!(
+            "Requested {}x MSAA but the context provides {}x (GL_MAX_SAMPLES = {}).",
+            requested_samples, samples, max_samples
+        );
+    }
+
+    unsafe {
+        if samples > 0 {
+            gl::Enable(gl::MULTISAMPLE);
+        } else {
+            gl::Disable(gl::MULTISAMPLE);
+        }
+    }
+
+    samples
+}
+
+/// Determine the anisotropic filtering level to apply to textures. The requested level
+/// is clamped to what the driver supports, and anisotropic filtering is switched off
+/// when neither anisotropic filtering extension is available.
+fn configure_anisotropy(settings: &GraphicsSettings) -> f32 {
+    let supported = has_extension("GL_EXT_texture_filter_anisotropic")
+        || has_extension("GL_ARB_texture_filter_anisotropic");
+    if !supported {
+        info!("Anisotropic filtering is not supported by this context. Disabling it.");
+        return 1.0;
+    }
+
+    let mut max_anisotropy = 1.0;
+    unsafe {
+        gl::GetFloatv(GL_MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut max_anisotropy);
+    }
+    let anisotropy = settings.anisotropy.max(1.0).min(max_anisotropy);
+    if anisotropy != settings.anisotropy {
+        info!(
+            "Requested anisotropy level {} but the context supports at most {}. Using {}.",
+            settings.anisotropy, max_anisotropy, anisotropy
+        );
+    }
+
+    anisotropy
+}
+
 /// A record for storing all the OpenGL state needed on the application side
 /// of the graphics application in order to manage OpenGL and GLFW.
 pub struct GLState {
@@ -152,14 +266,21 @@ pub struct GLState {
     pub running_time_seconds: f64,
     pub framerate_time_seconds: f64,
     pub frame_count: u32,
+    pub msaa_samples: u32,
+    pub anisotropy: f32,
+    /// The position and size of the window before it went fullscreen, or `None`
+    /// while the window is not fullscreen.
+    pub windowed_placement: Option<(i32, i32, u32, u32)>,
 }
 
 #[cfg(target_os = "macos")]
 fn __init_glfw() -> Glfw {
-    // Start a GL context and OS window using the GLFW helper library.
-    let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+    // Start a GL context and OS window using the GLFW helper library. Errors are
+    // logged rather than fatal, so a window the driver refuses can be retried with
+    // fewer MSAA samples.
+    let mut glfw = glfw::init(glfw::LOG_ERRORS).unwrap();
 
-    glfw.window_hint(glfw::WindowHint::Samples(Some(4)));
+    glfw.window_hint(glfw::WindowHint::StencilBits(Some(8)));
 
     glfw.window_hint(glfw::WindowHint::ContextVersionMajor(3));
     glfw.window_hint(glfw::WindowHint::ContextVersionMinor(3));
@@ -172,10 +293,12 @@ fn __init_glfw() -> Glfw {
 
 #[cfg(target_os = "windows")]
 fn __init_glfw() -> Glfw {
-    // Start a GL context and OS window using the GLFW helper library.
-    let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+    // Start a GL context and OS window using the GLFW helper library. Errors are
+    // logged rather than fatal, so a window the driver refuses can be retried with
+    // fewer MSAA samples.
+    let mut glfw = glfw::init(glfw::LOG_ERRORS).unwrap();
 
-    glfw.window_hint(glfw::WindowHint::Samples(Some(4)));
+    glfw.window_hint(glfw::WindowHint::StencilBits(Some(8)));
 
     glfw.window_hint(glfw::WindowHint::ContextVersionMajor(3));
     glfw.window_hint(glfw::WindowHint::ContextVersionMinor(3));
@@ -187,37 +310,57 @@ fn __init_glfw() -> Glfw {
 
 #[cfg(not(any(target_os = "macos", target_os = "windows")))]
 fn __init_glfw() -> Glfw {
-    // Start a GL context and OS window using the GLFW helper library.
-    let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+    // Start a GL context and OS window using the GLFW helper library. Errors are
+    // logged rather than fatal, so a window the driver refuses can be retried with
+    // fewer MSAA samples.
+    let mut glfw = glfw::init(glfw::LOG_ERRORS).unwrap();
 
-    glfw.window_hint(glfw::WindowHint::Samples(Some(4)));
+    glfw.window_hint(glfw::WindowHint::StencilBits(Some(8)));
 
     glfw
 }
 
 /// Initialize a new OpenGL context and start a new GLFW window. 
-pub fn start_gl(width: u32, height: u32) -> Result<GLState, String> {
+pub fn start_gl(width: u32, height: u32, settings: &GraphicsSettings) -> Result<GLState, String> {
     // Start GL context and O/S window using the GLFW helper library.
     info!("Starting GLFW");
     info!("Using GLFW version {}", glfw::get_version_string());
 
     // Start a GL context and OS window using the GLFW helper library.
-    let glfw = __init_glfw();
+    let mut glfw = __init_glfw();
 
     info!("Started GLFW successfully");
-    let maybe_glfw_window = glfw.create_window(
-        width, height, &format!("Triforces DEMO @ {} fps", 0.0), glfw::WindowMode::Windowed
-    );
-    let (mut window, events) = match maybe_glfw_window {
-        Some(tuple) => tuple,
-        None => {
-            error!("Failed to create GLFW window");
-            return Err(String::new());
+    // The glTF scene writes linear colors, which the framebuffer encodes into sRGB.
+    glfw.window_hint(glfw::WindowHint::SRgbCapable(true));
+    // Not every driver offers every sample count, so halve the count until the
+    // driver accepts a window, falling back to no MSAA at all.
+    let mut requested_samples = settings.msaa_samples;
+    let (mut window, events) = loop {
+        glfw.window_hint(glfw::WindowHint::Samples(Some(requested_samples)));
+        let maybe_glfw_window = glfw.create_window(
+            width, height, &format!("Triforces DEMO @ {} fps", 0.0), glfw::WindowMode::Windowed
+        );
+        match maybe_glfw_window {
+            Some(tuple) => break tuple,
+            None if requested_samples > 0 => {
+                info!(
+                    "Failed to create a GLFW window with {}x MSAA. Retrying with {}x MSAA.",
+                    requested_samples, requested_samples / 2
+                );
+                requested_samples /= 2;
+            }
+            None => {
+                error!("Failed to create GLFW window");
+                return Err(String::new());
+            }
         }
     };
 
     window.make_current();
     window.set_key_polling(true);
+    window.set_cursor_pos_polling(true);
+    window.set_mouse_button_polling(true);
+    window.set_scroll_polling(true);
     window.set_size_polling(true);
     window.set_refresh_polling(true);
     window.set_size_polling(true);
@@ -235,6 +378,10 @@ pub fn start_gl(width: u32, height: u32) -> Result<GLState, String> {
     info!("OpenGL version supported: {}", version);
     info!("{}", gl_params());
 
+    let msaa_samples = configure_msaa(requested_samples);
+    let anisotropy = configure_anisotropy(settings);
+    info!("Using {}x MSAA and anisotropic filtering level {}.", msaa_samples, anisotropy);
+
     Ok(GLState {
         glfw: glfw, 
         window: window, 
@@ -245,6 +392,9 @@ pub fn start_gl(width: u32, height: u32) -> Result<GLState, String> {
         running_time_seconds: 0.0,
         framerate_time_seconds: 0.0,
         frame_count: 0,
+        msaa_samples: msaa_samples,
+        anisotropy: anisotropy,
+        windowed_placement: None,
     })
 }
 
@@ -276,6 +426,42 @@ pub fn update_fps_counter(context: &mut GLState) {
     context.frame_count += 1;
 }
 
+///
+/// Switch the window between windowed mode and fullscreen mode on the primary monitor.
+/// Leaving fullscreen mode puts the window back where it was. This returns whether
+/// the window is now fullscreen.
+///
+pub fn toggle_fullscreen(context: &mut GLState) -> bool {
+    let window = &mut context.window;
+    if let Some((x, y, width, height)) = context.windowed_placement.take() {
+        window.set_monitor(glfw::WindowMode::Windowed, x, y, width, height, None);
+        return false;
+    }
+
+    let (x, y) = window.get_pos();
+    let (width, height) = window.get_size();
+    let fullscreen = context.glfw.with_primary_monitor(|_, monitor| {
+        let monitor = match monitor {
+            Some(monitor) => monitor,
+            None => return false,
+        };
+        match monitor.get_video_mode() {
+            Some(mode) => {
+                window.set_monitor(
+                    glfw::WindowMode::FullScreen(monitor), 0, 0, mode.width, mode.height, Some(mode.refresh_rate)
+                );
+                true
+            }
+            None => false,
+        }
+    });
+    if fullscreen {
+        context.windowed_placement = Some((x, y, width as u32, height as u32));
+    }
+
+    fullscreen
+}
+
 #[derive(Clone, Debug)]
 pub enum ShaderCompilationError {
     ShaderNotFound(String),
@@ -288,19 +474,19 @@ pub enum ShaderCompilationError {
 impl fmt::Display for ShaderCompilationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            &ShaderCompilationError::ShaderNotFound(ref file_name) => {
-                write!(f, "Could not open the shader file for reading: {}", file_name.to_string())
+            ShaderCompilationError::ShaderNotFound(file_name) => {
+                write!(f, "Could not open the shader file for reading: {}", file_name)
             }
-            &ShaderCompilationError::CouldNotParseShader(ref file_name) => {
-                write!(f, "The shader file exists, but there was an error in reading it: {}", file_name.to_string())
+            ShaderCompilationError::CouldNotParseShader(file_name) => {
+                write!(f, "The shader file exists, but there was an error in reading it: {}", file_name)
             }
-            &ShaderCompilationError::CouldNotCompileShader(ref file_name) => {
-                write!(f, "The shader could not be compiled: {}", file_name.to_string())
+            ShaderCompilationError::CouldNotCompileShader(file_name) => {
+                write!(f, "The shader could not be compiled: {}", file_name)
             }
-            &ShaderCompilationError::CouldNotLinkShader => {
+            ShaderCompilationError::CouldNotLinkShader => {
                 write!(f, "The shader program could not be linked.")
             }
-            &ShaderCompilationError::ShaderValidationFailed => {
+            ShaderCompilationError::ShaderValidationFailed => {
                 write!(f, "Shader validation failed.")
             }
         }
@@ -347,14 +533,14 @@ pub fn shader_info_log(shader_index: GLuint) -> ShaderLog {
     unsafe {
         gl::GetShaderiv(shader_index, gl::INFO_LOG_LENGTH, &mut actual_length);
     }
-    let mut raw_log = vec![0 as i8; actual_length as usize];
+    let mut raw_log = vec![0_i8; actual_length as usize];
     unsafe {
         gl::GetShaderInfoLog(shader_index, raw_log.len() as i32, &mut actual_length, &mut raw_log[0]);
     }
     
     let mut log = String::new();
-    for i in 0..actual_length as usize {
-        log.push(raw_log[i] as u8 as char);
+    for &c in raw_log.iter().take(actual_length as usize) {
+        log.push(c as u8 as char);
     }
 
     ShaderLog { index: shader_index, log: log }
@@ -432,14 +618,14 @@ pub fn program_info_log(index: GLuint) -> ProgramLog {
     unsafe {
         gl::GetProgramiv(index, gl::INFO_LOG_LENGTH, &mut actual_length);
     }
-    let mut raw_log = vec![0 as i8; actual_length as usize];
+    let mut raw_log = vec![0_i8; actual_length as usize];
     unsafe {
         gl::GetProgramInfoLog(index, raw_log.len() as i32, &mut actual_length, &mut raw_log[0]);
     }
 
     let mut log = String::new();
-    for i in 0..actual_length as usize {
-        log.push(raw_log[i] as u8 as char);
+    for &c in raw_log.iter().take(actual_length as usize) {
+        log.push(c as u8 as char);
     }
 
     ProgramLog { index: index, log: log }
@@ -0,0 +1,790 @@
+//! Import of glTF 2.0 scenes from `.gltf` and `.glb` files. The importer reads the
+//! node hierarchy of the default scene along with the meshes, materials, cameras,
+//! punctual lights and animations the nodes refer to, and converts them into the
+//! types the rest of the demo works with.
+use crate::animation::{
+    Animation,
+    Interpolation,
+    Keyframe,
+    PlaybackMode,
+    Track,
+};
+use crate::camera::{
+    Camera,
+    Projection,
+};
+use crate::component::Transform;
+use crate::lights::{
+    PointLight,
+    SpotCone,
+};
+use cglinalg::{
+    Degrees,
+    Matrix4,
+    Quaternion,
+    Vector3,
+    Vector4,
+};
+use gltf::animation::util::ReadOutputs;
+use gltf::image::Format;
+use gltf::khr_lights_punctual::Kind;
+use gltf::mesh::Mode;
+use log::{info, warn};
+use mini_obj::ObjMesh;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+
+#[derive(Clone, Debug)]
+pub enum GltfError {
+    CouldNotImport(String, String),
+    NoScene(String),
+    InvalidMesh(String, String),
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfError::CouldNotImport(path, error) => {
+                write!(f, "Could not import the glTF file {}: {}", path, error)
+            }
+            GltfError::NoScene(path) => {
+                write!(f, "The glTF file {} does not contain a scene.", path)
+            }
+            GltfError::InvalidMesh(path, error) => {
+                write!(f, "The glTF file {} contains an invalid mesh: {}", path, error)
+            }
+        }
+    }
+}
+
+/// One drawable piece of a glTF mesh, unrolled into a triangle list so it has the
+/// same layout as the meshes loaded from OBJ files.
+pub struct GltfPrimitive {
+    pub mesh: ObjMesh,
+    pub material: Option<usize>,
+}
+
+/// A metallic-roughness material. The demo shades everything with Blinn-Phong, so
+/// only the base color and the face culling make it to the screen; the remaining
+/// parameters are kept for completeness.
+#[derive(Clone, Debug)]
+pub struct GltfMaterial {
+    pub name: String,
+    pub base_color_factor: [f32; 4],
+    pub base_color_image: Option<usize>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    pub emissive_factor: [f32; 3],
+    pub double_sided: bool,
+}
+
+/// An image decoded into tightly packed RGB8 or RGBA8 texels, row by row.
+pub struct GltfImage {
+    pub width: usize,
+    pub height: usize,
+    /// Whether the texels carry an alpha channel after the three colour channels.
+    pub has_alpha: bool,
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum GltfProjection {
+    Perspective {
+        yfov: f32,
+        aspect: Option<f32>,
+        znear: f32,
+        zfar: Option<f32>,
+    },
+    Orthographic {
+        xmag: f32,
+        ymag: f32,
+        znear: f32,
+        zfar: f32,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct GltfCamera {
+    pub name: String,
+    pub projection: GltfProjection,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum GltfLightKind {
+    Directional,
+    Point,
+    Spot {
+        inner_cone_angle: f32,
+        outer_cone_angle: f32,
+    },
+}
+
+/// A light from the `KHR_lights_punctual` extension.
+#[derive(Clone, Debug)]
+pub struct GltfLight {
+    pub name: String,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub range: Option<f32>,
+    pub kind: GltfLightKind,
+}
+
+/// A node of the scene. Nodes are stored with every parent ahead of its children,
+/// and `parent` indexes into the same list.
+pub struct GltfNode {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub transform: Transform,
+    pub world_mat: Matrix4<f32>,
+    pub world_rotation: Quaternion<f32>,
+    pub primitives: Vec<usize>,
+    pub camera: Option<usize>,
+    pub light: Option<usize>,
+}
+
+impl GltfNode {
+    /// The position of the node in world space.
+    #[inline]
+    pub fn world_position(&self) -> Vector3<f32> {
+        (self.world_mat * Vector4::new(0.0, 0.0, 0.0, 1.0)).contract()
+    }
+}
+
+/// The animation of a single node, collected from all the channels of a glTF
+/// animation that target that node.
+pub struct GltfAnimation {
+    pub name: String,
+    pub node: usize,
+    pub animation: Animation,
+}
+
+pub struct GltfScene {
+    pub nodes: Vec<GltfNode>,
+    pub primitives: Vec<GltfPrimitive>,
+    pub materials: Vec<GltfMaterial>,
+    /// The decoded images, indexed by glTF image. This covers image files next to
+    /// the glTF file, images embedded in data URIs and images in `.glb` buffers.
+    pub images: Vec<GltfImage>,
+    pub cameras: Vec<GltfCamera>,
+    pub lights: Vec<GltfLight>,
+    pub animations: Vec<GltfAnimation>,
+}
+
+impl GltfScene {
+    /// Build a camera from a node carrying a glTF camera. The speeds of the camera
+    /// and any projection parameters the file leaves out come from `default`.
+    pub fn to_camera(&self, node: &GltfNode, default: &Camera) -> Option<Camera> {
+        let camera = &self.cameras[node.camera?];
+        let (near, far, fov, aspect) = match camera.projection {
+            GltfProjection::Perspective { yfov, aspect, znear, zfar } => {
+                let fov = Degrees(yfov.to_degrees());
+                (znear, zfar.unwrap_or(default.far), fov, aspect.unwrap_or(default.aspect))
+            }
+            GltfProjection::Orthographic { xmag, ymag, znear, zfar } => {
+                (znear, zfar, default.fov, xmag / ymag)
+            }
+        };
+
+        // A glTF camera looks down its local -z axis with +y up.
+        let axis = node.world_rotation;
+        let rot_mat_inv = Matrix4::from(axis);
+        let fwd = rot_mat_inv * Vector4::new(0.0, 0.0, -1.0, 0.0);
+        let rgt = rot_mat_inv * Vector4::new(1.0, 0.0,  0.0, 0.0);
+        let up  = rot_mat_inv * Vector4::new(0.0, 1.0,  0.0, 0.0);
+        let pos = node.world_position();
+
+        let mut result = Camera::new(
+            near, far, fov, aspect, default.speed, default.yaw_speed, pos, fwd, rgt, up, axis
+        );
+        result.rot_mat = rot_mat_inv.inverse().unwrap();
+        result.view_mat = result.rot_mat * result.trans_mat;
+
+        // The orthographic view of a glTF camera is `2 * ymag` tall, which sets the
+        // distance at which it matches the field of view.
+        if let GltfProjection::Orthographic { ymag, .. } = camera.projection {
+            result.projection = Projection::Orthographic;
+            result.focus_distance = ymag / f32::tan(fov.0.to_radians() / 2.0);
+            result.update_projection();
+        }
+        info!("Using the camera `{}` of the node `{}`.", camera.name, node.name);
+
+        Some(result)
+    }
+
+    /// Build a point light from a node carrying a glTF light. Spot lights shine down
+    /// the local -z axis of their node. Directional lights have no position, so they
+    /// are skipped. The shaders have no inverse square falloff, so the intensity is a
+    /// plain multiplier on the color of the light.
+    pub fn to_point_light(&self, node: &GltfNode) -> Option<PointLight> {
+        let light = &self.lights[node.light?];
+        let spot = match light.kind {
+            GltfLightKind::Directional => {
+                info!("Skipping the directional light `{}`.", light.name);
+                return None;
+            }
+            GltfLightKind::Point => None,
+            GltfLightKind::Spot { inner_cone_angle, outer_cone_angle } => {
+                let direction = Matrix4::from(node.world_rotation) * Vector4::new(0.0, 0.0, -1.0, 0.0);
+                Some(SpotCone {
+                    direction: direction.contract(),
+                    inner_cone_angle: inner_cone_angle,
+                    outer_cone_angle: outer_cone_angle,
+                })
+            }
+        };
+
+        let color = to_vector(light.color);
+        let ambient = color * 0.3;
+        let diffuse = color * 0.7;
+        let specular = color;
+        let specular_exponent = 100.0;
+
+        let mut result = PointLight::new(ambient, diffuse, specular, specular_exponent, node.world_position());
+        result.intensity = light.intensity;
+        result.range = light.range;
+        result.spot = spot;
+        info!("Using the light `{}` of the node `{}`.", light.name, node.name);
+
+        Some(result)
+    }
+}
+
+#[inline]
+fn to_quaternion(q: [f32; 4]) -> Quaternion<f32> {
+    // glTF stores quaternions with the scalar part last.
+    Quaternion::new(q[3], q[0], q[1], q[2])
+}
+
+#[inline]
+fn to_vector(v: [f32; 3]) -> Vector3<f32> {
+    Vector3::new(v[0], v[1], v[2])
+}
+
+fn to_transform(node: &gltf::Node) -> Transform {
+    let (translation, rotation, scale) = node.transform().decomposed();
+
+    Transform::new(to_vector(translation), to_quaternion(rotation), to_vector(scale))
+}
+
+fn cross(u: [f32; 3], v: [f32; 3]) -> [f32; 3] {
+    [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]
+}
+
+fn face_normal(p0: [f32; 3], p1: [f32; 3], p2: [f32; 3]) -> [f32; 3] {
+    let u = [p1[0] - p0[0], p1[1] - p0[1], p1[2] - p0[2]];
+    let v = [p2[0] - p0[0], p2[1] - p0[1], p2[2] - p0[2]];
+    let n = cross(u, v);
+    let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+    if length > 0.0 {
+        [n[0] / length, n[1] / length, n[2] / length]
+    } else {
+        [0.0, 0.0, 1.0]
+    }
+}
+
+/// Unroll an indexed glTF primitive into a triangle list. Missing texture coordinates
+/// default to zero, and missing normals are replaced with face normals. Primitives
+/// that are not triangle lists, or that have no positions, are skipped. This returns
+/// an error if an index points past the end of the vertex attributes.
+fn load_primitive(
+    primitive: &gltf::Primitive, buffers: &[gltf::buffer::Data]) -> Result<Option<GltfPrimitive>, String> {
+
+    if primitive.mode() != Mode::Triangles {
+        info!("Skipping a primitive drawn with {:?}. Only triangle lists are supported.", primitive.mode());
+        return Ok(None);
+    }
+
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()].0[..]));
+    let positions: Vec<[f32; 3]> = match reader.read_positions() {
+        Some(iter) => iter.collect(),
+        None => return Ok(None),
+    };
+    let normals: Option<Vec<[f32; 3]>> = reader.read_normals().map(|iter| iter.collect());
+    let tex_coords: Option<Vec<[f32; 2]>> = reader.read_tex_coords(0).map(|iter| {
+        iter.into_f32().collect()
+    });
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(iter) => iter.into_u32().collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+
+    // Every attribute of a primitive has one element per vertex, so checking the
+    // indices against the shortest attribute keeps every lookup below in bounds.
+    let vertex_count = usize::min(
+        positions.len(),
+        usize::min(
+            normals.as_ref().map_or(usize::MAX, |normals| normals.len()),
+            tex_coords.as_ref().map_or(usize::MAX, |tex_coords| tex_coords.len())
+        )
+    );
+    if let Some(&index) = indices.iter().find(|&&index| index as usize >= vertex_count) {
+        return Err(format!("Vertex index {} is out of range for {} vertices.", index, vertex_count));
+    }
+
+    let mut points = vec![];
+    let mut mesh_tex_coords = vec![];
+    let mut mesh_normals = vec![];
+    for triangle in indices.chunks(3).filter(|triangle| triangle.len() == 3) {
+        let face = face_normal(
+            positions[triangle[0] as usize],
+            positions[triangle[1] as usize],
+            positions[triangle[2] as usize]
+        );
+        for &index in triangle.iter() {
+            let index = index as usize;
+            points.push(positions[index]);
+            // glTF puts the origin of texture space at the top left of the image,
+            // while OBJ puts it at the bottom left.
+            mesh_tex_coords.push(match tex_coords {
+                Some(ref tex_coords) => [tex_coords[index][0], 1.0 - tex_coords[index][1]],
+                None => [0.0, 0.0],
+            });
+            mesh_normals.push(match normals {
+                Some(ref normals) => normals[index],
+                None => face,
+            });
+        }
+    }
+
+    Ok(Some(GltfPrimitive {
+        mesh: ObjMesh::new(points, mesh_tex_coords, mesh_normals),
+        material: primitive.material().index(),
+    }))
+}
+
+fn load_material(material: &gltf::Material) -> GltfMaterial {
+    let pbr = material.pbr_metallic_roughness();
+
+    GltfMaterial {
+        name: String::from(material.name().unwrap_or("")),
+        base_color_factor: pbr.base_color_factor(),
+        base_color_image: pbr.base_color_texture().map(|info| info.texture().source().index()),
+        metallic_factor: pbr.metallic_factor(),
+        roughness_factor: pbr.roughness_factor(),
+        emissive_factor: material.emissive_factor(),
+        double_sided: material.double_sided(),
+    }
+}
+
+/// Expand an image decoded by the glTF importer into RGB8, or RGBA8 when it has an
+/// alpha channel. Single and dual channel images are greyscale, with alpha in the
+/// second channel, and sixteen bit channels keep their high byte.
+fn load_image(data: &gltf::image::Data) -> GltfImage {
+    let (channels, bytes_per_channel, bgr) = match data.format {
+        Format::R8 => (1, 1, false),
+        Format::R8G8 => (2, 1, false),
+        Format::R8G8B8 => (3, 1, false),
+        Format::R8G8B8A8 => (4, 1, false),
+        Format::B8G8R8 => (3, 1, true),
+        Format::B8G8R8A8 => (4, 1, true),
+        Format::R16 => (1, 2, false),
+        Format::R16G16 => (2, 2, false),
+        Format::R16G16B16 => (3, 2, false),
+        Format::R16G16B16A16 => (4, 2, false),
+    };
+    let channel = |texel: &[u8], c: usize| -> u8 {
+        if bytes_per_channel == 1 {
+            texel[c]
+        } else {
+            (u16::from_ne_bytes([texel[2 * c], texel[2 * c + 1]]) >> 8) as u8
+        }
+    };
+
+    let has_alpha = channels == 2 || channels == 4;
+    let texel_size = if has_alpha { 4 } else { 3 };
+    let mut pixels = Vec::with_capacity(texel_size * data.width as usize * data.height as usize);
+    for texel in data.pixels.chunks(channels * bytes_per_channel) {
+        let mut rgba = match channels {
+            1 => [channel(texel, 0), channel(texel, 0), channel(texel, 0), 255],
+            2 => [channel(texel, 0), channel(texel, 0), channel(texel, 0), channel(texel, 1)],
+            3 => [channel(texel, 0), channel(texel, 1), channel(texel, 2), 255],
+            _ => [channel(texel, 0), channel(texel, 1), channel(texel, 2), channel(texel, 3)],
+        };
+        if bgr {
+            rgba.swap(0, 2);
+        }
+        pixels.extend_from_slice(&rgba[..texel_size]);
+    }
+
+    GltfImage {
+        width: data.width as usize,
+        height: data.height as usize,
+        has_alpha: has_alpha,
+        pixels: pixels,
+    }
+}
+
+fn load_camera(camera: &gltf::Camera) -> GltfCamera {
+    let projection = match camera.projection() {
+        gltf::camera::Projection::Perspective(perspective) => GltfProjection::Perspective {
+            yfov: perspective.yfov(),
+            aspect: perspective.aspect_ratio(),
+            znear: perspective.znear(),
+            zfar: perspective.zfar(),
+        },
+        gltf::camera::Projection::Orthographic(orthographic) => GltfProjection::Orthographic {
+            xmag: orthographic.xmag(),
+            ymag: orthographic.ymag(),
+            znear: orthographic.znear(),
+            zfar: orthographic.zfar(),
+        },
+    };
+
+    GltfCamera {
+        name: String::from(camera.name().unwrap_or("")),
+        projection: projection,
+    }
+}
+
+fn load_light(light: &gltf::khr_lights_punctual::Light) -> GltfLight {
+    let kind = match light.kind() {
+        Kind::Directional => GltfLightKind::Directional,
+        Kind::Point => GltfLightKind::Point,
+        Kind::Spot { inner_cone_angle, outer_cone_angle } => GltfLightKind::Spot {
+            inner_cone_angle: inner_cone_angle,
+            outer_cone_angle: outer_cone_angle,
+        },
+    };
+
+    GltfLight {
+        name: String::from(light.name().unwrap_or("")),
+        color: light.color(),
+        intensity: light.intensity(),
+        range: light.range(),
+        kind: kind,
+    }
+}
+
+/// Gather the keyframes of an animation channel. Cubic spline channels store an
+/// in-tangent, a value and an out-tangent per keyframe; the tangents are dropped
+/// and the values eased between instead.
+fn load_keyframes<T: Copy>(
+    inputs: &[f32], outputs: Vec<T>, interpolation: gltf::animation::Interpolation) -> Vec<Keyframe<T>> {
+
+    let values: Vec<T> = match interpolation {
+        gltf::animation::Interpolation::CubicSpline => {
+            outputs.chunks(3).filter(|chunk| chunk.len() == 3).map(|chunk| chunk[1]).collect()
+        }
+        _ => outputs,
+    };
+
+    inputs.iter().zip(values).map(|(&time, value)| Keyframe::new(time, value)).collect()
+}
+
+/// The tracks of a glTF animation that target one node, gathered channel by channel
+/// before they are assembled into an animation.
+#[derive(Default)]
+struct NodeTracks {
+    translation: Option<Track<Vector3<f32>>>,
+    rotation: Option<Track<Quaternion<f32>>>,
+    scale: Option<Track<Vector3<f32>>>,
+}
+
+fn load_animations(
+    document: &gltf::Document, buffers: &[gltf::buffer::Data],
+    node_map: &HashMap<usize, usize>) -> Vec<GltfAnimation> {
+
+    let mut animations = vec![];
+    for gltf_animation in document.animations() {
+        let name = gltf_animation.name().unwrap_or("");
+        let mut by_node: HashMap<usize, NodeTracks> = HashMap::new();
+        for channel in gltf_animation.channels() {
+            let node = match node_map.get(&channel.target().node().index()) {
+                Some(&node) => node,
+                None => continue,
+            };
+            let interpolation = channel.sampler().interpolation();
+            let track_interpolation = match interpolation {
+                gltf::animation::Interpolation::Step => Interpolation::Step,
+                gltf::animation::Interpolation::Linear => Interpolation::Linear,
+                gltf::animation::Interpolation::CubicSpline => Interpolation::CubicEase,
+            };
+
+            let reader = channel.reader(|buffer| Some(&buffers[buffer.index()].0[..]));
+            let inputs: Vec<f32> = match reader.read_inputs() {
+                Some(iter) => iter.collect(),
+                None => continue,
+            };
+            if inputs.is_empty() {
+                continue;
+            }
+            let tracks = by_node.entry(node).or_default();
+            match reader.read_outputs() {
+                Some(ReadOutputs::Translations(iter)) => {
+                    let outputs = iter.map(to_vector).collect();
+                    let keyframes = load_keyframes(&inputs, outputs, interpolation);
+                    match Track::new(keyframes, track_interpolation) {
+                        Ok(track) => tracks.translation = Some(track),
+                        Err(e) => warn!("Skipping a channel of the animation {}. {}", name, e),
+                    }
+                }
+                Some(ReadOutputs::Rotations(iter)) => {
+                    let outputs = iter.into_f32().map(to_quaternion).collect();
+                    let keyframes = load_keyframes(&inputs, outputs, interpolation);
+                    match Track::new(keyframes, track_interpolation) {
+                        Ok(track) => tracks.rotation = Some(track),
+                        Err(e) => warn!("Skipping a channel of the animation {}. {}", name, e),
+                    }
+                }
+                Some(ReadOutputs::Scales(iter)) => {
+                    let outputs = iter.map(to_vector).collect();
+                    let keyframes = load_keyframes(&inputs, outputs, interpolation);
+                    match Track::new(keyframes, track_interpolation) {
+                        Ok(track) => tracks.scale = Some(track),
+                        Err(e) => warn!("Skipping a channel of the animation {}. {}", name, e),
+                    }
+                }
+                Some(ReadOutputs::MorphTargetWeights(_)) => {
+                    info!("Skipping the morph target channel of the animation {}.", name);
+                }
+                None => {}
+            }
+        }
+
+        for (node, tracks) in by_node.into_iter() {
+            let mut animation = Animation::new(PlaybackMode::Loop);
+            let mut has_tracks = false;
+            if let Some(track) = tracks.translation {
+                animation = animation.with_translation(track);
+                has_tracks = true;
+            }
+            if let Some(track) = tracks.rotation {
+                animation = animation.with_rotation(track);
+                has_tracks = true;
+            }
+            if let Some(track) = tracks.scale {
+                animation = animation.with_scale(track);
+                has_tracks = true;
+            }
+            if has_tracks {
+                animations.push(GltfAnimation {
+                    name: String::from(name),
+                    node: node,
+                    animation: animation,
+                });
+            }
+        }
+    }
+
+    animations
+}
+
+/// Walk the node hierarchy depth first, so that every parent lands in the node list
+/// ahead of its children.
+fn load_node(
+    node: &gltf::Node, parent: Option<usize>, primitive_map: &HashMap<usize, Vec<usize>>,
+    nodes: &mut Vec<GltfNode>, node_map: &mut HashMap<usize, usize>) {
+
+    let transform = to_transform(node);
+    let (parent_mat, parent_rotation) = match parent {
+        Some(parent) => (nodes[parent].world_mat, nodes[parent].world_rotation),
+        None => (Matrix4::identity(), Quaternion::identity()),
+    };
+    let index = nodes.len();
+    nodes.push(GltfNode {
+        name: node.name().map_or_else(|| format!("node {}", node.index()), String::from),
+        parent: parent,
+        transform: transform,
+        world_mat: parent_mat * transform.to_matrix(),
+        world_rotation: parent_rotation * transform.rotation,
+        primitives: node.mesh().map_or(vec![], |mesh| primitive_map[&mesh.index()].clone()),
+        camera: node.camera().map(|camera| camera.index()),
+        light: node.light().map(|light| light.index()),
+    });
+    node_map.insert(node.index(), index);
+
+    for child in node.children() {
+        load_node(&child, Some(index), primitive_map, nodes, node_map);
+    }
+}
+
+/// Import the default scene of a glTF file, or its first scene if it does not name
+/// a default.
+pub fn load_file<P: AsRef<Path>>(path: P) -> Result<GltfScene, GltfError> {
+    let path = path.as_ref();
+    let display = path.display().to_string();
+    let (document, buffers, image_data) = match gltf::import(path) {
+        Ok(val) => val,
+        Err(e) => return Err(GltfError::CouldNotImport(display, e.to_string())),
+    };
+    let scene = match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(scene) => scene,
+        None => return Err(GltfError::NoScene(display)),
+    };
+
+    let mut primitives = vec![];
+    let mut primitive_map = HashMap::new();
+    for mesh in document.meshes() {
+        let mut mesh_primitives = vec![];
+        for primitive in mesh.primitives() {
+            let primitive = match load_primitive(&primitive, &buffers) {
+                Ok(val) => val,
+                Err(e) => return Err(GltfError::InvalidMesh(display, e)),
+            };
+            if let Some(primitive) = primitive {
+                mesh_primitives.push(primitives.len());
+                primitives.push(primitive);
+            }
+        }
+        primitive_map.insert(mesh.index(), mesh_primitives);
+    }
+
+    let mut nodes = vec![];
+    let mut node_map = HashMap::new();
+    for node in scene.nodes() {
+        load_node(&node, None, &primitive_map, &mut nodes, &mut node_map);
+    }
+
+    let materials: Vec<GltfMaterial> = document.materials().map(|material| load_material(&material)).collect();
+    let images = image_data.iter().map(load_image).collect();
+    let cameras: Vec<GltfCamera> = document.cameras().map(|camera| load_camera(&camera)).collect();
+    let lights: Vec<GltfLight> = match document.lights() {
+        Some(lights) => lights.map(|light| load_light(&light)).collect(),
+        None => vec![],
+    };
+    let animations = load_animations(&document, &buffers, &node_map);
+
+    info!(
+        "Imported {}: {} nodes, {} primitives, {} materials, {} cameras, {} lights, {} animations.",
+        display, nodes.len(), primitives.len(), materials.len(),
+        cameras.len(), lights.len(), animations.len()
+    );
+
+    Ok(GltfScene {
+        nodes: nodes,
+        primitives: primitives,
+        materials: materials,
+        images: images,
+        cameras: cameras,
+        lights: lights,
+        animations: animations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts;
+
+
+    fn approx_eq(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    fn image(format: Format, pixels: Vec<u8>) -> gltf::image::Data {
+        gltf::image::Data {
+            pixels: pixels,
+            format: format,
+            width: 1,
+            height: 1,
+        }
+    }
+
+    fn document(json: &str) -> gltf::Gltf {
+        gltf::Gltf::from_slice(json.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_keyframes_pair_inputs_with_outputs() {
+        let keyframes = load_keyframes(
+            &[0.0, 0.5, 2.0], vec![1.0, 2.0, 3.0], gltf::animation::Interpolation::Linear
+        );
+        let times: Vec<f32> = keyframes.iter().map(|keyframe| keyframe.time).collect();
+        let values: Vec<f32> = keyframes.iter().map(|keyframe| keyframe.value).collect();
+
+        assert_eq!(times, vec![0.0, 0.5, 2.0]);
+        assert_eq!(values, vec![1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn test_cubic_spline_keyframes_drop_the_tangents() {
+        let outputs = vec![-1.0, 1.0, -1.0, -2.0, 2.0, -2.0];
+        let keyframes = load_keyframes(&[0.0, 1.0], outputs, gltf::animation::Interpolation::CubicSpline);
+        let values: Vec<f32> = keyframes.iter().map(|keyframe| keyframe.value).collect();
+
+        assert_eq!(values, vec![1.0, 2.0]);
+    }
+
+    #[test]
+    fn test_opaque_images_load_as_rgb() {
+        let image = load_image(&image(Format::R8G8B8, vec![10, 20, 30]));
+
+        assert!(!image.has_alpha);
+        assert_eq!(image.pixels, vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn test_bgra_images_swap_to_rgba() {
+        let image = load_image(&image(Format::B8G8R8A8, vec![10, 20, 30, 40]));
+
+        assert!(image.has_alpha);
+        assert_eq!(image.pixels, vec![30, 20, 10, 40]);
+    }
+
+    #[test]
+    fn test_greyscale_images_spread_over_the_color_channels() {
+        let grey = load_image(&image(Format::R8, vec![7]));
+        let grey_alpha = load_image(&image(Format::R8G8, vec![7, 9]));
+
+        assert_eq!(grey.pixels, vec![7, 7, 7]);
+        assert_eq!(grey_alpha.pixels, vec![7, 7, 7, 9]);
+    }
+
+    #[test]
+    fn test_sixteen_bit_images_keep_the_high_byte() {
+        let mut pixels = vec![];
+        for &channel in [0x1234_u16, 0x5678, 0x9ABC, 0xDEF0].iter() {
+            pixels.extend_from_slice(&channel.to_ne_bytes());
+        }
+        let image = load_image(&image(Format::R16G16B16A16, pixels));
+
+        assert_eq!(image.pixels, vec![0x12, 0x56, 0x9A, 0xDE]);
+    }
+
+    #[test]
+    fn test_transform_keeps_the_scale_of_each_axis() {
+        let gltf = document(r#"{
+            "asset": { "version": "2.0" },
+            "nodes": [{
+                "translation": [1.0, 2.0, 3.0],
+                "rotation": [0.0, 0.0, 0.70710678, 0.70710678],
+                "scale": [2.0, 3.0, 4.0]
+            }]
+        }"#);
+        let transform = to_transform(&gltf.nodes().next().unwrap());
+
+        assert_eq!(transform.translation, Vector3::new(1.0, 2.0, 3.0));
+        assert!(approx_eq(transform.rotation.s, consts::FRAC_1_SQRT_2));
+        assert!(approx_eq(transform.rotation.v.z, consts::FRAC_1_SQRT_2));
+        assert_eq!(transform.scale, Vector3::new(2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn test_transform_decomposes_a_matrix() {
+        let gltf = document(r#"{
+            "asset": { "version": "2.0" },
+            "nodes": [{
+                "matrix": [
+                    2.0, 0.0, 0.0, 0.0,
+                    0.0, 3.0, 0.0, 0.0,
+                    0.0, 0.0, 4.0, 0.0,
+                    5.0, 6.0, 7.0, 1.0
+                ]
+            }]
+        }"#);
+        let transform = to_transform(&gltf.nodes().next().unwrap());
+
+        assert_eq!(transform.translation, Vector3::new(5.0, 6.0, 7.0));
+        assert!(approx_eq(transform.rotation.s.abs(), 1.0));
+        assert!(approx_eq(transform.scale.x, 2.0));
+        assert!(approx_eq(transform.scale.y, 3.0));
+        assert!(approx_eq(transform.scale.z, 4.0));
+    }
+}
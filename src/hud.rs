@@ -0,0 +1,271 @@
+//! The heads-up display drawn over the scene. It shows the frame rate in the top right
+//! corner of the window, recent status messages in the bottom left corner, and the
+//! controls in the middle of the window on request. The HUD is laid out in window
+//! coordinates every frame and drawn through the same interface as the debug overlay,
+//! so it follows the window when it is resized or goes fullscreen.
+use crate::ui::{
+    Rect,
+    Ui,
+};
+
+use std::collections::VecDeque;
+
+
+/// How long a status message stays on screen, in seconds.
+const MESSAGE_SECONDS: f64 = 3.0;
+
+/// How long a status message takes to fade out at the end of its time, in seconds.
+const MESSAGE_FADE_SECONDS: f64 = 0.5;
+
+/// The most status messages shown at once. Older messages make way for newer ones.
+const MAX_MESSAGES: usize = 5;
+
+/// The space in pixels between the HUD and the edges of the window.
+const MARGIN: f32 = 10.0;
+
+/// The space in pixels between the controls and the edge of the box around them.
+const HELP_PADDING: f32 = 12.0;
+
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const SHADOW_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.75];
+const HELP_BACKGROUND_COLOR: [f32; 4] = [0.05, 0.05, 0.08, 0.8];
+
+/// A status message, and the time it was posted.
+#[derive(Clone, Debug)]
+struct Message {
+    text: String,
+    time: f64,
+}
+
+/// What the HUD shows.
+#[derive(Clone, Debug)]
+pub struct Hud {
+    visible: bool,
+    help: Option<String>,
+    messages: VecDeque<Message>,
+}
+
+impl Hud {
+    pub fn new() -> Hud {
+        Hud {
+            visible: true,
+            help: None,
+            messages: VecDeque::new(),
+        }
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Show or hide the frame rate and the status messages. The controls can be
+    /// shown either way.
+    #[inline]
+    pub fn toggle_visible(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    #[inline]
+    pub fn is_showing_help(&self) -> bool {
+        self.help.is_some()
+    }
+
+    /// Show the controls described by `help`, one per line, or hide them if they
+    /// are already showing.
+    pub fn toggle_help(&mut self, help: &str) {
+        self.help = match self.help {
+            Some(_) => None,
+            None => Some(String::from(help.trim_end())),
+        };
+    }
+
+    /// Post a status message at time `now`, in seconds.
+    pub fn post_message(&mut self, text: &str, now: f64) {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+        self.messages.push_back(Message {
+            text: String::from(text),
+            time: now,
+        });
+    }
+
+    /// The status messages still on screen at time `now`, oldest first, each with
+    /// its opacity.
+    pub fn messages(&self, now: f64) -> Vec<(&str, f32)> {
+        self.messages.iter()
+            .filter_map(|message| {
+                let remaining = MESSAGE_SECONDS - (now - message.time);
+                if remaining > 0.0 {
+                    let opacity = f64::min(remaining / MESSAGE_FADE_SECONDS, 1.0) as f32;
+                    Some((message.text.as_str(), opacity))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Forget the status messages that have run out of time by `now`.
+    pub fn expire_messages(&mut self, now: f64) {
+        while let Some(message) = self.messages.front() {
+            if now - message.time < MESSAGE_SECONDS {
+                break;
+            }
+            self.messages.pop_front();
+        }
+    }
+
+    /// Draw the HUD into a window `width` by `height` pixels in size at time `now`.
+    /// The interface has to be between `begin_frame` and `end_frame`, and outside
+    /// of any panel.
+    pub fn draw(&self, ui: &mut Ui, width: f32, height: f32, frames_per_second: f64, frame_time: f64, now: f64) {
+        let line_height = ui.font().line_height();
+        if self.visible {
+            let fps = format!("{:.0} FPS  {:.2} ms", frames_per_second, 1000.0 * frame_time);
+            let fps_x = width - MARGIN - ui.font().text_width(&fps);
+            draw_shadowed_text(ui, &fps, fps_x, MARGIN, 1.0);
+
+            let messages = self.messages(now);
+            let mut y = height - MARGIN - messages.len() as f32 * line_height;
+            for &(text, opacity) in messages.iter() {
+                draw_shadowed_text(ui, text, MARGIN, y, opacity);
+                y += line_height;
+            }
+        }
+
+        if let Some(help) = self.help.as_ref() {
+            let help_width = ui.font().text_width(help);
+            let help_height = ui.font().text_height(help);
+            let x = f32::max(((width - help_width) / 2.0).round(), MARGIN + HELP_PADDING);
+            let y = f32::max(((height - help_height) / 2.0).round(), MARGIN + HELP_PADDING);
+            let background = Rect::new(
+                x - HELP_PADDING, y - HELP_PADDING, help_width + 2.0 * HELP_PADDING, help_height + 2.0 * HELP_PADDING
+            );
+            ui.draw_rect(background, HELP_BACKGROUND_COLOR);
+            ui.draw_text(help, x, y, TEXT_COLOR);
+        }
+    }
+}
+
+/// Draw text over a drop shadow, so it stands out against any part of the scene.
+fn draw_shadowed_text(ui: &mut Ui, text: &str, x: f32, y: f32, opacity: f32) {
+    let [r, g, b, a] = SHADOW_COLOR;
+    ui.draw_text(text, x + 1.0, y + 1.0, [r, g, b, a * opacity]);
+    let [r, g, b, a] = TEXT_COLOR;
+    ui.draw_text(text, x, y, [r, g, b, a * opacity]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::FontAtlas;
+    use crate::ui::UiInput;
+
+
+    fn ui() -> Ui {
+        Ui::new(FontAtlas::from_ttf(include_asset!("DejaVuSansMono.ttf"), 16.0).unwrap())
+    }
+
+    fn draw(hud: &Hud, ui: &mut Ui, now: f64) {
+        ui.begin_frame(UiInput::none());
+        hud.draw(ui, 800.0, 600.0, 60.0, 1.0 / 60.0, now);
+        ui.end_frame();
+    }
+
+    #[test]
+    fn test_messages_stay_until_they_run_out_of_time() {
+        let mut hud = Hud::new();
+        hud.post_message("Paused", 10.0);
+
+        assert_eq!(hud.messages(10.0), vec![("Paused", 1.0)]);
+        assert_eq!(hud.messages(10.0 + MESSAGE_SECONDS - 1.0), vec![("Paused", 1.0)]);
+        assert!(hud.messages(10.0 + MESSAGE_SECONDS).is_empty());
+    }
+
+    #[test]
+    fn test_messages_fade_out_at_the_end() {
+        let mut hud = Hud::new();
+        hud.post_message("Paused", 0.0);
+        let messages = hud.messages(MESSAGE_SECONDS - MESSAGE_FADE_SECONDS / 2.0);
+
+        assert_eq!(messages.len(), 1);
+        assert!((messages[0].1 - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_oldest_messages_make_way_for_new_ones() {
+        let mut hud = Hud::new();
+        for i in 0..(MAX_MESSAGES + 2) {
+            hud.post_message(&format!("{}", i), 0.0);
+        }
+        let messages = hud.messages(0.0);
+
+        assert_eq!(messages.len(), MAX_MESSAGES);
+        assert_eq!(messages[0].0, "2");
+        assert_eq!(messages[MAX_MESSAGES - 1].0, format!("{}", MAX_MESSAGES + 1));
+    }
+
+    #[test]
+    fn test_expired_messages_are_forgotten() {
+        let mut hud = Hud::new();
+        hud.post_message("first", 0.0);
+        hud.post_message("second", 2.0);
+        hud.expire_messages(MESSAGE_SECONDS + 1.0);
+
+        assert_eq!(hud.messages.len(), 1);
+        assert_eq!(hud.messages[0].text, "second");
+    }
+
+    #[test]
+    fn test_help_toggles() {
+        let mut hud = Hud::new();
+        hud.toggle_help("* F1 -- Show the controls\n");
+
+        assert!(hud.is_showing_help());
+        assert_eq!(hud.help.as_ref().unwrap(), "* F1 -- Show the controls");
+
+        hud.toggle_help("* F1 -- Show the controls\n");
+
+        assert!(!hud.is_showing_help());
+    }
+
+    #[test]
+    fn test_frame_rate_sits_in_the_top_right_corner() {
+        let hud = Hud::new();
+        let mut ui = ui();
+        draw(&hud, &mut ui, 0.0);
+
+        assert!(!ui.vertices().is_empty());
+        for vertex in ui.vertices().iter() {
+            assert!(vertex.pos[0] > 400.0 && vertex.pos[0] <= 800.0 - MARGIN + 1.0);
+            assert!(vertex.pos[1] >= MARGIN - 1.0 && vertex.pos[1] < 300.0);
+        }
+    }
+
+    #[test]
+    fn test_hidden_hud_draws_nothing() {
+        let mut hud = Hud::new();
+        hud.toggle_visible();
+        hud.post_message("Paused", 0.0);
+        let mut ui = ui();
+        draw(&hud, &mut ui, 0.0);
+
+        assert!(ui.vertices().is_empty());
+    }
+
+    #[test]
+    fn test_help_is_centered() {
+        let mut hud = Hud::new();
+        hud.toggle_visible();
+        hud.toggle_help("* F1 -- Show the controls");
+        let mut ui = ui();
+        draw(&hud, &mut ui, 0.0);
+        let background = &ui.vertices()[0..6];
+        let min_x = background.iter().map(|vertex| vertex.pos[0]).fold(f32::INFINITY, f32::min);
+        let max_x = background.iter().map(|vertex| vertex.pos[0]).fold(f32::NEG_INFINITY, f32::max);
+
+        assert!(((min_x + max_x) / 2.0 - 400.0).abs() <= 1.0);
+    }
+}
@@ -0,0 +1,860 @@
+//! The input action layer. The demo reacts to actions like `MoveLeft` or
+//! `ResetCamera` rather than to particular keys, and an action map binds each action
+//! to the keys that trigger it. The bindings come from a controls file, so the keys
+//! can be changed without touching the code, and the control help is generated from
+//! whatever bindings are active. Gamepad sticks and buttons go through the same
+//! actions as the keyboard, so a controller can do anything the keyboard can.
+use crate::camera::CameraDynamics;
+use glfw::Key;
+
+use std::collections::HashMap;
+use std::fs;
+use std::mem;
+use std::path::Path;
+
+
+/// Everything the player can do.
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+pub enum InputAction {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    MoveForward,
+    MoveBackward,
+    YawLeft,
+    YawRight,
+    PitchUp,
+    PitchDown,
+    RollLeft,
+    RollRight,
+    ResetCamera,
+    Quit,
+    TogglePause,
+    SlowDown,
+    SpeedUp,
+    StepFrame,
+    ShowHelp,
+    ToggleMouseLook,
+    ToggleOrbit,
+    ToggleProjection,
+    ToggleRecording,
+    TogglePlayback,
+    ToggleDebugUi,
+    ToggleHud,
+    ToggleFullscreen,
+    CycleRenderMode,
+}
+
+/// Every action in the order the control help lists them, with its name in the
+/// controls file, its description, and its default key.
+const ACTIONS: [(InputAction, &str, &str, Key); 28] = [
+    (InputAction::MoveLeft,     "move_left",     "Move camera left", Key::A),
+    (InputAction::MoveRight,    "move_right",    "Move camera right", Key::D),
+    (InputAction::MoveUp,       "move_up",       "Move camera up", Key::Q),
+    (InputAction::MoveDown,     "move_down",     "Move camera down", Key::E),
+    (InputAction::MoveForward,  "move_forward",  "Move camera forward", Key::W),
+    (InputAction::MoveBackward, "move_backward", "Move camera backwards", Key::S),
+    (InputAction::YawLeft,      "yaw_left",      "Yaw camera left", Key::Left),
+    (InputAction::YawRight,     "yaw_right",     "Yaw camera right", Key::Right),
+    (InputAction::PitchUp,      "pitch_up",      "Pitch camera up", Key::Up),
+    (InputAction::PitchDown,    "pitch_down",    "Pitch camera down", Key::Down),
+    (InputAction::RollLeft,     "roll_left",     "Roll camera left", Key::Z),
+    (InputAction::RollRight,    "roll_right",    "Roll camera right", Key::C),
+    (InputAction::Quit,         "quit",          "Close window and shut down program", Key::Escape),
+    (InputAction::ResetCamera,  "reset_camera",  "Reset the camera position and orientation to default", Key::Backspace),
+    (InputAction::TogglePause,  "toggle_pause",  "Pause or resume the animation", Key::P),
+    (InputAction::SlowDown,     "slow_down",     "Halve the animation speed, down to 0.1x", Key::LeftBracket),
+    (InputAction::SpeedUp,      "speed_up",      "Double the animation speed, up to 4x", Key::RightBracket),
+    (InputAction::StepFrame,    "step_frame",    "Advance the animation by a single step while paused", Key::Period),
+    (InputAction::ShowHelp,     "show_help",     "Show the controls on screen and print them to the terminal", Key::F1),
+    (InputAction::ToggleMouseLook, "toggle_mouse_look", "Capture or release the mouse for mouse-look", Key::M),
+    (InputAction::ToggleOrbit,  "toggle_orbit",  "Switch between the free-fly and orbit cameras", Key::O),
+    (InputAction::ToggleProjection, "toggle_projection", "Switch between the perspective and orthographic projections", Key::V),
+    (InputAction::ToggleRecording, "toggle_recording", "Start or stop recording the camera path", Key::F5),
+    (InputAction::TogglePlayback, "toggle_playback", "Start or stop playing the camera path back", Key::F6),
+    (InputAction::ToggleDebugUi, "toggle_debug_ui", "Show or hide the debug overlay", Key::F3),
+    (InputAction::ToggleHud,    "toggle_hud",    "Show or hide the frame rate and status messages", Key::F2),
+    (InputAction::ToggleFullscreen, "toggle_fullscreen", "Switch between windowed and fullscreen mode", Key::F11),
+    (InputAction::CycleRenderMode, "cycle_render_mode", "Switch to the next render mode", Key::F4),
+];
+
+/// Which way a gamepad axis has to be pushed to trigger an action.
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+/// A gamepad stick axis or button, numbered the way GLFW reports them for the
+/// connected joystick.
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+pub enum GamepadInput {
+    Axis(usize, AxisDirection),
+    Button(usize),
+}
+
+/// The default gamepad bindings, laid out for an Xbox style controller as Linux
+/// reports it. The left stick moves, the right stick turns, and nothing on the
+/// controller quits the demo, so a kiosk setup cannot be shut down from it.
+const GAMEPAD_DEFAULTS: [(InputAction, GamepadInput); 15] = [
+    (InputAction::MoveLeft,     GamepadInput::Axis(0, AxisDirection::Negative)),
+    (InputAction::MoveRight,    GamepadInput::Axis(0, AxisDirection::Positive)),
+    (InputAction::MoveForward,  GamepadInput::Axis(1, AxisDirection::Negative)),
+    (InputAction::MoveBackward, GamepadInput::Axis(1, AxisDirection::Positive)),
+    (InputAction::YawLeft,      GamepadInput::Axis(3, AxisDirection::Negative)),
+    (InputAction::YawRight,     GamepadInput::Axis(3, AxisDirection::Positive)),
+    (InputAction::PitchUp,      GamepadInput::Axis(4, AxisDirection::Negative)),
+    (InputAction::PitchDown,    GamepadInput::Axis(4, AxisDirection::Positive)),
+    (InputAction::MoveUp,       GamepadInput::Button(0)),
+    (InputAction::MoveDown,     GamepadInput::Button(1)),
+    (InputAction::RollLeft,     GamepadInput::Button(4)),
+    (InputAction::RollRight,    GamepadInput::Button(5)),
+    (InputAction::ResetCamera,  GamepadInput::Button(6)),
+    (InputAction::TogglePause,  GamepadInput::Button(7)),
+    (InputAction::ToggleOrbit,  GamepadInput::Button(3)),
+];
+
+/// Look up a gamepad input by the name it has in a controls file. Axes are written
+/// with the direction that triggers the action, as in `Axis0-` or `Axis0+`, and
+/// buttons by number, as in `Button3`.
+pub fn parse_gamepad_input(name: &str) -> Option<GamepadInput> {
+    let lowercase = name.to_ascii_lowercase();
+    if let Some(axis) = lowercase.strip_prefix("axis") {
+        if let Some(index) = axis.strip_suffix('+') {
+            index.parse::<usize>().ok().map(|index| GamepadInput::Axis(index, AxisDirection::Positive))
+        } else if let Some(index) = axis.strip_suffix('-') {
+            index.parse::<usize>().ok().map(|index| GamepadInput::Axis(index, AxisDirection::Negative))
+        } else {
+            None
+        }
+    } else if let Some(index) = lowercase.strip_prefix("button") {
+        index.parse::<usize>().ok().map(GamepadInput::Button)
+    } else {
+        None
+    }
+}
+
+/// The name of a gamepad input in a controls file.
+pub fn gamepad_input_name(input: GamepadInput) -> String {
+    match input {
+        GamepadInput::Axis(index, AxisDirection::Positive) => format!("Axis{}+", index),
+        GamepadInput::Axis(index, AxisDirection::Negative) => format!("Axis{}-", index),
+        GamepadInput::Button(index) => format!("Button{}", index),
+    }
+}
+
+/// The names of the keys that can appear in a controls file. The comma key is
+/// spelled out, since a comma separates the keys of a binding.
+const KEY_NAMES: [(&str, Key); 80] = [
+    ("A", Key::A), ("B", Key::B), ("C", Key::C), ("D", Key::D), ("E", Key::E),
+    ("F", Key::F), ("G", Key::G), ("H", Key::H), ("I", Key::I), ("J", Key::J),
+    ("K", Key::K), ("L", Key::L), ("M", Key::M), ("N", Key::N), ("O", Key::O),
+    ("P", Key::P), ("Q", Key::Q), ("R", Key::R), ("S", Key::S), ("T", Key::T),
+    ("U", Key::U), ("V", Key::V), ("W", Key::W), ("X", Key::X), ("Y", Key::Y),
+    ("Z", Key::Z),
+    ("0", Key::Num0), ("1", Key::Num1), ("2", Key::Num2), ("3", Key::Num3), ("4", Key::Num4),
+    ("5", Key::Num5), ("6", Key::Num6), ("7", Key::Num7), ("8", Key::Num8), ("9", Key::Num9),
+    ("F1", Key::F1), ("F2", Key::F2), ("F3", Key::F3), ("F4", Key::F4),
+    ("F5", Key::F5), ("F6", Key::F6), ("F7", Key::F7), ("F8", Key::F8),
+    ("F9", Key::F9), ("F10", Key::F10), ("F11", Key::F11), ("F12", Key::F12),
+    ("Left", Key::Left), ("Right", Key::Right), ("Up", Key::Up), ("Down", Key::Down),
+    ("Space", Key::Space), ("Enter", Key::Enter), ("Tab", Key::Tab),
+    ("Escape", Key::Escape), ("Backspace", Key::Backspace),
+    ("Insert", Key::Insert), ("Delete", Key::Delete), ("Home", Key::Home), ("End", Key::End),
+    ("PageUp", Key::PageUp), ("PageDown", Key::PageDown),
+    ("LeftShift", Key::LeftShift), ("RightShift", Key::RightShift),
+    ("LeftControl", Key::LeftControl), ("RightControl", Key::RightControl),
+    ("LeftAlt", Key::LeftAlt), ("RightAlt", Key::RightAlt),
+    ("[", Key::LeftBracket), ("]", Key::RightBracket), (".", Key::Period), ("Comma", Key::Comma),
+    ("/", Key::Slash), ("\\", Key::Backslash), (";", Key::Semicolon), ("'", Key::Apostrophe),
+    ("-", Key::Minus), ("=", Key::Equal), ("`", Key::GraveAccent),
+];
+
+/// Look up a key by the name it has in a controls file.
+pub fn parse_key(name: &str) -> Option<Key> {
+    KEY_NAMES.iter()
+        .find(|&&(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|&(_, key)| key)
+}
+
+/// The name of a key in a controls file.
+pub fn key_name(key: Key) -> &'static str {
+    KEY_NAMES.iter()
+        .find(|&&(_, k)| k == key)
+        .map_or("?", |&(name, _)| name)
+}
+
+/// Look up an action by the name it has in a controls file.
+pub fn parse_action(name: &str) -> Option<InputAction> {
+    ACTIONS.iter()
+        .find(|&&(_, action_name, _, _)| action_name == name)
+        .map(|&(action, _, _, _)| action)
+}
+
+/// How the mouse steers the camera.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct MouseSettings {
+    /// How far the camera turns for each pixel the mouse moves, in degrees.
+    pub sensitivity: f32,
+    /// Pitch the camera down when the mouse moves up, like a flight stick.
+    pub invert_y: bool,
+    /// How far one notch of the scroll wheel changes the field of view, in degrees.
+    pub zoom_step: f32,
+}
+
+impl MouseSettings {
+    pub fn new() -> MouseSettings {
+        MouseSettings {
+            sensitivity: 0.1,
+            invert_y: false,
+            zoom_step: 2.0,
+        }
+    }
+
+    /// Parse a mouse setting from a line of a controls file. This returns `None` when
+    /// the name is not a mouse setting, so the line can be read as a binding instead.
+    fn parse_setting(&mut self, name: &str, value: &str) -> Option<Result<(), String>> {
+        let result = match name {
+            "mouse_sensitivity" => match value.parse::<f32>() {
+                Ok(value) if value > 0.0 => {
+                    self.sensitivity = value;
+                    Ok(())
+                }
+                _ => Err(format!("Expected a positive number for mouse_sensitivity, got `{}`", value)),
+            },
+            "mouse_invert_y" => match value.parse::<bool>() {
+                Ok(value) => {
+                    self.invert_y = value;
+                    Ok(())
+                }
+                Err(_) => Err(format!("Expected `true` or `false` for mouse_invert_y, got `{}`", value)),
+            },
+            "zoom_step" => match value.parse::<f32>() {
+                Ok(value) if value > 0.0 => {
+                    self.zoom_step = value;
+                    Ok(())
+                }
+                _ => Err(format!("Expected a positive number for zoom_step, got `{}`", value)),
+            },
+            _ => return None,
+        };
+
+        Some(result)
+    }
+}
+
+/// How the deflection of a stick past the dead zone maps onto the strength of an
+/// action. The steeper curves give finer control near the centre of the stick.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ResponseCurve {
+    Linear,
+    Quadratic,
+    Cubic,
+}
+
+impl ResponseCurve {
+    /// Look up a response curve by the name it has in a controls file.
+    pub fn parse(name: &str) -> Option<ResponseCurve> {
+        match name {
+            "linear" => Some(ResponseCurve::Linear),
+            "quadratic" => Some(ResponseCurve::Quadratic),
+            "cubic" => Some(ResponseCurve::Cubic),
+            _ => None,
+        }
+    }
+
+    /// Map a deflection between zero and one onto a strength between zero and one.
+    #[inline]
+    pub fn apply(self, amount: f32) -> f32 {
+        match self {
+            ResponseCurve::Linear => amount,
+            ResponseCurve::Quadratic => amount * amount,
+            ResponseCurve::Cubic => amount * amount * amount,
+        }
+    }
+}
+
+/// How the gamepad sticks respond.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct GamepadSettings {
+    /// How far a stick has to be pushed along an axis before it does anything, as a
+    /// fraction of its full range. Sticks never rest exactly at zero, so without a
+    /// dead zone the camera would drift.
+    pub dead_zone: f32,
+    pub response_curve: ResponseCurve,
+}
+
+impl GamepadSettings {
+    pub fn new() -> GamepadSettings {
+        GamepadSettings {
+            dead_zone: 0.2,
+            response_curve: ResponseCurve::Quadratic,
+        }
+    }
+
+    /// Map the deflection of a stick along one direction of an axis onto the strength
+    /// of an action. The range past the dead zone is stretched back out to the range
+    /// from zero to one before the response curve applies, so the action still reaches
+    /// full strength and starts from zero at the edge of the dead zone.
+    pub fn shape(&self, deflection: f32) -> f32 {
+        if deflection <= self.dead_zone {
+            return 0.0;
+        }
+        let amount = f32::min((deflection - self.dead_zone) / (1.0 - self.dead_zone), 1.0);
+
+        self.response_curve.apply(amount)
+    }
+
+    /// Parse a gamepad setting from a line of a controls file. This returns `None` when
+    /// the name is not a gamepad setting, so the line can be read as a binding instead.
+    fn parse_setting(&mut self, name: &str, value: &str) -> Option<Result<(), String>> {
+        let result = match name {
+            "gamepad_dead_zone" => match value.parse::<f32>() {
+                Ok(value) if (0.0..1.0).contains(&value) => {
+                    self.dead_zone = value;
+                    Ok(())
+                }
+                _ => Err(format!("Expected a gamepad dead zone from 0 up to 1, got `{}`", value)),
+            },
+            "gamepad_response" => match ResponseCurve::parse(value) {
+                Some(curve) => {
+                    self.response_curve = curve;
+                    Ok(())
+                }
+                None => Err(format!("Expected `linear`, `quadratic` or `cubic` for gamepad_response, got `{}`", value)),
+            },
+            _ => return None,
+        };
+
+        Some(result)
+    }
+}
+
+/// Parse a camera motion setting from a line of a controls file. This returns `None`
+/// when the name is not a camera setting, so the line can be read as a binding instead.
+fn parse_camera_setting(dynamics: &mut CameraDynamics, name: &str, value: &str) -> Option<Result<(), String>> {
+    if name == "camera_smoothing" {
+        let result = match value.parse::<bool>() {
+            Ok(value) => {
+                dynamics.smooth = value;
+                Ok(())
+            }
+            Err(_) => Err(format!("Expected `true` or `false` for camera_smoothing, got `{}`", value)),
+        };
+
+        return Some(result);
+    }
+    let setting = match name {
+        "camera_acceleration" => &mut dynamics.acceleration,
+        "camera_max_speed" => &mut dynamics.max_speed,
+        "camera_damping" => &mut dynamics.damping,
+        "camera_turn_acceleration" => &mut dynamics.turn_acceleration,
+        "camera_max_turn_speed" => &mut dynamics.max_turn_speed,
+        "camera_turn_damping" => &mut dynamics.turn_damping,
+        _ => return None,
+    };
+    let result = match value.parse::<f32>() {
+        Ok(value) if value > 0.0 => {
+            *setting = value;
+            Ok(())
+        }
+        _ => Err(format!("Expected a positive number for {}, got `{}`", name, value)),
+    };
+
+    Some(result)
+}
+
+/// The state of the first joystick GLFW finds, read once per frame.
+pub struct Gamepad {
+    joystick: glfw::Joystick,
+    connected: bool,
+    axes: Vec<f32>,
+    buttons: Vec<bool>,
+    previous_buttons: Vec<bool>,
+}
+
+impl Gamepad {
+    pub fn new(glfw: &glfw::Glfw) -> Gamepad {
+        Gamepad {
+            joystick: glfw.get_joystick(glfw::JoystickId::Joystick1),
+            connected: false,
+            axes: vec![],
+            buttons: vec![],
+            previous_buttons: vec![],
+        }
+    }
+
+    /// Read the axes and buttons. A disconnected gamepad reads as centred sticks and
+    /// released buttons.
+    pub fn poll(&mut self) {
+        let connected = self.joystick.is_present();
+        self.connected = connected;
+        self.previous_buttons = mem::take(&mut self.buttons);
+        if connected {
+            self.axes = self.joystick.get_axes();
+            self.buttons = self.joystick.get_buttons().iter().map(|&button| button != 0).collect();
+        } else {
+            self.axes.clear();
+        }
+    }
+
+    #[inline]
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// The position of an axis between -1 and 1, or zero if the gamepad has no such axis.
+    #[inline]
+    pub fn axis(&self, index: usize) -> f32 {
+        self.axes.get(index).copied().unwrap_or(0.0)
+    }
+
+    /// Determine whether a button is held down.
+    #[inline]
+    pub fn button(&self, index: usize) -> bool {
+        self.buttons.get(index).copied().unwrap_or(false)
+    }
+
+    /// The buttons that went down since the previous poll.
+    pub fn pressed_buttons(&self) -> Vec<usize> {
+        (0..self.buttons.len()).filter(|&index| {
+            self.buttons[index] && !self.previous_buttons.get(index).copied().unwrap_or(false)
+        }).collect()
+    }
+}
+
+/// The bindings from actions to keys and gamepad inputs, along with the mouse,
+/// gamepad and camera motion settings.
+#[derive(Clone, Debug)]
+pub struct ActionMap {
+    bindings: HashMap<InputAction, Vec<Key>>,
+    gamepad_bindings: HashMap<InputAction, Vec<GamepadInput>>,
+    pub mouse: MouseSettings,
+    pub gamepad: GamepadSettings,
+    pub camera: CameraDynamics,
+}
+
+impl ActionMap {
+    /// The default bindings.
+    pub fn new() -> ActionMap {
+        let mut bindings = HashMap::new();
+        for &(action, _, _, key) in ACTIONS.iter() {
+            bindings.insert(action, vec![key]);
+        }
+
+        let mut gamepad_bindings = HashMap::new();
+        for &(action, input) in GAMEPAD_DEFAULTS.iter() {
+            gamepad_bindings.entry(action).or_insert_with(Vec::new).push(input);
+        }
+
+        ActionMap {
+            bindings: bindings,
+            gamepad_bindings: gamepad_bindings,
+            mouse: MouseSettings::new(),
+            gamepad: GamepadSettings::new(),
+            camera: CameraDynamics::new(),
+        }
+    }
+
+    /// Parse a controls file. Each line binds an action to a comma separated list of
+    /// keys and gamepad inputs, as in `move_left = A, Left, Axis0-`. Blank lines and
+    /// lines starting with `#` are ignored, and any action the file does not mention
+    /// keeps its default bindings. A line that only lists keys keeps the gamepad inputs
+    /// of its action, and the other way around. The mouse, gamepad and camera motion settings
+    /// appear in the same file, as in `mouse_sensitivity = 0.1`.
+    pub fn parse(text: &str) -> Result<ActionMap, String> {
+        let mut map = ActionMap::new();
+        for (line_number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, '=');
+            let action_name = parts.next().unwrap().trim();
+            let keys = match parts.next() {
+                Some(keys) => keys,
+                None => return Err(format!("Line {}: Expected `action = key`, got `{}`", line_number + 1, line)),
+            };
+            if let Some(result) = map.mouse.parse_setting(action_name, keys.trim()) {
+                result.map_err(|e| format!("Line {}: {}", line_number + 1, e))?;
+                continue;
+            }
+            if let Some(result) = map.gamepad.parse_setting(action_name, keys.trim()) {
+                result.map_err(|e| format!("Line {}: {}", line_number + 1, e))?;
+                continue;
+            }
+            if let Some(result) = parse_camera_setting(&mut map.camera, action_name, keys.trim()) {
+                result.map_err(|e| format!("Line {}: {}", line_number + 1, e))?;
+                continue;
+            }
+            let action = match parse_action(action_name) {
+                Some(action) => action,
+                None => return Err(format!("Line {}: Unknown action `{}`", line_number + 1, action_name)),
+            };
+            let mut bound_keys = vec![];
+            let mut bound_inputs = vec![];
+            for key_name in keys.split(',').map(|key_name| key_name.trim()).filter(|key_name| !key_name.is_empty()) {
+                if let Some(key) = parse_key(key_name) {
+                    bound_keys.push(key);
+                } else if let Some(input) = parse_gamepad_input(key_name) {
+                    bound_inputs.push(input);
+                } else {
+                    return Err(format!("Line {}: Unknown key `{}`", line_number + 1, key_name));
+                }
+            }
+            // A line only replaces the kinds of input it lists, so rebinding the keys of
+            // an action keeps its gamepad inputs and the other way around. A line that
+            // lists nothing unbinds the action entirely.
+            let unbind = bound_keys.is_empty() && bound_inputs.is_empty();
+            if unbind || !bound_keys.is_empty() {
+                map.bind(action, bound_keys);
+            }
+            if unbind || !bound_inputs.is_empty() {
+                map.bind_gamepad(action, bound_inputs);
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Load the bindings from a controls file.
+    pub fn load_file<P: AsRef<Path>>(path: P) -> Result<ActionMap, String> {
+        let text = match fs::read_to_string(&path) {
+            Ok(val) => val,
+            Err(e) => return Err(format!("Could not read {}: {}", path.as_ref().display(), e)),
+        };
+
+        ActionMap::parse(&text).map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+    }
+
+    /// Replace the keys bound to an action. Binding no keys disables the action.
+    #[inline]
+    pub fn bind(&mut self, action: InputAction, keys: Vec<Key>) {
+        self.bindings.insert(action, keys);
+    }
+
+    /// Replace the gamepad inputs bound to an action.
+    #[inline]
+    pub fn bind_gamepad(&mut self, action: InputAction, inputs: Vec<GamepadInput>) {
+        self.gamepad_bindings.insert(action, inputs);
+    }
+
+    /// The keys bound to an action.
+    #[inline]
+    pub fn keys(&self, action: InputAction) -> &[Key] {
+        match self.bindings.get(&action) {
+            Some(keys) => keys,
+            None => &[],
+        }
+    }
+
+    /// The gamepad inputs bound to an action.
+    #[inline]
+    pub fn gamepad_inputs(&self, action: InputAction) -> &[GamepadInput] {
+        match self.gamepad_bindings.get(&action) {
+            Some(inputs) => inputs,
+            None => &[],
+        }
+    }
+
+    /// Determine whether any key bound to an action is held down.
+    pub fn is_held(&self, window: &glfw::Window, action: InputAction) -> bool {
+        self.keys(action).iter().any(|&key| {
+            match window.get_key(key) {
+                glfw::Action::Press | glfw::Action::Repeat => true,
+                glfw::Action::Release => false,
+            }
+        })
+    }
+
+    /// How strongly an action is triggered, between zero and one. A held key or
+    /// button triggers its action at full strength, and a stick triggers its action
+    /// in proportion to how far it is pushed past the dead zone. When several inputs
+    /// trigger the same action, the strongest one wins.
+    pub fn strength(&self, window: &glfw::Window, gamepad: &Gamepad, action: InputAction) -> f32 {
+        if self.is_held(window, action) {
+            return 1.0;
+        }
+
+        self.gamepad_inputs(action).iter().fold(0.0, |strength, &input| {
+            let input_strength = match input {
+                GamepadInput::Button(index) => if gamepad.button(index) { 1.0 } else { 0.0 },
+                GamepadInput::Axis(index, AxisDirection::Positive) => self.gamepad.shape(gamepad.axis(index)),
+                GamepadInput::Axis(index, AxisDirection::Negative) => self.gamepad.shape(-gamepad.axis(index)),
+            };
+
+            f32::max(strength, input_strength)
+        })
+    }
+
+    /// The actions a gamepad button is bound to.
+    pub fn actions_for_button(&self, button: usize) -> Vec<InputAction> {
+        ACTIONS.iter()
+            .map(|&(action, _, _, _)| action)
+            .filter(|&action| self.gamepad_inputs(action).contains(&GamepadInput::Button(button)))
+            .collect()
+    }
+
+    /// The actions a key is bound to.
+    pub fn actions_for_key(&self, key: Key) -> Vec<InputAction> {
+        ACTIONS.iter()
+            .map(|&(action, _, _, _)| action)
+            .filter(|&action| self.keys(action).contains(&key))
+            .collect()
+    }
+
+    /// Generate the control help from the active bindings, one action per line.
+    pub fn help(&self) -> String {
+        let mut help = String::new();
+        for &(action, _, description, _) in ACTIONS.iter() {
+            let mut names: Vec<String> = self.keys(action).iter().map(|&key| String::from(key_name(key))).collect();
+            names.extend(self.gamepad_inputs(action).iter().map(|&input| gamepad_input_name(input)));
+            if names.is_empty() {
+                continue;
+            }
+            help.push_str(&format!("* {} -- {}\n", names.join(", "), description));
+        }
+
+        help
+    }
+}
+
+/// Tracks the mouse between frames for mouse-look and scroll-wheel zoom. While the
+/// cursor is captured, every cursor movement adds to the pending turn, and the scroll
+/// wheel adds to the pending zoom whether or not the cursor is captured. The camera
+/// takes both the next time it updates.
+#[derive(Copy, Clone, Debug)]
+pub struct MouseLook {
+    captured: bool,
+    last_position: Option<(f64, f64)>,
+    delta: (f64, f64),
+    scroll: f64,
+}
+
+impl MouseLook {
+    pub fn new() -> MouseLook {
+        MouseLook {
+            captured: false,
+            last_position: None,
+            delta: (0.0, 0.0),
+            scroll: 0.0,
+        }
+    }
+
+    #[inline]
+    pub fn is_captured(&self) -> bool {
+        self.captured
+    }
+
+    /// Capture or release the cursor. The cursor jumps when GLFW hides it, so the
+    /// next cursor position only sets the starting point and does not turn the camera.
+    pub fn set_captured(&mut self, captured: bool) {
+        self.captured = captured;
+        self.last_position = None;
+        self.delta = (0.0, 0.0);
+    }
+
+    /// Record a new cursor position.
+    pub fn cursor_moved(&mut self, x: f64, y: f64) {
+        if !self.captured {
+            return;
+        }
+        if let Some((last_x, last_y)) = self.last_position {
+            self.delta.0 += x - last_x;
+            self.delta.1 += y - last_y;
+        }
+        self.last_position = Some((x, y));
+    }
+
+    /// Record a turn of the scroll wheel.
+    #[inline]
+    pub fn scrolled(&mut self, y_offset: f64) {
+        self.scroll += y_offset;
+    }
+
+    /// The distance the cursor has moved in pixels since the last call.
+    #[inline]
+    pub fn take_delta(&mut self) -> (f64, f64) {
+        let delta = self.delta;
+        self.delta = (0.0, 0.0);
+        delta
+    }
+
+    /// The number of notches the scroll wheel has turned since the last call.
+    /// Scrolling up gives a positive count.
+    #[inline]
+    pub fn take_scroll(&mut self) -> f64 {
+        let scroll = self.scroll;
+        self.scroll = 0.0;
+        scroll
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn linear(dead_zone: f32) -> GamepadSettings {
+        GamepadSettings {
+            dead_zone: dead_zone,
+            response_curve: ResponseCurve::Linear,
+        }
+    }
+
+    #[test]
+    fn test_unknown_action_is_an_error() {
+        let result = ActionMap::parse("move_left = A\nfly_away = F");
+
+        assert_eq!(result.unwrap_err(), "Line 2: Unknown action `fly_away`");
+    }
+
+    #[test]
+    fn test_unknown_key_is_an_error() {
+        let result = ActionMap::parse("move_left = A, Hyperspace");
+
+        assert_eq!(result.unwrap_err(), "Line 1: Unknown key `Hyperspace`");
+    }
+
+    #[test]
+    fn test_line_without_a_binding_is_an_error() {
+        assert!(ActionMap::parse("move_left").is_err());
+    }
+
+    #[test]
+    fn test_empty_binding_disables_the_action() {
+        let map = ActionMap::parse("reset_camera =").unwrap();
+
+        assert!(map.keys(InputAction::ResetCamera).is_empty());
+        assert!(map.gamepad_inputs(InputAction::ResetCamera).is_empty());
+        assert!(map.actions_for_key(Key::Backspace).is_empty());
+        assert!(map.actions_for_button(6).is_empty());
+    }
+
+    #[test]
+    fn test_actions_left_out_keep_their_defaults() {
+        let map = ActionMap::parse("# Comments and blank lines are skipped.\n\nmove_left = J, Button2\n").unwrap();
+
+        assert_eq!(map.keys(InputAction::MoveLeft), &[Key::J]);
+        assert_eq!(map.gamepad_inputs(InputAction::MoveLeft), &[GamepadInput::Button(2)]);
+        assert_eq!(map.keys(InputAction::MoveRight), &[Key::D]);
+        assert_eq!(
+            map.gamepad_inputs(InputAction::MoveRight),
+            &[GamepadInput::Axis(0, AxisDirection::Positive)]
+        );
+        assert_eq!(map.mouse, MouseSettings::new());
+        assert_eq!(map.gamepad, GamepadSettings::new());
+    }
+
+    #[test]
+    fn test_comma_key_can_be_bound() {
+        let map = ActionMap::parse("step_frame = ., Comma").unwrap();
+
+        assert_eq!(map.keys(InputAction::StepFrame), &[Key::Period, Key::Comma]);
+        assert_eq!(key_name(Key::Comma), "Comma");
+    }
+
+    #[test]
+    fn test_settings_are_parsed_alongside_the_bindings() {
+        let map = ActionMap::parse("mouse_invert_y = true\ngamepad_dead_zone = 0.1\ngamepad_response = cubic").unwrap();
+
+        assert!(map.mouse.invert_y);
+        assert_eq!(map.gamepad.dead_zone, 0.1);
+        assert_eq!(map.gamepad.response_curve, ResponseCurve::Cubic);
+        assert!(ActionMap::parse("gamepad_dead_zone = 1.0").is_err());
+        assert!(ActionMap::parse("gamepad_response = steep").is_err());
+    }
+
+    #[test]
+    fn test_camera_smoothing_can_be_switched_off() {
+        let map = ActionMap::parse("camera_smoothing = false").unwrap();
+
+        assert!(!map.camera.smooth);
+        assert!(ActionMap::new().camera.smooth);
+        assert!(ActionMap::parse("camera_smoothing = off").is_err());
+    }
+
+    #[test]
+    fn test_mouse_settings_must_be_positive() {
+        let map = ActionMap::parse("mouse_sensitivity = 0.25\nzoom_step = 5").unwrap();
+
+        assert_eq!(map.mouse.sensitivity, 0.25);
+        assert_eq!(map.mouse.zoom_step, 5.0);
+        for setting in ["mouse_sensitivity", "zoom_step"].iter() {
+            for value in ["0", "-1", "NaN", "fast"].iter() {
+                assert!(ActionMap::parse(&format!("{} = {}", setting, value)).is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_rebinding_keys_keeps_the_gamepad_inputs() {
+        let map = ActionMap::parse("move_left = J").unwrap();
+
+        assert_eq!(map.keys(InputAction::MoveLeft), &[Key::J]);
+        assert_eq!(
+            map.gamepad_inputs(InputAction::MoveLeft),
+            ActionMap::new().gamepad_inputs(InputAction::MoveLeft)
+        );
+        assert!(!map.gamepad_inputs(InputAction::MoveLeft).is_empty());
+    }
+
+    #[test]
+    fn test_rebinding_gamepad_inputs_keeps_the_keys() {
+        let map = ActionMap::parse("move_left = Button2").unwrap();
+
+        assert_eq!(map.keys(InputAction::MoveLeft), &[Key::A]);
+        assert_eq!(map.gamepad_inputs(InputAction::MoveLeft), &[GamepadInput::Button(2)]);
+    }
+
+    #[test]
+    fn test_stick_inside_the_dead_zone_does_nothing() {
+        let settings = linear(0.2);
+
+        assert_eq!(settings.shape(-1.0), 0.0);
+        assert_eq!(settings.shape(0.0), 0.0);
+        assert_eq!(settings.shape(0.2), 0.0);
+    }
+
+    #[test]
+    fn test_range_past_the_dead_zone_is_stretched_to_full_strength() {
+        let settings = linear(0.2);
+
+        assert!((settings.shape(0.6) - 0.5).abs() < 1e-6);
+        assert_eq!(settings.shape(1.0), 1.0);
+        assert_eq!(settings.shape(1.5), 1.0);
+    }
+
+    #[test]
+    fn test_response_curves_soften_small_deflections() {
+        let mut settings = linear(0.2);
+        settings.response_curve = ResponseCurve::Quadratic;
+
+        assert!((settings.shape(0.6) - 0.25).abs() < 1e-6);
+
+        settings.response_curve = ResponseCurve::Cubic;
+
+        assert!((settings.shape(0.6) - 0.125).abs() < 1e-6);
+        assert_eq!(settings.shape(1.0), 1.0);
+    }
+
+    #[test]
+    fn test_help_lists_every_bound_action_in_order() {
+        let help = ActionMap::new().help();
+        let lines: Vec<&str> = help.lines().collect();
+
+        assert_eq!(lines.len(), ACTIONS.len());
+        assert_eq!(lines[0], "* A, Axis0- -- Move camera left");
+        assert_eq!(lines[ACTIONS.len() - 1], "* F4 -- Switch to the next render mode");
+    }
+
+    #[test]
+    fn test_help_follows_the_active_bindings() {
+        let help = ActionMap::parse("move_left = J, Left\nquit =").unwrap().help();
+
+        assert!(help.starts_with("* J, Left, Axis0- -- Move camera left\n"));
+        assert!(!help.contains("Close window"));
+    }
+}
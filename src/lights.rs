@@ -3,12 +3,30 @@ use cglinalg::{
 };
 
 
+/// The cone of a spot light. The light is full strength inside the inner cone and
+/// fades out towards the edge of the outer cone. Both angles are half angles in
+/// radians, measured from the direction the light points in.
+#[derive(Copy, Clone, Debug)]
+pub struct SpotCone {
+    pub direction: Vector3<f32>,
+    pub inner_cone_angle: f32,
+    pub outer_cone_angle: f32,
+}
+
 pub struct PointLight {
     pub ambient: Vector3<f32>,
     pub diffuse: Vector3<f32>,
     pub specular: Vector3<f32>,
     pub specular_exponent: f32,
     pub position: Vector3<f32>,
+    /// A multiplier on the diffuse and specular colors.
+    pub intensity: f32,
+    /// The distance at which the light fades out completely. A light without a
+    /// range reaches everywhere.
+    pub range: Option<f32>,
+    /// The cone a spot light shines into. A light without a cone shines in every
+    /// direction.
+    pub spot: Option<SpotCone>,
 }
 
 impl PointLight {
@@ -23,6 +41,9 @@ impl PointLight {
             specular: specular,
             specular_exponent: specular_exponent,
             position: position,
+            intensity: 1.0,
+            range: None,
+            spot: None,
         }
     }
 }
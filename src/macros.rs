@@ -19,7 +19,7 @@ macro_rules! include_asset {
     }
 }
 
-#[cfg(target_os = "mac_os")]
+#[cfg(target_os = "macos")]
 macro_rules! shader_file {
     ($asset:expr) => {
         concat!(concat_path!("..", "shaders", "330"), $asset)
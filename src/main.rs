@@ -1,10 +1,18 @@
+// The struct literals spell out `field: field` on purpose, to keep every field name
+// visible at the place it is set.
+#![allow(clippy::redundant_field_names)]
+
 extern crate glfw;
 extern crate teximage2d;
 extern crate cglinalg;
 extern crate mini_obj;
 extern crate log;
 extern crate file_logger;
+extern crate gltf;
 
+// The bindings are generated by the build script, so they are left to the generator's
+// style.
+#[allow(clippy::all)]
 mod gl {
     include!(concat!(env!("OUT_DIR"), "/gl_bindings.rs"));
 }
@@ -12,19 +20,38 @@ mod gl {
 #[macro_use]
 mod macros;
 
+mod animation;
+mod bcn;
 mod camera;
+mod camera_path;
+mod debug_draw;
+mod frustum;
 mod gl_help;
+mod gltf_import;
+mod hud;
+mod input;
 mod component;
 mod lights;
+mod picking;
+mod render_mode;
+mod scene;
+mod skybox;
+mod texture;
+mod text;
+mod texture_container;
+mod timing;
+mod ui;
 
 use glfw::{
     Action,
     Context,
-    Key
+    MouseButtonLeft,
+    WindowEvent,
 };
 use gl::types::{
+    GLchar,
     GLfloat, 
-    GLint, 
+    GLint,
     GLsizeiptr, 
     GLuint, 
     GLvoid
@@ -33,14 +60,36 @@ use gl::types::{
 use gl_help as glh;
 use mini_obj as obj;
 
-use camera::Camera;
+use animation::{
+    Animation,
+    Animator,
+    Interpolation,
+    Keyframe,
+    PlaybackMode,
+    Track,
+};
+use camera::{
+    Camera,
+    CameraController,
+    CameraInput,
+    CameraMode,
+    CameraPose,
+    Projection,
+};
+use camera_path::{
+    CameraPath,
+    CameraPlayback,
+    CameraRecorder,
+};
 use component::{
-    BufferHandle, 
+    Buffer,
     EntityID,
     ShaderUniformHandle, 
     ShaderProgram, 
     ShaderProgramHandle,
-    TextureHandle
+    Texture,
+    Transform,
+    VertexArray,
 };
 use cglinalg::{
     Degrees, 
@@ -50,20 +99,101 @@ use cglinalg::{
     Vector4,
     Unit,
 };
+use debug_draw::{
+    DebugLines,
+    LineVertex,
+};
+use frustum::{
+    Aabb,
+    Frustum,
+};
+use gltf_import::GltfScene;
+use hud::Hud;
+use input::{
+    ActionMap,
+    Gamepad,
+    InputAction,
+    MouseLook,
+};
 use lights::PointLight;
-use log::{info};
+use picking::Ray;
+use render_mode::RenderMode;
+use scene::{
+    SceneGraph,
+    SceneNodeID,
+};
+use skybox::{
+    SKYBOX_FACE_SIZE,
+    SKYBOX_POINTS,
+};
+use texture::{
+    SamplerSettings,
+    TextureFormat,
+    TextureManager,
+};
+use log::{error, info};
 use teximage2d::TexImage2D;
+use text::FontAtlas;
+use timing::{
+    FixedTimestep,
+    FrameTimer,
+    TimeControl,
+    MAX_TIME_SCALE,
+    MIN_TIME_SCALE,
+};
+use ui::{
+    Ui,
+    UiInput,
+    UiVertex,
+};
 
+use std::env;
+use std::ffi::CString;
 use std::io;
 use std::mem;
+use std::path::Path;
 use std::process;
 use std::ptr;
-use std::collections::HashMap;
+use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
+
+
+// How strongly the environment map shows up in the specular term of the triforce.
+const ENV_REFLECTIVITY: f32 = 0.25;
+
+// The length in seconds of a simulation step.
+const SIMULATION_TIMESTEP: f64 = 1.0 / 120.0;
+
+// The longest a single frame can count for in the simulation. Past this, the
+// simulation slows down rather than trying to catch up.
+const MAX_FRAME_TIME: f64 = 0.25;
 
+// The narrowest and widest field of view, in degrees, the scroll wheel can zoom to.
+const MIN_FOV: f32 = 20.0;
+const MAX_FOV: f32 = 90.0;
 
-// OpenGL extension constants.
-const GL_TEXTURE_MAX_ANISOTROPY_EXT: u32 = 0x84FE;
-const GL_MAX_TEXTURE_MAX_ANISOTROPY_EXT: u32 = 0x84FF;
+// How much one notch of the scroll wheel changes the distance of the orbit camera
+// from its target, as a fraction of the distance.
+const ORBIT_SCROLL_STEP: f32 = 0.1;
+
+// The outline around the selected entity is a copy of the entity scaled up by this
+// much about the center of its bounding box, drawn in this color.
+const OUTLINE_SCALE: f32 = 1.05;
+const OUTLINE_COLOR: [f32; 4] = [1.0, 0.6, 0.0, 1.0];
+
+// The height in pixels of a line of text in the HUD and the debug overlay, and the
+// width of the debug overlay panel.
+const OVERLAY_FONT_SIZE: f32 = 16.0;
+const DEBUG_PANEL_WIDTH: f32 = 360.0;
+
+// The sizes and colors of the debug lines. The world axes and the normals are
+// lengths, and the light is drawn as a sphere of the given radius.
+const DEBUG_AXES_LENGTH: f32 = 5.0;
+const DEBUG_NORMAL_LENGTH: f32 = 0.1;
+const DEBUG_LIGHT_RADIUS: f32 = 0.25;
+const DEBUG_BOUNDS_COLOR: [f32; 4] = [1.0, 1.0, 0.0, 1.0];
+const DEBUG_NORMAL_COLOR: [f32; 4] = [0.0, 1.0, 1.0, 1.0];
+const DEBUG_FRUSTUM_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
 
 #[cfg(feature = "build_for_install")]
 const LOG_FILE: &str = "/tmp/triforces-demo.log";
@@ -71,13 +201,31 @@ const LOG_FILE: &str = "/tmp/triforces-demo.log";
 #[cfg(not(feature = "build_for_install"))]
 const LOG_FILE: &str = "triforces-demo.log";
 
+// The controls file the demo reads its key bindings from when none is given on
+// the command line.
+const CONTROLS_FILE: &str = "controls.cfg";
+
+// The file camera paths are recorded to and played back from when none is given on
+// the command line.
+const CAMERA_PATH_FILE: &str = "camera_path.txt";
+
+// How often a camera path recording keeps the pose of the camera, in seconds.
+const CAMERA_PATH_INTERVAL: f32 = 0.1;
+
 
 struct EntityDatabase {
     meshes: HashMap<EntityID, obj::ObjMesh>,
     shaders: HashMap<EntityID, ShaderProgram>,
-    textures: HashMap<EntityID, TextureHandle>,
-    buffers: HashMap<EntityID, Vec<BufferHandle>>,
+    textures: HashMap<EntityID, Rc<Texture>>,
+    buffers: HashMap<EntityID, Vec<Buffer>>,
+    // The uniform buffers holding the light parameters of the lit entities.
+    light_buffers: HashMap<EntityID, Buffer>,
+    vertex_arrays: HashMap<EntityID, VertexArray>,
     model_matrices: HashMap<EntityID, Matrix4<f32>>,
+    bounds: HashMap<EntityID, Aabb>,
+    materials: HashMap<EntityID, String>,
+    // The entities drawn without back face culling.
+    double_sided: HashSet<EntityID>,
 }
 
 impl EntityDatabase {
@@ -87,16 +235,90 @@ impl EntityDatabase {
             shaders: HashMap::new(),
             textures: HashMap::new(),
             buffers: HashMap::new(),
+            light_buffers: HashMap::new(),
+            vertex_arrays: HashMap::new(),
             model_matrices: HashMap::new(),
+            bounds: HashMap::new(),
+            materials: HashMap::new(),
+            double_sided: HashSet::new(),
         }
     }
 }
 
+// The fields of the game context drop in declaration order. Every GL object the
+// entities own has to be deleted while the OpenGL context still exists, so the GL
+// state must stay the last field.
 struct GameContext {
-    gl: glh::GLState,
     camera: Camera,
+    /// Where the camera was before the last simulation step. Each frame draws the
+    /// camera part of the way from here to where the step left it.
+    previous_camera_pose: CameraPose,
+    projection_settings: ProjectionSettings,
+    camera_controller: CameraController,
+    camera_path_file: String,
+    camera_path_frame: Quaternion<f32>,
+    camera_path: Option<CameraPath>,
+    camera_recorder: Option<CameraRecorder>,
+    camera_playback: Option<CameraPlayback>,
     light: PointLight,
+    textures: TextureManager,
     entities: EntityDatabase,
+    scene: SceneGraph,
+    /// The parent node of the triforce pieces.
+    triforce: SceneNodeID,
+    animator: Animator,
+    timestep: FixedTimestep,
+    time_control: TimeControl,
+    controls: ActionMap,
+    mouse_look: MouseLook,
+    gamepad: Gamepad,
+    gltf_entities: Vec<EntityID>,
+    selection: Selection,
+    render_mode: RenderMode,
+    frame_timer: FrameTimer,
+    overlay: Overlay,
+    debug_draw: DebugDraw,
+    gl: glh::GLState,
+}
+
+/// The entity picked with the mouse, and what it takes to draw its outline. The
+/// outline shader reads the points buffer of the selected entity through a vertex
+/// array of its own, since the attribute locations of the shader the entity is
+/// drawn with need not match those of the outline shader.
+struct Selection {
+    shader: ShaderProgram,
+    entity: Option<EntityID>,
+    vertex_array: Option<VertexArray>,
+}
+
+/// The debug lines, what they show, and the GL objects that draw them. The vertex
+/// buffer is filled anew every frame with the lines of that frame.
+struct DebugDraw {
+    lines: DebugLines,
+    show_axes: bool,
+    show_normals: bool,
+    show_bounds: bool,
+    show_light: bool,
+    /// The projection and view matrix of the camera frustum to draw. The frustum
+    /// stays where the camera was when it was switched on, so the camera can move
+    /// away and look at it.
+    frustum: Option<Matrix4<f32>>,
+    shader: ShaderProgram,
+    buffer: Buffer,
+    vertex_array: VertexArray,
+}
+
+/// Everything drawn over the scene, which is the HUD and the debug overlay, along with
+/// the GL objects that draw it. The vertex buffer is filled anew every frame with
+/// whatever the interface drew.
+struct Overlay {
+    ui: Ui,
+    hud: Hud,
+    debug_visible: bool,
+    shader: ShaderProgram,
+    texture: Rc<Texture>,
+    buffer: Buffer,
+    vertex_array: VertexArray,
 }
 
 fn create_light() -> PointLight {
@@ -109,10 +331,30 @@ fn create_light() -> PointLight {
     PointLight::new(ambient, diffuse, specular, specular_exponent, light_pos)
 }
 
-fn create_camera(width: f32, height: f32) -> Camera {
-    let near = 0.1;
-    let far = 100.0;
-    let fov = Degrees(67.0);
+/// The projection parameters the camera starts out with.
+#[derive(Copy, Clone, Debug)]
+struct ProjectionSettings {
+    projection: Projection,
+    near: f32,
+    far: f32,
+    fov: f32,
+}
+
+impl ProjectionSettings {
+    fn new() -> ProjectionSettings {
+        ProjectionSettings {
+            projection: Projection::Perspective,
+            near: 0.1,
+            far: 100.0,
+            fov: 67.0,
+        }
+    }
+}
+
+fn create_camera(width: f32, height: f32, settings: &ProjectionSettings) -> Camera {
+    let near = settings.near;
+    let far = settings.far;
+    let fov = Degrees(settings.fov);
     let aspect = width / height;
 
     let cam_speed: GLfloat = 5.0;
@@ -125,37 +367,11 @@ fn create_camera(width: f32, height: f32) -> Camera {
 
     let axis = Quaternion::new(0.0, 0.0, 0.0, -1.0);
 
-    Camera::new(near, far, fov, aspect, cam_speed, cam_yaw_speed, cam_pos, fwd, rgt, up, axis)
-}
-
-/// Load texture image into the GPU.
-fn load_texture(tex_data: &TexImage2D, wrapping_mode: GLuint) -> Result<TextureHandle, String> {
-    let mut tex = 0;
-    unsafe {
-        gl::GenTextures(1, &mut tex);
-        gl::ActiveTexture(gl::TEXTURE0);
-        gl::BindTexture(gl::TEXTURE_2D, tex);
-        gl::TexImage2D(
-            gl::TEXTURE_2D, 0, gl::RGBA as i32, tex_data.width as i32, tex_data.height as i32, 0,
-            gl::RGBA, gl::UNSIGNED_BYTE,
-            tex_data.as_ptr() as *const GLvoid
-        );
-        gl::GenerateMipmap(gl::TEXTURE_2D);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrapping_mode as GLint);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrapping_mode as GLint);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
-        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as GLint);
-    }
-    assert!(tex > 0);
-
-    let mut max_aniso = 0.0;
-    unsafe {
-        gl::GetFloatv(GL_MAX_TEXTURE_MAX_ANISOTROPY_EXT, &mut max_aniso);
-        // Set the maximum!
-        gl::TexParameterf(gl::TEXTURE_2D, GL_TEXTURE_MAX_ANISOTROPY_EXT, max_aniso);
-    }
+    let mut camera = Camera::new(near, far, fov, aspect, cam_speed, cam_yaw_speed, cam_pos, fwd, rgt, up, axis);
+    camera.projection = settings.projection;
+    camera.update_projection();
 
-    Ok(TextureHandle::new(tex))
+    camera
 }
 
 fn create_triforce_lights(context: &mut GameContext, id: EntityID) {
@@ -166,6 +382,41 @@ fn create_triforce_lights(context: &mut GameContext, id: EntityID) {
     };
     assert!(ubo_index != gl::INVALID_INDEX);
 
+    let buffer = create_light_block(shader, ubo_index, &context.light);
+
+    let mut ubo = 0;
+    unsafe {
+        gl::GenBuffers(1, &mut ubo);
+        gl::BindBuffer(gl::UNIFORM_BUFFER, ubo);
+        gl::BufferData(
+            gl::UNIFORM_BUFFER, buffer.len() as GLsizeiptr,
+            buffer.as_ptr() as *const GLvoid, gl::STATIC_DRAW
+        );
+        gl::BindBufferBase(gl::UNIFORM_BUFFER, ubo_index, ubo);
+    }
+    assert!(ubo > 0);
+
+    context.entities.light_buffers.insert(id, Buffer::new(ubo));
+}
+
+/// The members of the `PointLight` uniform block, in the order `create_light_block`
+/// fills them in.
+const LIGHT_BLOCK_UNIFORMS: [&str; 10] = [
+    "PointLight.La",
+    "PointLight.Ld",
+    "PointLight.Ls",
+    "PointLight.specular_exponent",
+    "PointLight.pos_wor",
+    "PointLight.intensity",
+    "PointLight.range",
+    "PointLight.spot_dir_wor",
+    "PointLight.spot_inner_cos",
+    "PointLight.spot_outer_cos",
+];
+
+/// Lay out the light parameters the way the `PointLight` uniform block of a shader
+/// program expects them.
+fn create_light_block(shader: GLuint, ubo_index: GLuint, light: &PointLight) -> Vec<u8> {
     let mut ubo_size = 0;
     unsafe {
         gl::GetActiveUniformBlockiv(
@@ -174,62 +425,87 @@ fn create_triforce_lights(context: &mut GameContext, id: EntityID) {
     };
     assert!(ubo_size > 0);
 
-    let light = &context.light;
-
-    let mut indices = [0; 5];
-    let mut sizes = [0; 5];
-    let mut offsets = [0; 5];
-    let mut types = [0; 5];
+    // The driver lists the active uniforms of a block in whatever order it likes,
+    // so look each member up by name.
+    let names: Vec<CString> = LIGHT_BLOCK_UNIFORMS.iter().map(|name| glh::gl_str(name)).collect();
+    let name_ptrs: Vec<*const GLchar> = names.iter().map(|name| name.as_ptr()).collect();
+    let mut indices = [0; 10];
+    let mut offsets = [0; 10];
     unsafe {
-        gl::GetActiveUniformBlockiv(
-            shader, ubo_index,
-            gl::UNIFORM_BLOCK_ACTIVE_UNIFORM_INDICES, indices.as_mut_ptr()
-        );
+        gl::GetUniformIndices(shader, 10, name_ptrs.as_ptr(), indices.as_mut_ptr());
         gl::GetActiveUniformsiv(
-            shader, 5, indices.as_ptr() as *const u32,
+            shader, 10, indices.as_ptr(),
             gl::UNIFORM_OFFSET, offsets.as_mut_ptr()
         );
-        gl::GetActiveUniformsiv(
-            shader, 5, indices.as_ptr() as *const u32,
-            gl::UNIFORM_SIZE, sizes.as_mut_ptr()
-        );
-        gl::GetActiveUniformsiv(
-            shader, 5, indices.as_ptr() as *const u32,
-            gl::UNIFORM_TYPE, types.as_mut_ptr()
-        );
     }
+    assert!(indices.iter().all(|&index| index != gl::INVALID_INDEX));
+
+    // A range of zero and cone cosines of minus one tell the shader to leave the
+    // light unbounded.
+    let range = light.range.unwrap_or(0.0);
+    let (spot_direction, spot_inner_cos, spot_outer_cos) = match light.spot {
+        Some(spot) => {
+            let outer_cos = f32::cos(spot.outer_cone_angle);
+            // The shader fades between the cones, so keep them apart.
+            let inner_cos = f32::max(f32::cos(spot.inner_cone_angle), outer_cos + 0.0001);
+            (spot.direction, inner_cos, outer_cos)
+        }
+        None => (Vector3::new(0.0, 0.0, -1.0), -1.0, -1.0),
+    };
 
-    let mut buffer = vec![0 as u8; ubo_size as usize];
-    unsafe {
-        ptr::copy(&light.ambient, mem::transmute(&mut buffer[offsets[0] as usize]), 1);
-        ptr::copy(&light.diffuse, mem::transmute(&mut buffer[offsets[1] as usize]), 1);
-        ptr::copy(&light.specular, mem::transmute(&mut buffer[offsets[2] as usize]), 1);
-        ptr::copy(&light.specular_exponent, mem::transmute(&mut buffer[offsets[3] as usize]), 1);
-        ptr::copy(&light.position, mem::transmute(&mut buffer[offsets[4] as usize]), 1);
-    }
+    let mut buffer = vec![0_u8; ubo_size as usize];
+    write_block_member(&mut buffer, offsets[0], &light.ambient);
+    write_block_member(&mut buffer, offsets[1], &light.diffuse);
+    write_block_member(&mut buffer, offsets[2], &light.specular);
+    write_block_member(&mut buffer, offsets[3], &light.specular_exponent);
+    write_block_member(&mut buffer, offsets[4], &light.position);
+    write_block_member(&mut buffer, offsets[5], &light.intensity);
+    write_block_member(&mut buffer, offsets[6], &range);
+    write_block_member(&mut buffer, offsets[7], &spot_direction);
+    write_block_member(&mut buffer, offsets[8], &spot_inner_cos);
+    write_block_member(&mut buffer, offsets[9], &spot_outer_cos);
+
+    buffer
+}
 
-    let mut ubo = 0;
+/// Copy the bytes of `value` into a uniform block buffer at `offset`. The buffer
+/// holds plain bytes, so the copy makes no assumptions about alignment.
+fn write_block_member<T: Copy>(buffer: &mut [u8], offset: GLint, value: &T) {
+    let size = mem::size_of::<T>();
+    let member = &mut buffer[(offset as usize)..(offset as usize + size)];
     unsafe {
-        gl::GenBuffers(1, &mut ubo);
-        gl::BindBuffer(gl::UNIFORM_BUFFER, ubo);
-        gl::BufferData(
-            gl::UNIFORM_BUFFER, ubo_size as GLsizeiptr,
-            buffer.as_ptr() as *const GLvoid, gl::STATIC_DRAW
-        );
-        gl::BindBufferBase(gl::UNIFORM_BUFFER, ubo_index, ubo);
+        ptr::copy_nonoverlapping(value as *const T as *const u8, member.as_mut_ptr(), size);
     }
-    assert!(ubo > 0);
+}
 
-    let ubo_handle = BufferHandle::new(ubo, 0);
-    let mut buffers = (context.entities.buffers[&id]).clone();
-    buffers.push(ubo_handle);
-    context.entities.buffers.insert(id, buffers);
+/// Send the light parameters to every shader program that uses them.
+fn update_lights(context: &GameContext) {
+    for (id, shader) in context.entities.shaders.iter() {
+        let shader = shader.handle.into();
+        let ubo_index = unsafe {
+            gl::GetUniformBlockIndex(shader, glh::gl_str("PointLight").as_ptr())
+        };
+        if ubo_index == gl::INVALID_INDEX {
+            continue;
+        }
+        let ubo = match context.entities.light_buffers.get(id) {
+            Some(ubo) => ubo.vbo,
+            None => continue,
+        };
+        let buffer = create_light_block(shader, ubo_index, &context.light);
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, ubo);
+            gl::BufferSubData(
+                gl::UNIFORM_BUFFER, 0, buffer.len() as GLsizeiptr, buffer.as_ptr() as *const GLvoid
+            );
+        }
+    }
 }
 
 fn arr_to_vec(ptr: *const u8, length: usize) -> Vec<u8> {
-    let mut vec = vec![0 as u8; length];
-    for i in 0..length {
-        vec[i] = unsafe { *((ptr as usize + i) as *const u8) };
+    let mut vec = vec![0_u8; length];
+    for (i, byte) in vec.iter_mut().enumerate() {
+        *byte = unsafe { *((ptr as usize + i) as *const u8) };
     }
 
     vec
@@ -284,23 +560,49 @@ fn create_ground_plane_geometry(context: &mut GameContext, id: EntityID) {
     }
     assert!(vao > 0);
 
-    let points_handle = BufferHandle::new(points_vbo, vao);
-    let tex_coords_handle = BufferHandle::new(tex_coords_vbo, vao);
-    let model_mat = Matrix4::identity();
+    let points_buffer = Buffer::new(points_vbo);
+    let tex_coords_buffer = Buffer::new(tex_coords_vbo);
 
-    context.entities.buffers.insert(id, vec![points_handle, tex_coords_handle]);
-    context.entities.model_matrices.insert(id, model_mat);
+    context.entities.buffers.insert(id, vec![points_buffer, tex_coords_buffer]);
+    context.entities.vertex_arrays.insert(id, VertexArray::new(vao));
+    if let Some(aabb) = Aabb::from_points(&mesh.points) {
+        context.entities.bounds.insert(id, aabb);
+    }
     context.entities.meshes.insert(id, mesh);
 }
 
-fn create_ground_plane_texture(context: &mut GameContext, id: EntityID) {
-    let arr: &'static [u8; 1789] = include_asset!("ground_plane.png");
-    let vec = arr_to_vec(&arr[0], 1789);
-    let result = teximage2d::load_from_memory(&vec).unwrap();
-    let tex_image = result.image;
-    let tex = load_texture(&tex_image, gl::CLAMP_TO_EDGE).unwrap();
+/// Load the ground plane texture, either from an image file or else from the built-in
+/// image. DDS and KTX2 files load as block compressed textures with their own mipmaps.
+fn create_ground_plane_texture(context: &mut GameContext, id: EntityID, file: Option<&str>) {
+    let sampler = SamplerSettings::new(gl::CLAMP_TO_EDGE);
+    let (tex, name) = match file {
+        Some(file) => {
+            let extension = Path::new(file).extension().and_then(|extension| extension.to_str());
+            let result = match extension {
+                Some("dds") | Some("ktx2") => context.textures.load_compressed_file(&context.gl, file, sampler),
+                _ => context.textures.load_file(&context.gl, file, TextureFormat::Rgba, sampler),
+            };
+            match result {
+                Ok(val) => (val, file),
+                Err(e) => {
+                    eprintln!("Failed to load the ground plane texture. Got error:");
+                    eprintln!("{}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        None => {
+            let arr: &'static [u8; 1789] = include_asset!("ground_plane.png");
+            let vec = arr_to_vec(&arr[0], 1789);
+            let tex = context.textures.load_from_memory(
+                &context.gl, "ground_plane.png", &vec, TextureFormat::Rgb, sampler
+            ).unwrap();
+            (tex, "ground_plane.png")
+        }
+    };
 
     context.entities.textures.insert(id, tex);
+    context.entities.materials.insert(id, format!("texture {}", name));
 }
 
 fn create_ground_plane_shaders(context: &mut GameContext, id: EntityID) {
@@ -328,6 +630,16 @@ fn create_ground_plane_shaders(context: &mut GameContext, id: EntityID) {
     };
     assert!(sp_proj_mat_loc > -1);
 
+    let sp_render_mode_loc = unsafe {
+        gl::GetUniformLocation(sp, glh::gl_str("render_mode").as_ptr())
+    };
+    assert!(sp_render_mode_loc > -1);
+
+    let sp_depth_range_loc = unsafe {
+        gl::GetUniformLocation(sp, glh::gl_str("depth_range").as_ptr())
+    };
+    assert!(sp_depth_range_loc > -1);
+
     let mut shader = ShaderProgram::new(ShaderProgramHandle::from(sp));
     shader.uniforms.insert(
         String::from("model_mat"), ShaderUniformHandle::from(sp_model_mat_loc)
@@ -338,6 +650,12 @@ fn create_ground_plane_shaders(context: &mut GameContext, id: EntityID) {
     shader.uniforms.insert(
         String::from("proj_mat"), ShaderUniformHandle::from(sp_proj_mat_loc)
     );
+    shader.uniforms.insert(
+        String::from("render_mode"), ShaderUniformHandle::from(sp_render_mode_loc)
+    );
+    shader.uniforms.insert(
+        String::from("depth_range"), ShaderUniformHandle::from(sp_depth_range_loc)
+    );
 
     context.entities.shaders.insert(id, shader);
 }
@@ -358,12 +676,20 @@ fn create_ground_plane_uniforms(context: &GameContext, id: EntityID) {
             shader.uniforms["proj_mat"].into(), 1, gl::FALSE,
             context.camera.proj_mat.as_ptr()
         );
+        gl::Uniform1i(shader.uniforms["render_mode"].into(), context.render_mode.shader_mode());
+        gl::Uniform2f(shader.uniforms["depth_range"].into(), context.camera.near, context.camera.far);
     }
 }
 
 /// Load the geometry for the triforce.
-fn create_triforce_geometry(context: &mut GameContext, id: EntityID, model_mat: Matrix4<f32>) {
+fn create_triforce_geometry(context: &mut GameContext, id: EntityID) {
     let mesh = include_code!("triangle.obj.in");
+    create_mesh_geometry(context, id, mesh);
+}
+
+/// Load a mesh with points, texture coordinates and normals into the GPU, for
+/// drawing with the triforce shader program.
+fn create_mesh_geometry(context: &mut GameContext, id: EntityID, mesh: obj::ObjMesh) {
     let shader = context.entities.shaders[&id].handle.into();
 
     let points_loc = unsafe { gl::GetAttribLocation(shader, glh::gl_str("v_pos").as_ptr()) };
@@ -430,12 +756,15 @@ fn create_triforce_geometry(context: &mut GameContext, id: EntityID, model_mat:
     }
     assert!(vao > 0);
 
-    let points_handle = BufferHandle::new(points_vbo, vao);
-    let tex_coords_handle = BufferHandle::new(tex_coords_vbo, vao);
-    let normals_handle = BufferHandle::new(normals_vbo, vao);
+    let points_buffer = Buffer::new(points_vbo);
+    let tex_coords_buffer = Buffer::new(tex_coords_vbo);
+    let normals_buffer = Buffer::new(normals_vbo);
 
-    context.entities.buffers.insert(id, vec![points_handle, tex_coords_handle, normals_handle]);
-    context.entities.model_matrices.insert(id, model_mat);
+    context.entities.buffers.insert(id, vec![points_buffer, tex_coords_buffer, normals_buffer]);
+    context.entities.vertex_arrays.insert(id, VertexArray::new(vao));
+    if let Some(aabb) = Aabb::from_points(&mesh.points) {
+        context.entities.bounds.insert(id, aabb);
+    }
     context.entities.meshes.insert(id, mesh);
 }
 
@@ -464,6 +793,31 @@ fn create_triforce_shaders(context: &mut GameContext, id: EntityID) {
     };
     assert!(sp_proj_mat_loc > -1);
 
+    let sp_env_map_loc = unsafe {
+        gl::GetUniformLocation(sp, glh::gl_str("env_map").as_ptr())
+    };
+    assert!(sp_env_map_loc > -1);
+
+    let sp_env_reflectivity_loc = unsafe {
+        gl::GetUniformLocation(sp, glh::gl_str("env_reflectivity").as_ptr())
+    };
+    assert!(sp_env_reflectivity_loc > -1);
+
+    let sp_render_mode_loc = unsafe {
+        gl::GetUniformLocation(sp, glh::gl_str("render_mode").as_ptr())
+    };
+    assert!(sp_render_mode_loc > -1);
+
+    let sp_depth_range_loc = unsafe {
+        gl::GetUniformLocation(sp, glh::gl_str("depth_range").as_ptr())
+    };
+    assert!(sp_depth_range_loc > -1);
+
+    let sp_base_color_loc = unsafe {
+        gl::GetUniformLocation(sp, glh::gl_str("base_color").as_ptr())
+    };
+    assert!(sp_base_color_loc > -1);
+
     let mut shader = ShaderProgram::new(ShaderProgramHandle::from(sp));
     shader.uniforms.insert(
         String::from("model_mat"), ShaderUniformHandle::from(sp_model_mat_loc)
@@ -474,6 +828,21 @@ fn create_triforce_shaders(context: &mut GameContext, id: EntityID) {
     shader.uniforms.insert(
         String::from("proj_mat"), ShaderUniformHandle::from(sp_proj_mat_loc)
     );
+    shader.uniforms.insert(
+        String::from("env_map"), ShaderUniformHandle::from(sp_env_map_loc)
+    );
+    shader.uniforms.insert(
+        String::from("env_reflectivity"), ShaderUniformHandle::from(sp_env_reflectivity_loc)
+    );
+    shader.uniforms.insert(
+        String::from("render_mode"), ShaderUniformHandle::from(sp_render_mode_loc)
+    );
+    shader.uniforms.insert(
+        String::from("depth_range"), ShaderUniformHandle::from(sp_depth_range_loc)
+    );
+    shader.uniforms.insert(
+        String::from("base_color"), ShaderUniformHandle::from(sp_base_color_loc)
+    );
 
     context.entities.shaders.insert(id, shader);
 }
@@ -482,11 +851,17 @@ fn create_triforce_shaders(context: &mut GameContext, id: EntityID) {
 fn create_triforce_texture(context: &mut GameContext, id: EntityID) {
     let arr: &'static [u8; 213] = include_asset!("triangle.png");
     let vec = arr_to_vec(&arr[0], 213);
-    let result = teximage2d::load_from_memory(&vec).unwrap();
-    let tex_image = result.image;
-    let tex = load_texture(&tex_image, gl::CLAMP_TO_EDGE).unwrap();
+    // Every triforce piece uses the same image, so the texture manager hands back the
+    // same GL texture for all of them.
+    let tex = context.textures.load_from_memory(
+        &context.gl, "triangle.png", &vec,
+        TextureFormat::Rgb, SamplerSettings::new(gl::CLAMP_TO_EDGE)
+    ).unwrap();
 
     context.entities.textures.insert(id, tex);
+    context.entities.materials.insert(
+        id, format!("texture triangle.png, environment reflectivity {}", ENV_REFLECTIVITY)
+    );
 }
 
 /// Send the uniform variables for a triforce to the GPU.
@@ -506,348 +881,1646 @@ fn create_triforce_uniforms(context: &GameContext, id: EntityID) {
             shader.uniforms["proj_mat"].into(), 1, gl::FALSE,
             context.camera.proj_mat.as_ptr()
         );
+        // The environment map is bound to texture unit 1; the surface texture sits on unit 0.
+        gl::Uniform1i(shader.uniforms["env_map"].into(), 1);
+        gl::Uniform1f(shader.uniforms["env_reflectivity"].into(), ENV_REFLECTIVITY);
+        gl::Uniform1i(shader.uniforms["render_mode"].into(), context.render_mode.shader_mode());
+        gl::Uniform2f(shader.uniforms["depth_range"].into(), context.camera.near, context.camera.far);
+        gl::Uniform4f(shader.uniforms["base_color"].into(), 1.0, 1.0, 1.0, 1.0);
     }
 }
 
-/// Reset the position of the camera to the default position and orientation.
-fn reset_camera_to_default(context: &mut GameContext) {
-    let width = context.gl.width as f32;
-    let height = context.gl.height as f32;
-    context.camera = create_camera(width, height);
-}
+/// Load the skybox shader program.
+fn create_skybox_shaders(context: &mut GameContext, id: EntityID) {
+    let mut vert_reader = io::Cursor::new(include_shader!("skybox.vert.glsl"));
+    let mut frag_reader = io::Cursor::new(include_shader!("skybox.frag.glsl"));
+    let sp = glh::create_program_from_reader(
+        &context.gl,
+        &mut vert_reader, "skybox.vert.glsl",
+        &mut frag_reader, "skybox.frag.glsl"
+    ).unwrap();
+    assert!(sp > 0);
 
-/// The GLFW frame buffer size callback function. This is normally set using 
-/// the GLFW `glfwSetFramebufferSizeCallback` function, but instead we explicitly
-/// handle window resizing in our state updates on the application side. Run this function 
-/// whenever the size of the viewport changes.
-#[inline]
-fn glfw_framebuffer_size_callback(context: &mut GameContext, width: u32, height: u32) {
-    context.gl.width = width;
-    context.gl.height = height;
+    let sp_view_mat_loc = unsafe {
+        gl::GetUniformLocation(sp, glh::gl_str("view_mat").as_ptr())
+    };
+    assert!(sp_view_mat_loc > -1);
 
-    let aspect = context.gl.width as f32 / context.gl.height as f32;
-    context.camera.aspect = aspect;
-    context.camera.proj_mat = Matrix4::from_perspective_fov(
-        context.camera.fov, aspect, context.camera.near, context.camera.far
+    let sp_proj_mat_loc = unsafe {
+        gl::GetUniformLocation(sp, glh::gl_str("proj_mat").as_ptr())
+    };
+    assert!(sp_proj_mat_loc > -1);
+
+    let mut shader = ShaderProgram::new(ShaderProgramHandle::from(sp));
+    shader.uniforms.insert(
+        String::from("view_mat"), ShaderUniformHandle::from(sp_view_mat_loc)
+    );
+    shader.uniforms.insert(
+        String::from("proj_mat"), ShaderUniformHandle::from(sp_proj_mat_loc)
     );
-}
 
-/// Initialize the logger.
-fn init_logger(log_file: &str) {
-    eprintln!("Logging is stored in file: {}", log_file);
-    file_logger::init(log_file).expect("Failed to initialize logger.");
-    info!("OpenGL application log.");
-    info!("build version: ??? ?? ???? ??:??:??\n\n");
+    context.entities.shaders.insert(id, shader);
 }
 
-/// Initialize the demo.
-fn init_game_state(ids: &[EntityID]) -> GameContext {
-    init_logger(LOG_FILE);
-    let gl_state = match glh::start_gl(720, 480) {
-        Ok(val) => val,
-        Err(e) => {
-            eprintln!("Failed to Initialize OpenGL context. Got error:");
-            eprintln!("{}", e);
-            process::exit(1);
-        }
-    };
+/// Load the geometry for the skybox.
+fn create_skybox_geometry(context: &mut GameContext, id: EntityID) {
+    let shader = context.entities.shaders[&id].handle.into();
 
-    let camera = create_camera(gl_state.width as f32, gl_state.height as f32);
-    let light = create_light();
-    let mut context = GameContext {
-        gl: gl_state,
-        camera: camera,
-        light: light,
-        entities: EntityDatabase::new(),
-    };
+    let points_loc = unsafe { gl::GetAttribLocation(shader, glh::gl_str("v_pos").as_ptr()) };
+    assert!(points_loc > -1);
+    let points_loc = points_loc as u32;
 
-    let model_mats = [
-        Matrix4::from_affine_scale(2.0) * Matrix4::from_affine_angle_z(Degrees(180.0)) * Matrix4::from_affine_translation(&Vector3::new( 0.0,       0.5, 2.0)),
-        Matrix4::from_affine_scale(2.0) * Matrix4::from_affine_angle_z(Degrees(180.0)) * Matrix4::from_affine_translation(&Vector3::new(-0.577350, -0.5, 2.0)),
-        Matrix4::from_affine_scale(2.0) * Matrix4::from_affine_angle_z(Degrees(180.0)) * Matrix4::from_affine_translation(&Vector3::new( 0.577350, -0.5, 2.0)),
-    ];
+    let mut points_vbo = 0;
+    unsafe {
+        gl::GenBuffers(1, &mut points_vbo);
+        gl::BindBuffer(gl::ARRAY_BUFFER, points_vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER,
+            (mem::size_of::<GLfloat>() * SKYBOX_POINTS.len()) as GLsizeiptr,
+            SKYBOX_POINTS.as_ptr() as *const GLvoid, gl::STATIC_DRAW
+        );
+    }
+    assert!(points_vbo > 0);
 
-    create_ground_plane_shaders(&mut context, ids[0]);
-    create_ground_plane_geometry(&mut context, ids[0]);
-    create_ground_plane_uniforms(&context, ids[0]);
-    create_ground_plane_texture(&mut context, ids[0]);
-    create_triforce_shaders(&mut context, ids[1]);
-    create_triforce_geometry(&mut context, ids[1], model_mats[0]);
-    create_triforce_uniforms(&mut context, ids[1]);
-    create_triforce_texture(&mut context, ids[1]);
-    create_triforce_lights(&mut context, ids[1]);
-    create_triforce_shaders(&mut context, ids[2]);
-    create_triforce_geometry(&mut context, ids[2], model_mats[1]);
-    create_triforce_uniforms(&mut context, ids[2]);
-    create_triforce_texture(&mut context, ids[2]);
-    create_triforce_lights(&mut context, ids[2]);
-    create_triforce_shaders(&mut context, ids[3]);
-    create_triforce_geometry(&mut context, ids[3], model_mats[2]);
-    create_triforce_uniforms(&mut context, ids[3]);
-    create_triforce_texture(&mut context, ids[3]);
-    create_triforce_lights(&mut context, ids[3]);
+    let mut vao = 0;
+    unsafe {
+        gl::GenVertexArrays(1, &mut vao);
+        gl::BindVertexArray(vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, points_vbo);
+        gl::VertexAttribPointer(points_loc, 3, gl::FLOAT, gl::FALSE, 0, ptr::null());
+        gl::EnableVertexAttribArray(points_loc);
+    }
+    assert!(vao > 0);
 
-    context
+    context.entities.buffers.insert(id, vec![Buffer::new(points_vbo)]);
+    context.entities.vertex_arrays.insert(id, VertexArray::new(vao));
 }
 
-fn main() {
-    let ids = [EntityID::new(0), EntityID::new(1), EntityID::new(2), EntityID::new(3)];
-    let mut context = init_game_state(&ids);
+/// Load the skybox cube map, either from six face images or else from the built-in
+/// equirectangular panorama.
+fn create_skybox_texture(context: &mut GameContext, id: EntityID, faces: Option<&[TexImage2D]>) {
+    let tex = match faces {
+        Some(faces) => match skybox::load_cube_map(&context.gl, faces) {
+            Ok(val) => val,
+            Err(e) => {
+                eprintln!("Failed to load the skybox. Got error:");
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        None => {
+            let arr: &'static [u8; 1631] = include_asset!("skybox.png");
+            let vec = arr_to_vec(&arr[0], 1631);
+            let result = teximage2d::load_from_memory(&vec).unwrap();
+            skybox::load_cube_map_from_equirectangular(&context.gl, &result.image, SKYBOX_FACE_SIZE).unwrap()
+        }
+    };
 
-    // Triforce animation parameters.
-    let v_triforce: f32 = 5.0; // Meters per second.
-    let mut vhat_triforce = Vector3::new(1.0, 0.0, 0.0);
-    let mut position_triforce = 0.0;
-    let mut direction = 1.0;
+    context.entities.textures.insert(id, Rc::new(tex));
+}
 
+/// Send the uniform variables for the skybox to the GPU.
+fn create_skybox_uniforms(context: &GameContext, id: EntityID) {
+    let shader = &context.entities.shaders[&id];
     unsafe {
-        // Enable depth testing.
-        gl::Enable(gl::DEPTH_TEST);
-        gl::DepthFunc(gl::LESS);
-        gl::Enable(gl::CULL_FACE);
-        gl::CullFace(gl::BACK);
-        gl::FrontFace(gl::CCW);
-        // Gray background.
-        gl::ClearColor(0.2, 0.2, 0.2, 1.0);
-        gl::Viewport(0, 0, context.gl.width as i32, context.gl.height as i32);
+        gl::UseProgram(shader.handle.into());
+        gl::UniformMatrix4fv(
+            shader.uniforms["view_mat"].into(), 1, gl::FALSE,
+            context.camera.view_mat.as_ptr()
+        );
+        gl::UniformMatrix4fv(
+            shader.uniforms["proj_mat"].into(), 1, gl::FALSE,
+            context.camera.proj_mat.as_ptr()
+        );
     }
+}
 
-    /* --------------------------- GAME LOOP ------------------------------- */
-    while !context.gl.window.should_close() {
-        // Check input.
-        let elapsed_seconds = glh::update_timers(&mut context.gl);
-
-        // Update the game world.
-        glh::update_fps_counter(&mut context.gl);
+/// Build the transform hierarchy for the scene. The triforce pieces hang off of a
+/// single parent node that carries the rotation and scale of the whole triforce, so
+/// moving or rotating the triforce only touches the parent. Returns that parent.
+fn create_scene(scene: &mut SceneGraph, ids: &[EntityID]) -> SceneNodeID {
+    scene.add_node("ground_plane", None, Some(ids[0]), Transform::identity());
+
+    // Each piece used to be placed with the model matrix
+    //     scale(2) * rotate_z(180) * translate(piece_position),
+    // which factors into the rotation and scale of the parent followed by the
+    // translation of the piece.
+    let z_axis = Unit::from_value(Vector3::new(0.0, 0.0, 1.0));
+    let triforce = scene.add_node("triforce", None, None, Transform::new(
+        Vector3::new(0.0, 0.0, 0.0), Quaternion::from_axis_angle(&z_axis, Degrees(180.0)),
+        Vector3::new(2.0, 2.0, 2.0)
+    ));
+    scene.add_node(
+        "triforce_top", Some(triforce), Some(ids[1]),
+        Transform::from_translation(Vector3::new(0.0, 0.5, 2.0))
+    );
+    scene.add_node(
+        "triforce_left", Some(triforce), Some(ids[2]),
+        Transform::from_translation(Vector3::new(-0.577350, -0.5, 2.0))
+    );
+    scene.add_node(
+        "triforce_right", Some(triforce), Some(ids[3]),
+        Transform::from_translation(Vector3::new(0.577350, -0.5, 2.0))
+    );
 
-        context.gl.glfw.poll_events();
+    triforce
+}
 
-        // Camera control keys.
-        let mut cam_moved = false;
-        let mut move_to = Vector3::new(0.0, 0.0, 0.0);
-        let mut cam_yaw = 0.0;
-        let mut cam_pitch = 0.0;
-        let mut cam_roll = 0.0;
-        match context.gl.window.get_key(Key::A) {
-            Action::Press | Action::Repeat => {
-                move_to.x -= context.camera.speed * (elapsed_seconds as GLfloat);
-                cam_moved = true;
+/// Set up the material of an entity imported from glTF. The texture is the base color
+/// texture of its material, or plain white when the material has none. The base color
+/// factor tints the texture, and double sided materials switch off back face culling.
+fn create_gltf_material(
+    context: &mut GameContext, id: EntityID,
+    gltf_scene: &GltfScene, material: Option<usize>) {
+
+    let sampler = SamplerSettings::new(gl::REPEAT);
+    let base_color = material.and_then(|material| {
+        let material = &gltf_scene.materials[material];
+        let image = &gltf_scene.images[material.base_color_image?];
+        let name = format!("{} base color", material.name);
+        // glTF stores base colors in sRGB, so the GPU converts them to linear on sampling.
+        let format = if image.has_alpha { TextureFormat::SrgbAlpha } else { TextureFormat::Srgb };
+        match context.textures.load_from_pixels(
+            &context.gl, &name, (image.width, image.height), &image.pixels, format, sampler) {
+            Ok(tex) => Some(tex),
+            Err(e) => {
+                info!("{}", e);
+                None
             }
-            _ => {}
         }
-        match context.gl.window.get_key(Key::D) {
-            Action::Press | Action::Repeat => {
-                move_to.x += context.camera.speed * (elapsed_seconds as GLfloat);
-                cam_moved = true;
-            }
-            _ => {}
+    });
+    let tex = match base_color {
+        Some(tex) => tex,
+        None => {
+            let arr: &'static [u8; 68] = include_asset!("white.png");
+            let vec = arr_to_vec(&arr[0], 68);
+            context.textures.load_from_memory(
+                &context.gl, "white.png", &vec, TextureFormat::Rgba, sampler
+            ).unwrap()
         }
-        match context.gl.window.get_key(Key::Q) {
-            Action::Press | Action::Repeat => {
-                move_to.y += context.camera.speed * (elapsed_seconds as GLfloat);
-                cam_moved = true;
+    };
+    let description = match material {
+        Some(material) => {
+            let material = &gltf_scene.materials[material];
+            let [r, g, b, a] = material.base_color_factor;
+            let shader = &context.entities.shaders[&id];
+            unsafe {
+                gl::UseProgram(shader.handle.into());
+                gl::Uniform4f(shader.uniforms["base_color"].into(), r, g, b, a);
             }
-            _ => {}
-        }
-        match context.gl.window.get_key(Key::E) {
-            Action::Press | Action::Repeat => {
-                move_to.y -= context.camera.speed * (elapsed_seconds as GLfloat);
-                cam_moved = true;
+            if material.double_sided {
+                context.entities.double_sided.insert(id);
             }
-            _ => {}
+            format!(
+                "glTF material `{}`, base color ({}, {}, {}, {}), metallic {}, roughness {}, emissive ({}, {}, {})",
+                material.name, r, g, b, a, material.metallic_factor, material.roughness_factor,
+                material.emissive_factor[0], material.emissive_factor[1], material.emissive_factor[2]
+            )
         }
-        match context.gl.window.get_key(Key::W) {
-            Action::Press | Action::Repeat => {
-                move_to.z -= context.camera.speed * (elapsed_seconds as GLfloat);
-                cam_moved = true;
-            }
-            _ => {}
+        None => String::from("glTF default material"),
+    };
+
+    context.entities.textures.insert(id, tex);
+    context.entities.materials.insert(id, description);
+}
+
+/// Add the contents of an imported glTF scene to the game. The glTF node hierarchy
+/// is mirrored into the scene graph, each mesh primitive becomes an entity drawn
+/// with the triforce shader program, and the animations play on the mirrored nodes.
+/// Entity ids are handed out starting from `first_id`.
+fn create_gltf_entities(context: &mut GameContext, gltf_scene: &GltfScene, first_id: u32) {
+    let mut next_id = first_id;
+    let mut nodes = vec![];
+    let mut entities = vec![];
+    for node in gltf_scene.nodes.iter() {
+        let parent = node.parent.map(|parent| nodes[parent]);
+        let scene_node = context.scene.add_node(&node.name, parent, None, node.transform);
+        nodes.push(scene_node);
+        for (i, &primitive) in node.primitives.iter().enumerate() {
+            let id = EntityID::new(next_id);
+            next_id += 1;
+            let primitive = &gltf_scene.primitives[primitive];
+            let mesh = obj::ObjMesh::new(
+                primitive.mesh.points.clone(),
+                primitive.mesh.tex_coords.clone(),
+                primitive.mesh.normals.clone()
+            );
+            let name = format!("{} primitive {}", node.name, i);
+            context.scene.add_node(&name, Some(scene_node), Some(id), Transform::identity());
+            create_triforce_shaders(context, id);
+            create_mesh_geometry(context, id, mesh);
+            entities.push((id, primitive.material));
         }
-        match context.gl.window.get_key(Key::S) {
-            Action::Press | Action::Repeat => {
-                move_to.z += context.camera.speed * (elapsed_seconds as GLfloat);
-                cam_moved = true;
+    }
+
+    // The uniforms need the model matrices, which come out of the scene graph.
+    update_scene(context, 1.0);
+    for &(id, material) in entities.iter() {
+        create_triforce_uniforms(context, id);
+        create_gltf_material(context, id, gltf_scene, material);
+        create_triforce_lights(context, id);
+        context.gltf_entities.push(id);
+    }
+
+    for animation in gltf_scene.animations.iter() {
+        info!(
+            "Playing the animation `{}` on the node `{}`.",
+            animation.name, gltf_scene.nodes[animation.node].name
+        );
+        context.animator.attach(nodes[animation.node], animation.animation.clone());
+    }
+}
+
+/// Set up the motion of the triforce. The triforce slides along the x-axis at five
+/// meters per second, turning around whenever it gets ten meters from the origin.
+fn create_triforce_animation(context: &mut GameContext) {
+    let triforce = context.triforce;
+    let translation = Track::new(vec![
+        Keyframe::new(0.0, Vector3::new(-10.0, 0.0, 0.0)),
+        Keyframe::new(4.0, Vector3::new( 10.0, 0.0, 0.0)),
+    ], Interpolation::Linear).unwrap();
+    let animation = Animation::new(PlaybackMode::PingPong).with_translation(translation);
+
+    // Halfway through the first pass the triforce is at the origin, heading right.
+    context.animator.attach_from(triforce, animation, 2.0);
+}
+
+/// Determine whether an entity might be on screen. The bounding box of its mesh is
+/// carried into world space by its model matrix and tested against the view
+/// frustum. Entities without a bounding box are always drawn.
+fn is_visible(context: &GameContext, frustum: &Frustum, id: EntityID) -> bool {
+    let aabb = match context.entities.bounds.get(&id) {
+        Some(aabb) => aabb,
+        None => return true,
+    };
+    let world_aabb = match context.entities.model_matrices.get(&id) {
+        Some(model_mat) => aabb.transform(model_mat),
+        None => *aabb,
+    };
+
+    frustum.intersects_aabb(&world_aabb)
+}
+
+/// Recompute the world matrices of the scene graph, and send the model matrices
+/// of the entities that moved to the GPU.
+fn update_scene(context: &mut GameContext, alpha: f32) {
+    for (id, model_mat) in context.scene.update(alpha) {
+        context.entities.model_matrices.insert(id, model_mat);
+        if let Some(shader) = context.entities.shaders.get(&id) {
+            unsafe {
+                gl::UseProgram(shader.handle.into());
+                gl::UniformMatrix4fv(
+                    shader.uniforms["model_mat"].into(), 1, gl::FALSE,
+                    model_mat.as_ptr()
+                );
             }
-            _ => {}
         }
-        match context.gl.window.get_key(Key::Left) {
-            Action::Press | Action::Repeat => {
-                cam_yaw += context.camera.yaw_speed * (elapsed_seconds as GLfloat);
-                cam_moved = true;
+    }
+}
+
+/// Reset the position of the camera to the default position and orientation.
+fn reset_camera_to_default(context: &mut GameContext) {
+    let width = context.gl.width as f32;
+    let height = context.gl.height as f32;
+    context.camera = create_camera(width, height, &context.projection_settings);
+    update_projection(context);
+}
+
+/// The GLFW frame buffer size callback function. This is normally set using 
+/// the GLFW `glfwSetFramebufferSizeCallback` function, but instead we explicitly
+/// handle window resizing in our state updates on the application side. Run this function 
+/// whenever the size of the viewport changes.
+#[inline]
+fn glfw_framebuffer_size_callback(context: &mut GameContext, width: u32, height: u32) {
+    context.gl.width = width;
+    context.gl.height = height;
+
+    context.camera.aspect = context.gl.width as f32 / context.gl.height as f32;
+    update_projection(context);
+}
+
+/// Load the shader program that draws the outline around the selected entity.
+fn create_outline_shader(gl_state: &glh::GLState) -> ShaderProgram {
+    let mut vert_reader = io::Cursor::new(include_shader!("outline.vert.glsl"));
+    let mut frag_reader = io::Cursor::new(include_shader!("outline.frag.glsl"));
+    let sp = glh::create_program_from_reader(
+        gl_state,
+        &mut vert_reader, "outline.vert.glsl",
+        &mut frag_reader, "outline.frag.glsl"
+    ).unwrap();
+    assert!(sp > 0);
+
+    let mut shader = ShaderProgram::new(ShaderProgramHandle::from(sp));
+    for &name in ["proj_mat", "view_mat", "model_mat", "outline_color"].iter() {
+        let location = unsafe { gl::GetUniformLocation(sp, glh::gl_str(name).as_ptr()) };
+        assert!(location > -1);
+        shader.uniforms.insert(String::from(name), ShaderUniformHandle::from(location));
+    }
+
+    shader
+}
+
+/// Set up a vertex array that feeds the points of an entity to the outline shader.
+fn create_outline_geometry(context: &GameContext, id: EntityID) -> VertexArray {
+    let shader = context.selection.shader.handle.into();
+
+    let points_loc = unsafe { gl::GetAttribLocation(shader, glh::gl_str("v_pos").as_ptr()) };
+    assert!(points_loc > -1);
+    let points_loc = points_loc as u32;
+
+    // The points buffer always comes first among the buffers of an entity.
+    let points_vbo = context.entities.buffers[&id][0].vbo;
+    let mut vao = 0;
+    unsafe {
+        gl::GenVertexArrays(1, &mut vao);
+        gl::BindVertexArray(vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, points_vbo);
+        gl::VertexAttribPointer(points_loc, 3, gl::FLOAT, gl::FALSE, 0, ptr::null());
+        gl::EnableVertexAttribArray(points_loc);
+    }
+    assert!(vao > 0);
+
+    VertexArray::new(vao)
+}
+
+/// Find the entity under the mouse cursor. Every entity with a bounding box can be
+/// picked, and when the cursor covers more than one, the closest one wins.
+fn pick_entity(context: &GameContext) -> Option<EntityID> {
+    let (cursor_x, cursor_y) = context.gl.window.get_cursor_pos();
+    let (width, height) = context.gl.window.get_size();
+    let proj_view = context.camera.proj_mat * context.camera.view_mat;
+    let ray = Ray::from_cursor(cursor_x, cursor_y, width as f64, height as f64, &proj_view)?;
+    let mut closest: Option<(EntityID, f32)> = None;
+    for (&id, aabb) in context.entities.bounds.iter() {
+        let model_mat = match context.entities.model_matrices.get(&id) {
+            Some(model_mat) => model_mat,
+            None => continue,
+        };
+        // Only test the triangles of a mesh when the ray passes through its box
+        // closer than the closest hit so far.
+        let box_distance = match ray.intersect_aabb(&aabb.transform(model_mat)) {
+            Some(distance) => distance,
+            None => continue,
+        };
+        if let Some((_, closest_distance)) = closest {
+            if box_distance >= closest_distance {
+                continue;
             }
-            _ => {}
         }
-        match context.gl.window.get_key(Key::Right) {
-            Action::Press | Action::Repeat => {
-                cam_yaw -= context.camera.yaw_speed * (elapsed_seconds as GLfloat);
-                cam_moved = true;
+        if let Some(distance) = ray.intersect_mesh(&context.entities.meshes[&id].points, model_mat) {
+            let is_closer = match closest {
+                Some((_, closest_distance)) => distance < closest_distance,
+                None => true,
+            };
+            if is_closer {
+                closest = Some((id, distance));
             }
-            _ => {}
         }
-        match context.gl.window.get_key(Key::Up) {
-            Action::Press | Action::Repeat => {
-                cam_pitch += context.camera.yaw_speed * (elapsed_seconds as GLfloat);
-                cam_moved = true;
-            }
-            _ => {}
+    }
+
+    closest.map(|(id, distance)| {
+        let hit = ray.point_at(distance);
+        info!("Picked a point at ({}, {}, {})", hit.x, hit.y, hit.z);
+        id
+    })
+}
+
+/// Describe the transform and material of an entity.
+fn describe_entity(context: &GameContext, id: EntityID) -> String {
+    let mut description = String::new();
+    match context.scene.find_entity(id) {
+        Some(node) => {
+            let transform = context.scene.transform(node);
+            let t = transform.translation;
+            let q = transform.rotation;
+            description.push_str(&format!("Selected `{}`\n", context.scene.name(node)));
+            description.push_str(&format!("    translation: ({}, {}, {})\n", t.x, t.y, t.z));
+            description.push_str(&format!("    rotation: ({}, {}, {}, {})\n", q.s, q.v.x, q.v.y, q.v.z));
+            let s = transform.scale;
+            description.push_str(&format!("    scale: ({}, {}, {})\n", s.x, s.y, s.z));
         }
-        match context.gl.window.get_key(Key::Down) {
-            Action::Press | Action::Repeat => {
-                cam_pitch -= context.camera.yaw_speed * (elapsed_seconds as GLfloat);
-                cam_moved = true;
-            }
-            _ => {}
+        None => description.push_str("Selected an unnamed entity\n"),
+    }
+    if let Some(model_mat) = context.entities.model_matrices.get(&id) {
+        let position = *model_mat * Vector4::new(0.0, 0.0, 0.0, 1.0);
+        description.push_str(&format!(
+            "    world position: ({}, {}, {})\n", position.x, position.y, position.z
+        ));
+    }
+    if let Some(material) = context.entities.materials.get(&id) {
+        description.push_str(&format!("    material: {}\n", material));
+    }
+
+    description
+}
+
+/// Select an entity, or clear the selection with `None`, and report the transform
+/// and material of the selected entity.
+fn select_entity(context: &mut GameContext, entity: Option<EntityID>) {
+    let vertex_array = entity.map(|id| create_outline_geometry(context, id));
+    context.selection.entity = entity;
+    context.selection.vertex_array = vertex_array;
+    match entity {
+        Some(id) => {
+            let description = describe_entity(context, id);
+            info!("{}", description.trim_end());
         }
-        match context.gl.window.get_key(Key::Z) {
-            Action::Press | Action::Repeat => {
-                cam_roll -= context.camera.yaw_speed * (elapsed_seconds as GLfloat);
-                cam_moved = true;
-            }
-            _ => {}
+        None => info!("Cleared the selection"),
+    }
+}
+
+/// Draw an outline around the selected entity. The entity first marks the pixels it
+/// covers in the stencil buffer, and then a slightly larger copy of it is drawn in
+/// flat color everywhere except those pixels, leaving a rim around its silhouette.
+/// Depth testing is off for both passes, so the outline shows through whatever
+/// stands in front of the entity.
+fn render_selection_outline(context: &GameContext) {
+    let (id, vertex_array) = match (context.selection.entity, context.selection.vertex_array.as_ref()) {
+        (Some(id), Some(vertex_array)) => (id, vertex_array),
+        _ => return,
+    };
+    let model_mat = match context.entities.model_matrices.get(&id) {
+        Some(model_mat) => *model_mat,
+        None => return,
+    };
+    let center = match context.entities.bounds.get(&id) {
+        Some(aabb) => (aabb.min + aabb.max) * 0.5,
+        None => Vector3::new(0.0, 0.0, 0.0),
+    };
+    let outline_mat = model_mat *
+        Matrix4::from_affine_translation(&center) *
+        Matrix4::from_affine_scale(OUTLINE_SCALE) *
+        Matrix4::from_affine_translation(&(-center));
+    let shader = &context.selection.shader;
+    let [r, g, b, a] = OUTLINE_COLOR;
+    let count = context.entities.meshes[&id].len() as i32;
+
+    unsafe {
+        gl::UseProgram(shader.handle.into());
+        gl::UniformMatrix4fv(
+            shader.uniforms["proj_mat"].into(), 1, gl::FALSE, context.camera.proj_mat.as_ptr()
+        );
+        gl::UniformMatrix4fv(
+            shader.uniforms["view_mat"].into(), 1, gl::FALSE, context.camera.view_mat.as_ptr()
+        );
+        gl::Uniform4f(shader.uniforms["outline_color"].into(), r, g, b, a);
+        gl::BindVertexArray(vertex_array.vao);
+        gl::Disable(gl::DEPTH_TEST);
+        gl::Disable(gl::CULL_FACE);
+        gl::Enable(gl::STENCIL_TEST);
+        gl::Clear(gl::STENCIL_BUFFER_BIT);
+
+        // Mark the silhouette of the entity.
+        gl::StencilFunc(gl::ALWAYS, 1, 0xFF);
+        gl::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE);
+        gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+        gl::UniformMatrix4fv(shader.uniforms["model_mat"].into(), 1, gl::FALSE, model_mat.as_ptr());
+        gl::DrawArrays(gl::TRIANGLES, 0, count);
+
+        // Draw the enlarged copy around it.
+        gl::StencilFunc(gl::NOTEQUAL, 1, 0xFF);
+        gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+        gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+        gl::UniformMatrix4fv(shader.uniforms["model_mat"].into(), 1, gl::FALSE, outline_mat.as_ptr());
+        gl::DrawArrays(gl::TRIANGLES, 0, count);
+
+        gl::Disable(gl::STENCIL_TEST);
+        gl::Enable(gl::CULL_FACE);
+        gl::Enable(gl::DEPTH_TEST);
+    }
+}
+
+/// Set up the shader and the buffers the debug lines are drawn with.
+fn create_debug_draw(gl_state: &glh::GLState) -> DebugDraw {
+    let mut vert_reader = io::Cursor::new(include_shader!("lines.vert.glsl"));
+    let mut frag_reader = io::Cursor::new(include_shader!("lines.frag.glsl"));
+    let sp = glh::create_program_from_reader(
+        gl_state,
+        &mut vert_reader, "lines.vert.glsl",
+        &mut frag_reader, "lines.frag.glsl"
+    ).unwrap();
+    assert!(sp > 0);
+
+    let mut shader = ShaderProgram::new(ShaderProgramHandle::from(sp));
+    for &name in ["proj_mat", "view_mat"].iter() {
+        let location = unsafe { gl::GetUniformLocation(sp, glh::gl_str(name).as_ptr()) };
+        assert!(location > -1);
+        shader.uniforms.insert(String::from(name), ShaderUniformHandle::from(location));
+    }
+
+    let attributes = [("v_pos", 3, 0), ("v_color", 4, 3)];
+    let stride = mem::size_of::<LineVertex>() as i32;
+    let mut vbo = 0;
+    let mut vao = 0;
+    unsafe {
+        gl::GenBuffers(1, &mut vbo);
+        gl::GenVertexArrays(1, &mut vao);
+        gl::BindVertexArray(vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        for &(name, size, offset) in attributes.iter() {
+            let location = gl::GetAttribLocation(sp, glh::gl_str(name).as_ptr());
+            assert!(location > -1);
+            gl::VertexAttribPointer(
+                location as u32, size, gl::FLOAT, gl::FALSE, stride,
+                (offset * mem::size_of::<GLfloat>()) as *const GLvoid
+            );
+            gl::EnableVertexAttribArray(location as u32);
         }
-        match context.gl.window.get_key(Key::C) {
-            Action::Press | Action::Repeat => {
-                cam_roll += context.camera.yaw_speed * (elapsed_seconds as GLfloat);
-                cam_moved = true;
-            }
-            _ => {}
+    }
+    assert!(vbo > 0);
+    assert!(vao > 0);
+
+    DebugDraw {
+        lines: DebugLines::new(),
+        show_axes: false,
+        show_normals: false,
+        show_bounds: false,
+        show_light: false,
+        frustum: None,
+        shader: shader,
+        buffer: Buffer::new(vbo),
+        vertex_array: VertexArray::new(vao),
+    }
+}
+
+/// Gather the debug lines for this frame. The bounding boxes are the world space
+/// boxes frustum culling tests against.
+fn update_debug_draw(context: &mut GameContext) {
+    let debug_draw = &mut context.debug_draw;
+    let lines = &mut debug_draw.lines;
+    lines.clear();
+    if debug_draw.show_axes {
+        lines.draw_axes(&Matrix4::identity(), DEBUG_AXES_LENGTH);
+    }
+    for (id, aabb) in context.entities.bounds.iter() {
+        let model_mat = context.entities.model_matrices.get(id);
+        if debug_draw.show_bounds {
+            let world_aabb = match model_mat {
+                Some(model_mat) => aabb.transform(model_mat),
+                None => *aabb,
+            };
+            lines.draw_aabb(&world_aabb, DEBUG_BOUNDS_COLOR);
         }
-        match context.gl.window.get_key(Key::Backspace) {
-            Action::Press | Action::Repeat => {
-                reset_camera_to_default(&mut context);
-                cam_moved = true;
-            }
-            _ => {}
+        if debug_draw.show_normals {
+            let mesh = &context.entities.meshes[id];
+            let model_mat = model_mat.copied().unwrap_or_else(Matrix4::identity);
+            lines.draw_normals(&mesh.points, &mesh.normals, &model_mat, DEBUG_NORMAL_LENGTH, DEBUG_NORMAL_COLOR);
         }
-        match context.gl.window.get_key(Key::Escape) {
-            Action::Press | Action::Repeat => {
-                context.gl.window.set_should_close(true);
-            }
-            _ => {}
+    }
+    if let Some(proj_view) = debug_draw.frustum {
+        lines.draw_frustum(&proj_view, DEBUG_FRUSTUM_COLOR);
+    }
+    if debug_draw.show_light {
+        // The light is drawn in its diffuse color, with a set of axes through its
+        // center to pin down where exactly it is.
+        let light = &context.light;
+        let color = [light.diffuse.x, light.diffuse.y, light.diffuse.z, 1.0];
+        lines.draw_sphere(&light.position, DEBUG_LIGHT_RADIUS, color);
+        lines.draw_axes(&Matrix4::from_affine_translation(&light.position), 2.0 * DEBUG_LIGHT_RADIUS);
+    }
+}
+
+/// Draw the debug lines into the scene. The lines are depth tested like the rest of
+/// the scene, so the scene hides the parts of them behind it.
+fn render_debug_draw(context: &GameContext) {
+    let debug_draw = &context.debug_draw;
+    if debug_draw.lines.is_empty() {
+        return;
+    }
+    let vertices = debug_draw.lines.vertices();
+
+    unsafe {
+        gl::UseProgram(debug_draw.shader.handle.into());
+        gl::UniformMatrix4fv(
+            debug_draw.shader.uniforms["proj_mat"].into(), 1, gl::FALSE, context.camera.proj_mat.as_ptr()
+        );
+        gl::UniformMatrix4fv(
+            debug_draw.shader.uniforms["view_mat"].into(), 1, gl::FALSE, context.camera.view_mat.as_ptr()
+        );
+        gl::BindBuffer(gl::ARRAY_BUFFER, debug_draw.buffer.vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER, mem::size_of_val(vertices) as GLsizeiptr,
+            vertices.as_ptr() as *const GLvoid, gl::STREAM_DRAW
+        );
+        gl::BindVertexArray(debug_draw.vertex_array.vao);
+        gl::DrawArrays(gl::LINES, 0, vertices.len() as i32);
+    }
+}
+
+/// Set up the HUD and the debug overlay. The font atlas goes to the GPU as a single channel
+/// texture, and the overlay shader reads the coverage of each texel from its red
+/// channel.
+fn create_overlay(gl_state: &glh::GLState, textures: &mut TextureManager) -> Overlay {
+    let font = FontAtlas::from_ttf(include_asset!("DejaVuSansMono.ttf"), OVERLAY_FONT_SIZE).unwrap();
+
+    let mut vert_reader = io::Cursor::new(include_shader!("ui.vert.glsl"));
+    let mut frag_reader = io::Cursor::new(include_shader!("ui.frag.glsl"));
+    let sp = glh::create_program_from_reader(
+        gl_state,
+        &mut vert_reader, "ui.vert.glsl",
+        &mut frag_reader, "ui.frag.glsl"
+    ).unwrap();
+    assert!(sp > 0);
+
+    let mut shader = ShaderProgram::new(ShaderProgramHandle::from(sp));
+    for &name in ["proj_mat", "font_atlas"].iter() {
+        let location = unsafe { gl::GetUniformLocation(sp, glh::gl_str(name).as_ptr()) };
+        assert!(location > -1);
+        shader.uniforms.insert(String::from(name), ShaderUniformHandle::from(location));
+    }
+    unsafe {
+        gl::UseProgram(sp);
+        gl::Uniform1i(shader.uniforms["font_atlas"].into(), 0);
+    }
+
+    // The glyphs are drawn at the size they were rasterized at, so the atlas needs
+    // neither mipmaps nor anisotropic filtering.
+    let sampler = SamplerSettings {
+        min_filter: gl::LINEAR,
+        mipmaps: false,
+        anisotropic: false,
+        ..SamplerSettings::new(gl::CLAMP_TO_EDGE)
+    };
+    let texture = textures.load_from_pixels(
+        gl_state, "font atlas", (font.width(), font.height()), font.pixels(),
+        TextureFormat::Greyscale, sampler
+    ).unwrap();
+
+    let attributes = [("v_pos", 2, 0), ("v_tex", 2, 2), ("v_color", 4, 4)];
+    let stride = mem::size_of::<UiVertex>() as i32;
+    let mut vbo = 0;
+    let mut vao = 0;
+    unsafe {
+        gl::GenBuffers(1, &mut vbo);
+        gl::GenVertexArrays(1, &mut vao);
+        gl::BindVertexArray(vao);
+        gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+        for &(name, size, offset) in attributes.iter() {
+            let location = gl::GetAttribLocation(sp, glh::gl_str(name).as_ptr());
+            assert!(location > -1);
+            gl::VertexAttribPointer(
+                location as u32, size, gl::FLOAT, gl::FALSE, stride,
+                (offset * mem::size_of::<GLfloat>()) as *const GLvoid
+            );
+            gl::EnableVertexAttribArray(location as u32);
         }
+    }
+    assert!(vbo > 0);
+    assert!(vao > 0);
 
-        // Update view matrix.
-        if cam_moved {
-            // Update the axis of rotation of the camera.
-            let yaw_axis = Unit::from_value(context.camera.up.contract());
-            let pitch_axis = Unit::from_value(context.camera.rgt.contract());
-            let roll_axis = Unit::from_value(context.camera.fwd.contract());
-            let q_yaw = Quaternion::from_axis_angle(&yaw_axis, Degrees(cam_yaw));
-            context.camera.axis = q_yaw * &context.camera.axis;
-            let q_pitch = Quaternion::from_axis_angle(&pitch_axis, Degrees(cam_pitch));
-            context.camera.axis = q_pitch * &context.camera.axis;
-            let q_roll = Quaternion::from_axis_angle(&roll_axis, Degrees(cam_roll));
-            context.camera.axis = q_roll * &context.camera.axis;
+    Overlay {
+        ui: Ui::new(font),
+        hud: Hud::new(),
+        debug_visible: false,
+        shader: shader,
+        texture: texture,
+        buffer: Buffer::new(vbo),
+        vertex_array: VertexArray::new(vao),
+    }
+}
 
-            // Recalculate local axes so we can move fwd in the direction the camera is pointing.
-            let rot_mat_inv = Matrix4::from(context.camera.axis);
-            context.camera.fwd = rot_mat_inv * Vector4::new(0.0, 0.0, -1.0, 0.0);
-            context.camera.rgt = rot_mat_inv * Vector4::new(1.0, 0.0,  0.0, 0.0);
-            context.camera.up  = rot_mat_inv * Vector4::new(0.0, 1.0,  0.0, 0.0);
+/// Build this frame of the HUD and the debug overlay.
+fn update_overlay(context: &mut GameContext, ids: &[EntityID]) {
+    // While the mouse turns the camera, it does not work the overlay.
+    let input = if context.mouse_look.is_captured() {
+        UiInput::none()
+    } else {
+        let (cursor_x, cursor_y) = context.gl.window.get_cursor_pos();
+        let mouse_down = context.gl.window.get_mouse_button(MouseButtonLeft) == Action::Press;
+        UiInput::new([cursor_x as f32, cursor_y as f32], mouse_down)
+    };
 
-            context.camera.pos += context.camera.fwd.contract() * -move_to.z;
-            context.camera.pos += context.camera.up.contract()  *  move_to.y;
-            context.camera.pos += context.camera.rgt.contract() *  move_to.x;
+    let now = context.gl.glfw.get_time();
+    let (width, height) = context.gl.window.get_size();
+    let timer = &context.frame_timer;
+    let overlay = &mut context.overlay;
+    overlay.hud.expire_messages(now);
+    overlay.ui.begin_frame(input);
+    overlay.hud.draw(
+        &mut overlay.ui, width as f32, height as f32,
+        timer.frames_per_second(), timer.average_frame_time(), now
+    );
+    if overlay.debug_visible {
+        update_debug_panel(context, ids);
+    }
+    context.overlay.ui.end_frame();
+}
 
-            let trans_mat_inv = Matrix4::from_affine_translation(&context.camera.pos);
+/// Build the panel of the debug overlay, and apply whatever was edited in it.
+fn update_debug_panel(context: &mut GameContext, ids: &[EntityID]) {
+    let mut projection_changed = false;
+    let mut light_changed = false;
+    let mut new_selection = None;
+    let mut new_render_mode = None;
+    let ui = &mut context.overlay.ui;
+    ui.begin_panel("Debug", 10.0, 10.0, DEBUG_PANEL_WIDTH);
+
+    if ui.section("Frame") {
+        let timer = &context.frame_timer;
+        ui.value("FPS", &format!("{:.1}", timer.frames_per_second()));
+        ui.value("Frame time", &format!("{:.2} ms", 1000.0 * timer.average_frame_time()));
+        ui.value("Worst frame", &format!("{:.2} ms", 1000.0 * timer.max_frame_time()));
+        let mut paused = context.time_control.is_paused();
+        if ui.checkbox("Paused", &mut paused) {
+            context.time_control.toggle_pause();
+        }
+        let mut time_scale = context.time_control.time_scale() as f32;
+        if ui.slider("Time scale", &mut time_scale, MIN_TIME_SCALE as f32, MAX_TIME_SCALE as f32) {
+            context.time_control.set_time_scale(time_scale as f64);
+        }
+    }
 
-            context.camera.rot_mat = rot_mat_inv.inverse().unwrap();
-            context.camera.trans_mat = trans_mat_inv.inverse().unwrap();
-            context.camera.view_mat = context.camera.rot_mat * context.camera.trans_mat;
+    if ui.section("Camera") {
+        let camera = &mut context.camera;
+        let mode = context.camera_controller.mode();
+        ui.value("Mode", &format!("{:?}", mode));
+        ui.value("Projection", &format!("{:?}", camera.projection));
+        // The orbit camera works out its own position, so the position can only be
+        // edited in free-fly mode.
+        let mut position = [camera.pos.x, camera.pos.y, camera.pos.z];
+        if ui.drag3("Position", &mut position, 0.05) && mode == CameraMode::FreeFly {
+            let mut pose = camera.pose();
+            pose.pos = Vector3::new(position[0], position[1], position[2]);
+            camera.set_pose(pose);
+        }
+        projection_changed |= ui.slider("Field of view", &mut camera.fov.0, MIN_FOV, MAX_FOV);
+        // The near plane stays in front of the far plane, and the far plane behind
+        // the near plane.
+        if ui.drag("Near plane", &mut camera.near, 0.01) {
+            camera.near = f32::min(f32::max(camera.near, 0.01), camera.far - 0.01);
+            projection_changed = true;
+        }
+        if ui.drag("Far plane", &mut camera.far, 0.5) {
+            camera.far = f32::max(camera.far, camera.near + 0.01);
+            projection_changed = true;
+        }
+    }
 
-            let gp_sp = &context.entities.shaders[&ids[0]];
-            let gp_view_mat_loc = gp_sp.uniforms["view_mat"];
-            unsafe {
-                gl::UseProgram(gp_sp.handle.into());
-                gl::UniformMatrix4fv(
-                    gp_view_mat_loc.into(), 1, gl::FALSE,
-                    context.camera.view_mat.as_ptr()
+    if ui.section("Light") {
+        let light = &mut context.light;
+        let mut position = [light.position.x, light.position.y, light.position.z];
+        if ui.drag3("Position", &mut position, 0.05) {
+            light.position = Vector3::new(position[0], position[1], position[2]);
+            light_changed = true;
+        }
+        for (label, color) in [
+            ("Ambient", &mut light.ambient), ("Diffuse", &mut light.diffuse), ("Specular", &mut light.specular)
+        ].iter_mut() {
+            let mut rgb = [color.x, color.y, color.z];
+            if ui.drag3(label, &mut rgb, 0.005) {
+                **color = Vector3::new(
+                    f32::max(rgb[0], 0.0), f32::max(rgb[1], 0.0), f32::max(rgb[2], 0.0)
                 );
+                light_changed = true;
             }
+        }
+        light_changed |= ui.slider("Shininess", &mut light.specular_exponent, 1.0, 256.0);
+        light_changed |= ui.slider("Intensity", &mut light.intensity, 0.0, 10.0);
+    }
 
-            let tri_sp1 = &context.entities.shaders[&ids[1]];
-            let tri_sp_view_mat_loc1 = tri_sp1.uniforms["view_mat"];
-            unsafe {
-                gl::UseProgram(tri_sp1.handle.into());
-                gl::UniformMatrix4fv(
-                    tri_sp_view_mat_loc1.into(), 1, gl::FALSE,
-                    context.camera.view_mat.as_ptr()
-                );
+    if ui.section("Debug drawing") {
+        if ui.button(&format!("Render mode: {:?}", context.render_mode)) {
+            new_render_mode = Some(context.render_mode.next());
+        }
+        let debug_draw = &mut context.debug_draw;
+        ui.checkbox("World axes", &mut debug_draw.show_axes);
+        ui.checkbox("Normals", &mut debug_draw.show_normals);
+        ui.checkbox("Bounding boxes", &mut debug_draw.show_bounds);
+        ui.checkbox("Light", &mut debug_draw.show_light);
+        let mut show_frustum = debug_draw.frustum.is_some();
+        if ui.checkbox("Camera frustum", &mut show_frustum) {
+            debug_draw.frustum = if show_frustum {
+                Some(context.camera.proj_mat * context.camera.view_mat)
+            } else {
+                None
+            };
+        }
+    }
+
+    if ui.section("Entities") {
+        // Closures borrow the whole context, so the bounds are borrowed on their own
+        // to leave the scene free to change.
+        let bounds = &context.entities.bounds;
+        let pickable = ids.iter().filter(|id| bounds.contains_key(*id));
+        for (i, &id) in pickable.chain(context.gltf_entities.iter()).enumerate() {
+            let node = context.scene.find_entity(id);
+            // Nodes are indented under their parents, and nodes with children say how
+            // many they have.
+            let name = match node {
+                Some(node) => {
+                    let mut depth = 0;
+                    let mut ancestor = context.scene.parent(node);
+                    while let Some(parent) = ancestor {
+                        depth += 1;
+                        ancestor = context.scene.parent(parent);
+                    }
+                    let children = context.scene.children(node).len();
+                    let indent = "  ".repeat(depth);
+                    if children > 0 {
+                        format!("{}{} ({} children)", indent, context.scene.name(node), children)
+                    } else {
+                        format!("{}{}", indent, context.scene.name(node))
+                    }
+                }
+                None => String::from("unnamed"),
+            };
+            let selected = context.selection.entity == Some(id);
+            let marker = if selected { "*" } else { " " };
+            if ui.button(&format!("{} {}. {}", marker, i, name)) {
+                new_selection = Some(if selected { None } else { Some(id) });
             }
+            if let Some(node) = node.filter(|_| selected) {
+                let mut transform = context.scene.transform(node);
+                let t = transform.translation;
+                let mut translation = [t.x, t.y, t.z];
+                let s = transform.scale;
+                let mut scale = [s.x, s.y, s.z];
+                let mut changed = ui.drag3("Translation", &mut translation, 0.05);
+                changed |= ui.drag3("Scale", &mut scale, 0.01);
+                if changed {
+                    transform.translation = Vector3::new(translation[0], translation[1], translation[2]);
+                    transform.scale = Vector3::new(
+                        f32::max(scale[0], 0.01), f32::max(scale[1], 0.01), f32::max(scale[2], 0.01)
+                    );
+                    context.scene.set_transform(node, transform);
+                }
+                if let Some(material) = context.entities.materials.get(&id) {
+                    ui.label(&format!("Material: {}", material));
+                }
+            }
+        }
+    }
 
-            let tri_sp2 = &context.entities.shaders[&ids[2]];
-            let tri_sp_view_mat_loc2 = tri_sp2.uniforms["view_mat"];
+    ui.end_panel();
+
+    if projection_changed {
+        update_projection(context);
+    }
+    if light_changed {
+        update_lights(context);
+    }
+    if let Some(entity) = new_selection {
+        select_entity(context, entity);
+    }
+    if let Some(render_mode) = new_render_mode {
+        set_render_mode(context, render_mode);
+    }
+}
+
+/// Draw the HUD and the debug overlay over the scene.
+fn render_overlay(context: &GameContext) {
+    let overlay = &context.overlay;
+    let vertices = overlay.ui.vertices();
+    if vertices.is_empty() {
+        return;
+    }
+
+    // The overlay is laid out in window coordinates, which need not match the
+    // framebuffer size on high resolution displays.
+    let (width, height) = context.gl.window.get_size();
+    let proj_mat = Matrix4::from_orthographic(0.0, width as f32, height as f32, 0.0, -1.0, 1.0);
+    unsafe {
+        gl::UseProgram(overlay.shader.handle.into());
+        gl::UniformMatrix4fv(overlay.shader.uniforms["proj_mat"].into(), 1, gl::FALSE, proj_mat.as_ptr());
+        gl::BindBuffer(gl::ARRAY_BUFFER, overlay.buffer.vbo);
+        gl::BufferData(
+            gl::ARRAY_BUFFER, mem::size_of_val(vertices) as GLsizeiptr,
+            vertices.as_ptr() as *const GLvoid, gl::STREAM_DRAW
+        );
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_2D, overlay.texture.handle().into());
+        gl::BindVertexArray(overlay.vertex_array.vao);
+        gl::Disable(gl::DEPTH_TEST);
+        gl::Disable(gl::CULL_FACE);
+        gl::Enable(gl::BLEND);
+        gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        gl::DrawArrays(gl::TRIANGLES, 0, vertices.len() as i32);
+        gl::Disable(gl::BLEND);
+        gl::Enable(gl::CULL_FACE);
+        gl::Enable(gl::DEPTH_TEST);
+    }
+}
+
+/// Rebuild the projection matrix from the camera parameters, and send it to every
+/// shader program that uses one.
+fn update_projection(context: &mut GameContext) {
+    context.camera.update_projection();
+    for shader in context.entities.shaders.values() {
+        if let Some(&proj_mat_loc) = shader.uniforms.get("proj_mat") {
             unsafe {
-                gl::UseProgram(tri_sp2.handle.into());
+                gl::UseProgram(shader.handle.into());
                 gl::UniformMatrix4fv(
-                    tri_sp_view_mat_loc2.into(), 1, gl::FALSE,
-                    context.camera.view_mat.as_ptr()
+                    proj_mat_loc.into(), 1, gl::FALSE,
+                    context.camera.proj_mat.as_ptr()
                 );
             }
+        }
+        if let Some(&depth_range_loc) = shader.uniforms.get("depth_range") {
+            unsafe {
+                gl::UseProgram(shader.handle.into());
+                gl::Uniform2f(depth_range_loc.into(), context.camera.near, context.camera.far);
+            }
+        }
+    }
+}
 
-            let tri_sp3 = &context.entities.shaders[&ids[3]];
-            let tri_sp_view_mat_loc3 = tri_sp3.uniforms["view_mat"];
+/// Switch every entity shader over to a render mode.
+fn set_render_mode(context: &mut GameContext, render_mode: RenderMode) {
+    context.render_mode = render_mode;
+    for shader in context.entities.shaders.values() {
+        if let Some(&render_mode_loc) = shader.uniforms.get("render_mode") {
             unsafe {
-                gl::UseProgram(tri_sp3.handle.into());
-                gl::UniformMatrix4fv(
-                    tri_sp_view_mat_loc3.into(), 1, gl::FALSE,
-                    context.camera.view_mat.as_ptr()
-                );
+                gl::UseProgram(shader.handle.into());
+                gl::Uniform1i(render_mode_loc.into(), render_mode.shader_mode());
             }
         }
+    }
+    post_status(context, &format!("Render mode: {:?}", render_mode));
+}
 
-        let (width, height) = context.gl.window.get_framebuffer_size();
-        if (width != context.gl.width as i32) && (height != context.gl.height as i32) {
-            glfw_framebuffer_size_callback(
-                &mut context, width as u32, height as u32
-            );
+/// Initialize the logger.
+fn init_logger(log_file: &str) {
+    eprintln!("Logging is stored in file: {}", log_file);
+    file_logger::init(log_file).expect("Failed to initialize logger.");
+    info!("OpenGL application log.");
+    info!("build version: ??? ?? ???? ??:??:??\n\n");
+}
+
+/// The settings the demo accepts on the command line.
+struct CommandLineOptions {
+    graphics: glh::GraphicsSettings,
+    scene_file: Option<String>,
+    controls_file: Option<String>,
+    camera_path_file: Option<String>,
+    skybox_dir: Option<String>,
+    ground_texture_file: Option<String>,
+    projection: ProjectionSettings,
+}
+
+/// Parse the command line arguments. The demo accepts `--msaa <samples>`,
+/// `--anisotropy <level>`, `--scene <file>`, `--controls <file>`,
+/// `--camera-path <file>`, `--skybox <dir>`, `--ground-texture <file>`,
+/// `--projection <perspective|orthographic>`, `--fov <degrees>`, `--near <distance>`
+/// and `--far <distance>`. Any setting not given on the command line keeps its
+/// default value.
+fn parse_command_line<I: Iterator<Item = String>>(mut args: I) -> Result<CommandLineOptions, String> {
+    let mut settings = glh::GraphicsSettings::default();
+    let mut scene_file = None;
+    let mut controls_file = None;
+    let mut camera_path_file = None;
+    let mut skybox_dir = None;
+    let mut ground_texture_file = None;
+    let mut projection = ProjectionSettings::new();
+    while let Some(arg) = args.next() {
+        let value = match args.next() {
+            Some(value) => value,
+            None => return Err(format!("Missing value for command line option {}", arg)),
+        };
+        match arg.as_str() {
+            "--msaa" => {
+                settings.msaa_samples = value.parse::<u32>().map_err(|_| {
+                    format!("Invalid MSAA sample count: {}", value)
+                })?;
+            }
+            "--anisotropy" => {
+                settings.anisotropy = value.parse::<f32>().map_err(|_| {
+                    format!("Invalid anisotropic filtering level: {}", value)
+                })?;
+            }
+            "--scene" => {
+                scene_file = Some(value);
+            }
+            "--controls" => {
+                controls_file = Some(value);
+            }
+            "--camera-path" => {
+                camera_path_file = Some(value);
+            }
+            "--skybox" => {
+                skybox_dir = Some(value);
+            }
+            "--ground-texture" => {
+                ground_texture_file = Some(value);
+            }
+            "--projection" => {
+                projection.projection = Projection::parse(&value).ok_or_else(|| {
+                    format!("Invalid projection: {}. Expected perspective or orthographic", value)
+                })?;
+            }
+            "--fov" => {
+                projection.fov = value.parse::<f32>().map_err(|_| {
+                    format!("Invalid field of view: {}", value)
+                })?;
+            }
+            "--near" => {
+                projection.near = value.parse::<f32>().map_err(|_| {
+                    format!("Invalid near plane distance: {}", value)
+                })?;
+            }
+            "--far" => {
+                projection.far = value.parse::<f32>().map_err(|_| {
+                    format!("Invalid far plane distance: {}", value)
+                })?;
+            }
+            _ => return Err(format!("Unknown command line option: {}", arg)),
         }
+    }
+
+    // The comparisons are written so that NaN fails them.
+    if !(projection.fov > 0.0 && projection.fov < 180.0) {
+        return Err(format!("The field of view must be between 0 and 180 degrees, got {}", projection.fov));
+    }
+    if !(projection.near > 0.0 && projection.far > projection.near && projection.far.is_finite()) {
+        return Err(format!(
+            "The near plane must be in front of the camera and before the far plane, got near {} and far {}",
+            projection.near, projection.far
+        ));
+    }
 
-        // Update the kinematics of the triforce.
-        let dx = v_triforce * elapsed_seconds as f32;
-        position_triforce += dx * direction;
-        if position_triforce > 10.0 || position_triforce < -10.0 {
-            vhat_triforce = -vhat_triforce;
-            direction = -direction;
+    Ok(CommandLineOptions {
+        graphics: settings,
+        scene_file: scene_file,
+        controls_file: controls_file,
+        camera_path_file: camera_path_file,
+        skybox_dir: skybox_dir,
+        ground_texture_file: ground_texture_file,
+        projection: projection,
+    })
+}
+
+/// Initialize the demo.
+fn init_game_state(ids: &[EntityID]) -> GameContext {
+    init_logger(LOG_FILE);
+    let options = match parse_command_line(env::args().skip(1)) {
+        Ok(val) => val,
+        Err(e) => {
+            eprintln!("Failed to parse the command line. Got error:");
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    info!("Requested graphics settings: {:?}", options.graphics);
+    let gltf_scene = match options.scene_file {
+        Some(ref scene_file) => match gltf_import::load_file(scene_file) {
+            Ok(val) => Some(val),
+            Err(e) => {
+                eprintln!("Failed to load the glTF scene. Got error:");
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let skybox_faces = match options.skybox_dir {
+        Some(ref skybox_dir) => match skybox::load_skybox_faces(skybox_dir) {
+            Ok(val) => Some(val),
+            Err(e) => {
+                eprintln!("Failed to load the skybox. Got error:");
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let controls = match options.controls_file {
+        Some(ref controls_file) => ActionMap::load_file(controls_file),
+        None if Path::new(CONTROLS_FILE).exists() => ActionMap::load_file(CONTROLS_FILE),
+        None => Ok(ActionMap::new()),
+    };
+    let controls = match controls {
+        Ok(val) => val,
+        Err(e) => {
+            eprintln!("Failed to load the controls. Got error:");
+            eprintln!("{}", e);
+            process::exit(1);
         }
-        let trans_mat = Matrix4::from_affine_translation(&(vhat_triforce * dx));
-        let model_mat = context.entities.model_matrices[&ids[1]];
-        context.entities.model_matrices.insert(ids[1], trans_mat * model_mat);
-        let model_mat = context.entities.model_matrices[&ids[2]];
-        context.entities.model_matrices.insert(ids[2], trans_mat * model_mat);
-        let model_mat = context.entities.model_matrices[&ids[3]];
-        context.entities.model_matrices.insert(ids[3], trans_mat * model_mat);
+    };
+    info!("Controls:\n{}", controls.help());
+    let gl_state = match glh::start_gl(720, 480, &options.graphics) {
+        Ok(val) => val,
+        Err(e) => {
+            eprintln!("Failed to Initialize OpenGL context. Got error:");
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
 
-        let tri_sp1 = &context.entities.shaders[&ids[1]];
-        let tri_sp_model_mat_loc1 = tri_sp1.uniforms["model_mat"];
-        unsafe {
-            gl::UseProgram(tri_sp1.handle.into());
-            gl::UniformMatrix4fv(
-                tri_sp_model_mat_loc1.into(), 1, gl::FALSE,
-                context.entities.model_matrices[&ids[1]].as_ptr()
+    let camera = create_camera(gl_state.width as f32, gl_state.height as f32, &options.projection);
+    let light = create_light();
+    let gamepad = Gamepad::new(&gl_state.glfw);
+    let camera_controller = CameraController::new(&camera).with_dynamics(controls.camera);
+
+    // A camera path given on the command line has to load. Otherwise the default
+    // camera path file gets loaded when playback starts, if there is one.
+    let camera_path_frame = camera.axis;
+    let camera_path = match options.camera_path_file {
+        Some(ref camera_path_file) => match CameraPath::load_file(camera_path_file, camera_path_frame) {
+            Ok(val) => Some(val),
+            Err(e) => {
+                eprintln!("Failed to load the camera path. Got error:");
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let camera_path_file = options.camera_path_file.clone().unwrap_or_else(|| String::from(CAMERA_PATH_FILE));
+    let selection = Selection {
+        shader: create_outline_shader(&gl_state),
+        entity: None,
+        vertex_array: None,
+    };
+    let mut textures = TextureManager::new();
+    let overlay = create_overlay(&gl_state, &mut textures);
+    let mut scene = SceneGraph::new();
+    let triforce = create_scene(&mut scene, ids);
+    let debug_draw = create_debug_draw(&gl_state);
+    let mut context = GameContext {
+        gl: gl_state,
+        previous_camera_pose: camera.pose(),
+        camera: camera,
+        projection_settings: options.projection,
+        camera_controller: camera_controller,
+        camera_path_file: camera_path_file,
+        camera_path_frame: camera_path_frame,
+        camera_path: camera_path,
+        camera_recorder: None,
+        camera_playback: None,
+        light: light,
+        textures: textures,
+        entities: EntityDatabase::new(),
+        scene: scene,
+        triforce: triforce,
+        animator: Animator::new(),
+        timestep: FixedTimestep::new(SIMULATION_TIMESTEP, MAX_FRAME_TIME),
+        time_control: TimeControl::new(),
+        controls: controls,
+        mouse_look: MouseLook::new(),
+        gamepad: gamepad,
+        gltf_entities: vec![],
+        selection: selection,
+        render_mode: RenderMode::Shaded,
+        frame_timer: FrameTimer::new(),
+        overlay: overlay,
+        debug_draw: debug_draw,
+    };
+
+    // A camera or light in the glTF scene takes the place of the default one. The
+    // shaders only support a single light, so the first one wins.
+    if let Some(ref gltf_scene) = gltf_scene {
+        let camera = gltf_scene.nodes.iter().filter_map(|node| {
+            gltf_scene.to_camera(node, &context.camera)
+        }).next();
+        if let Some(camera) = camera {
+            context.previous_camera_pose = camera.pose();
+            context.camera = camera;
+        }
+        let light = gltf_scene.nodes.iter().filter_map(|node| gltf_scene.to_point_light(node)).next();
+        if let Some(light) = light {
+            context.light = light;
+        }
+    }
+
+    // The model matrices come out of the scene graph, so they are in place before any
+    // of the uniforms go to the GPU.
+    update_scene(&mut context, 1.0);
+    create_triforce_animation(&mut context);
+    create_ground_plane_shaders(&mut context, ids[0]);
+    create_ground_plane_geometry(&mut context, ids[0]);
+    create_ground_plane_uniforms(&context, ids[0]);
+    create_ground_plane_texture(&mut context, ids[0], options.ground_texture_file.as_deref());
+    create_triforce_shaders(&mut context, ids[1]);
+    create_triforce_geometry(&mut context, ids[1]);
+    create_triforce_uniforms(&context, ids[1]);
+    create_triforce_texture(&mut context, ids[1]);
+    create_triforce_lights(&mut context, ids[1]);
+    create_triforce_shaders(&mut context, ids[2]);
+    create_triforce_geometry(&mut context, ids[2]);
+    create_triforce_uniforms(&context, ids[2]);
+    create_triforce_texture(&mut context, ids[2]);
+    create_triforce_lights(&mut context, ids[2]);
+    create_triforce_shaders(&mut context, ids[3]);
+    create_triforce_geometry(&mut context, ids[3]);
+    create_triforce_uniforms(&context, ids[3]);
+    create_triforce_texture(&mut context, ids[3]);
+    create_triforce_lights(&mut context, ids[3]);
+    create_skybox_shaders(&mut context, ids[4]);
+    create_skybox_geometry(&mut context, ids[4]);
+    create_skybox_uniforms(&context, ids[4]);
+    create_skybox_texture(&mut context, ids[4], skybox_faces.as_deref());
+    if let Some(ref gltf_scene) = gltf_scene {
+        create_gltf_entities(&mut context, gltf_scene, ids.len() as u32);
+    }
+
+    context
+}
+
+/// Handle the actions that happen once per key press rather than for as long as
+/// a key is held down.
+fn handle_action_event(context: &mut GameContext, event: &WindowEvent) {
+    let (key, action) = match event {
+        &WindowEvent::Key(key, _, action, _) => (key, action),
+        _ => return,
+    };
+    for input_action in context.controls.actions_for_key(key) {
+        handle_action(context, input_action, action);
+    }
+}
+
+/// Handle the actions triggered by the gamepad buttons pressed since the last frame.
+fn handle_gamepad_buttons(context: &mut GameContext) {
+    for button in context.gamepad.pressed_buttons() {
+        for input_action in context.controls.actions_for_button(button) {
+            handle_action(context, input_action, Action::Press);
+        }
+    }
+}
+
+/// Log a status message, and show it on the HUD for a few seconds.
+fn post_status(context: &mut GameContext, text: &str) {
+    info!("{}", text);
+    let now = context.gl.glfw.get_time();
+    context.overlay.hud.post_message(text, now);
+}
+
+/// Carry out a one-shot action when the key or button bound to it is pressed.
+fn handle_action(context: &mut GameContext, input_action: InputAction, action: Action) {
+    let time_control = &mut context.time_control;
+    match (input_action, action) {
+        (InputAction::TogglePause, Action::Press) => {
+            time_control.toggle_pause();
+            let status = format!("Simulation paused: {}", time_control.is_paused());
+            post_status(context, &status);
+        }
+        (InputAction::SlowDown, Action::Press) => {
+            time_control.set_time_scale(time_control.time_scale() / 2.0);
+            let status = format!("Simulation time scale: {}", time_control.time_scale());
+            post_status(context, &status);
+        }
+        (InputAction::SpeedUp, Action::Press) => {
+            time_control.set_time_scale(time_control.time_scale() * 2.0);
+            let status = format!("Simulation time scale: {}", time_control.time_scale());
+            post_status(context, &status);
+        }
+        (InputAction::StepFrame, Action::Press) | (InputAction::StepFrame, Action::Repeat) => {
+            time_control.request_step();
+        }
+        (InputAction::ShowHelp, Action::Press) => {
+            let help = context.controls.help();
+            context.overlay.hud.toggle_help(&help);
+            if context.overlay.hud.is_showing_help() {
+                info!("Controls:\n{}", help.trim_end());
+            }
+        }
+        (InputAction::ToggleMouseLook, Action::Press) => {
+            let captured = !context.mouse_look.is_captured();
+            context.mouse_look.set_captured(captured);
+            if captured {
+                context.gl.window.set_cursor_mode(glfw::CursorMode::Disabled);
+            } else {
+                context.gl.window.set_cursor_mode(glfw::CursorMode::Normal);
+            }
+            post_status(context, &format!("Mouse-look: {}", captured));
+        }
+        (InputAction::ToggleProjection, Action::Press) => {
+            context.camera.projection = match context.camera.projection {
+                Projection::Perspective => Projection::Orthographic,
+                Projection::Orthographic => Projection::Perspective,
+            };
+            update_projection(context);
+            let status = format!("Camera projection: {:?}", context.camera.projection);
+            post_status(context, &status);
+        }
+        (InputAction::ToggleOrbit, Action::Press) => {
+            toggle_camera_mode(context);
+        }
+        (InputAction::ToggleDebugUi, Action::Press) => {
+            context.overlay.debug_visible = !context.overlay.debug_visible;
+            info!("Debug overlay visible: {}", context.overlay.debug_visible);
+        }
+        (InputAction::CycleRenderMode, Action::Press) => {
+            let render_mode = context.render_mode.next();
+            set_render_mode(context, render_mode);
+        }
+        (InputAction::ToggleHud, Action::Press) => {
+            context.overlay.hud.toggle_visible();
+            info!("HUD visible: {}", context.overlay.hud.is_visible());
+        }
+        (InputAction::ToggleFullscreen, Action::Press) => {
+            let fullscreen = glh::toggle_fullscreen(&mut context.gl);
+            post_status(context, &format!("Fullscreen: {}", fullscreen));
+        }
+        (InputAction::ToggleRecording, Action::Press) => {
+            toggle_camera_recording(context);
+        }
+        (InputAction::TogglePlayback, Action::Press) => {
+            toggle_camera_playback(context);
+        }
+        _ => {}
+    }
+}
+
+/// The point halfway between the three pieces of the triforce.
+fn triforce_centroid(context: &GameContext) -> Vector3<f32> {
+    let mut centroid = Vector3::new(0.0, 0.0, 0.0);
+    for &node in context.scene.children(context.triforce).iter() {
+        let world_mat = *context.scene.world_matrix(node);
+        centroid += (world_mat * Vector4::new(0.0, 0.0, 0.0, 1.0)).contract() / 3.0;
+    }
+
+    centroid
+}
+
+/// Switch between the free-fly and orbit cameras. The orbit camera circles the
+/// triforce.
+fn toggle_camera_mode(context: &mut GameContext) {
+    let target = triforce_centroid(context);
+    context.camera_controller.toggle_mode(&context.camera, target);
+    let status = format!("Camera mode: {:?}", context.camera_controller.mode());
+    post_status(context, &status);
+}
+
+/// Start recording the camera path, or stop recording and save it.
+fn toggle_camera_recording(context: &mut GameContext) {
+    match context.camera_recorder.take() {
+        Some(recorder) => {
+            let camera_path = recorder.finish();
+            match camera_path.save_file(&context.camera_path_file) {
+                Ok(()) => {
+                    let status = format!(
+                        "Saved {} camera poses to {}", camera_path.keyframes().len(), context.camera_path_file
+                    );
+                    post_status(context, &status);
+                }
+                Err(e) => error!("Failed to save the camera path: {}", e),
+            }
+            context.camera_path = Some(camera_path);
+        }
+        None => {
+            context.camera_recorder = Some(CameraRecorder::new(CAMERA_PATH_INTERVAL));
+            post_status(context, "Recording the camera path");
+        }
+    }
+}
+
+/// Start playing the camera path back, or stop playing it. With nothing recorded
+/// yet, the camera path file gets loaded.
+fn toggle_camera_playback(context: &mut GameContext) {
+    if let Some(playback) = context.camera_playback.take() {
+        context.camera_path = Some(playback.into_path());
+        post_status(context, "Stopped playing the camera path");
+        return;
+    }
+    if context.camera_recorder.is_some() {
+        post_status(context, "Cannot play the camera path back while recording it");
+        return;
+    }
+    if context.camera_path.is_none() && Path::new(&context.camera_path_file).exists() {
+        match CameraPath::load_file(&context.camera_path_file, context.camera_path_frame) {
+            Ok(val) => context.camera_path = Some(val),
+            Err(e) => error!("Failed to load the camera path: {}", e),
+        }
+    }
+    match context.camera_path.take() {
+        Some(camera_path) if !camera_path.is_empty() => {
+            let status = format!(
+                "Playing {} camera poses over {:.1} seconds", camera_path.keyframes().len(), camera_path.duration()
             );
+            context.camera_controller.reset();
+            context.camera_playback = Some(CameraPlayback::new(camera_path));
+            post_status(context, &status);
         }
+        _ => post_status(context, "There is no camera path to play"),
+    }
+}
+
+/// Collect the cursor movement and scroll wheel turns for the next camera update.
+fn handle_mouse_event(context: &mut GameContext, event: &WindowEvent) {
+    match *event {
+        WindowEvent::CursorPos(x, y) => context.mouse_look.cursor_moved(x, y),
+        WindowEvent::Scroll(_, y_offset) => context.mouse_look.scrolled(y_offset),
+        // Clicking selects the entity under the cursor, unless the mouse is busy
+        // turning the camera or working the debug overlay.
+        WindowEvent::MouseButton(MouseButtonLeft, Action::Press, _) if !context.mouse_look.is_captured() => {
+            let (cursor_x, cursor_y) = context.gl.window.get_cursor_pos();
+            let cursor = [cursor_x as f32, cursor_y as f32];
+            if context.overlay.ui.wants_mouse(cursor) {
+                return;
+            }
+            let entity = pick_entity(context);
+            select_entity(context, entity);
+        }
+        _ => {}
+    }
+}
+
+/// Move the camera according to the keys held down, over a span of
+/// `elapsed_seconds`. The view matrices go to the shaders when the frame is drawn.
+fn update_camera(context: &mut GameContext, elapsed_seconds: f64) {
+    // Camera control actions. Each action is as strong as the key, button or stick
+    // that triggers it, so the camera moves and turns in proportion to how far a
+    // stick is pushed.
+    let controls = &context.controls;
+    let window = &context.gl.window;
+    let gamepad = &context.gamepad;
+    let strength = |action| controls.strength(window, gamepad, action);
+    let held = |action| strength(action) > 0.5;
+    let mut input = CameraInput::new();
+    input.movement.x = strength(InputAction::MoveRight) - strength(InputAction::MoveLeft);
+    input.movement.y = strength(InputAction::MoveUp) - strength(InputAction::MoveDown);
+    input.movement.z = strength(InputAction::MoveBackward) - strength(InputAction::MoveForward);
+    input.yaw = strength(InputAction::YawLeft) - strength(InputAction::YawRight);
+    input.pitch = strength(InputAction::PitchUp) - strength(InputAction::PitchDown);
+    input.roll = strength(InputAction::RollRight) - strength(InputAction::RollLeft);
+    let reset_camera = held(InputAction::ResetCamera);
+    let quit = held(InputAction::Quit);
+
+    // Mouse-look. Moving the mouse right turns the camera right, and moving it up
+    // pitches the camera up unless the y axis is inverted. Cursor coordinates grow
+    // downwards, so the y movement is flipped for the normal case.
+    let (mouse_dx, mouse_dy) = context.mouse_look.take_delta();
+    let mouse = context.controls.mouse;
+    let invert = if mouse.invert_y { -1.0 } else { 1.0 };
+    input.look_yaw = -(mouse_dx as GLfloat) * mouse.sensitivity;
+    input.look_pitch = -invert * (mouse_dy as GLfloat) * mouse.sensitivity;
+
+    // Scroll wheel zoom. Scrolling up narrows the field of view, or brings the orbit
+    // camera closer to its target.
+    let scroll = context.mouse_look.take_scroll();
+    if scroll != 0.0 && context.camera_controller.mode() == CameraMode::Orbit {
+        input.zoom = scroll as GLfloat * ORBIT_SCROLL_STEP;
+    } else if scroll != 0.0 {
+        let fov = context.camera.fov.0 - scroll as GLfloat * mouse.zoom_step;
+        context.camera.fov = Degrees(fov.clamp(MIN_FOV, MAX_FOV));
+        update_projection(context);
+    }
+
+    // A camera path being played back takes over from the controls.
+    match context.camera_playback {
+        Some(ref mut playback) => {
+            context.camera.set_pose(playback.advance(elapsed_seconds as GLfloat));
+        }
+        None => {
+            context.camera_controller.update(&mut context.camera, &input, elapsed_seconds as GLfloat);
+        }
+    }
+    if context.camera_playback.as_ref().is_some_and(|playback| playback.is_finished()) {
+        toggle_camera_playback(context);
+    }
+    if let Some(ref mut recorder) = context.camera_recorder {
+        recorder.record(context.camera.pose(), elapsed_seconds as GLfloat);
+    }
+    if reset_camera {
+        // The camera jumps straight to the default pose rather than gliding there.
+        reset_camera_to_default(context);
+        context.camera_controller.reset();
+        context.previous_camera_pose = context.camera.pose();
+    }
+    if quit {
+        context.gl.window.set_should_close(true);
+    }
+}
+
+/// Send the view matrix of the camera to every shader that draws the scene.
+fn update_view_matrices(context: &GameContext, ids: &[EntityID]) {
+    let gp_sp = &context.entities.shaders[&ids[0]];
+    let gp_view_mat_loc = gp_sp.uniforms["view_mat"];
+    unsafe {
+        gl::UseProgram(gp_sp.handle.into());
+        gl::UniformMatrix4fv(
+            gp_view_mat_loc.into(), 1, gl::FALSE,
+            context.camera.view_mat.as_ptr()
+        );
+    }
+
+    let tri_sp1 = &context.entities.shaders[&ids[1]];
+    let tri_sp_view_mat_loc1 = tri_sp1.uniforms["view_mat"];
+    unsafe {
+        gl::UseProgram(tri_sp1.handle.into());
+        gl::UniformMatrix4fv(
+            tri_sp_view_mat_loc1.into(), 1, gl::FALSE,
+            context.camera.view_mat.as_ptr()
+        );
+    }
 
-        let tri_sp2 = &context.entities.shaders[&ids[2]];
-        let tri_sp_model_mat_loc2 = tri_sp1.uniforms["model_mat"];
+    let tri_sp2 = &context.entities.shaders[&ids[2]];
+    let tri_sp_view_mat_loc2 = tri_sp2.uniforms["view_mat"];
+    unsafe {
+        gl::UseProgram(tri_sp2.handle.into());
+        gl::UniformMatrix4fv(
+            tri_sp_view_mat_loc2.into(), 1, gl::FALSE,
+            context.camera.view_mat.as_ptr()
+        );
+    }
+
+    let tri_sp3 = &context.entities.shaders[&ids[3]];
+    let tri_sp_view_mat_loc3 = tri_sp3.uniforms["view_mat"];
+    unsafe {
+        gl::UseProgram(tri_sp3.handle.into());
+        gl::UniformMatrix4fv(
+            tri_sp_view_mat_loc3.into(), 1, gl::FALSE,
+            context.camera.view_mat.as_ptr()
+        );
+    }
+
+    for id in context.gltf_entities.iter() {
+        let sp = &context.entities.shaders[id];
         unsafe {
-            gl::UseProgram(tri_sp2.handle.into());
+            gl::UseProgram(sp.handle.into());
             gl::UniformMatrix4fv(
-                tri_sp_model_mat_loc2.into(), 1, gl::FALSE,
-                context.entities.model_matrices[&ids[2]].as_ptr()
+                sp.uniforms["view_mat"].into(), 1, gl::FALSE,
+                context.camera.view_mat.as_ptr()
             );
         }
+    }
 
-        let tri_sp3 = &context.entities.shaders[&ids[3]];
-        let tri_sp_model_mat_loc3 = tri_sp1.uniforms["model_mat"];
-        unsafe {
-            gl::UseProgram(tri_sp3.handle.into());
-            gl::UniformMatrix4fv(
-                tri_sp_model_mat_loc3.into(), 1, gl::FALSE,
-                context.entities.model_matrices[&ids[3]].as_ptr()
+    let sky_sp = &context.entities.shaders[&ids[4]];
+    let sky_sp_view_mat_loc = sky_sp.uniforms["view_mat"];
+    unsafe {
+        gl::UseProgram(sky_sp.handle.into());
+        gl::UniformMatrix4fv(
+            sky_sp_view_mat_loc.into(), 1, gl::FALSE,
+            context.camera.view_mat.as_ptr()
+        );
+    }
+}
+
+fn main() {
+    let ids = [
+        EntityID::new(0), EntityID::new(1), EntityID::new(2), EntityID::new(3), EntityID::new(4)
+    ];
+    let mut context = init_game_state(&ids);
+
+    unsafe {
+        // Enable depth testing.
+        gl::Enable(gl::DEPTH_TEST);
+        gl::DepthFunc(gl::LESS);
+        gl::Enable(gl::CULL_FACE);
+        gl::CullFace(gl::BACK);
+        gl::FrontFace(gl::CCW);
+        // Sample across cube map face edges so the skybox has no visible seams.
+        gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+        // Gray background.
+        gl::ClearColor(0.2, 0.2, 0.2, 1.0);
+        gl::Viewport(0, 0, context.gl.width as i32, context.gl.height as i32);
+    }
+
+    /* --------------------------- GAME LOOP ------------------------------- */
+    while !context.gl.window.should_close() {
+        // Update the game world.
+        glh::update_fps_counter(&mut context.gl);
+        context.frame_timer.tick(&context.gl.glfw);
+
+        context.gl.glfw.poll_events();
+        let was_connected = context.gamepad.is_connected();
+        context.gamepad.poll();
+        if context.gamepad.is_connected() != was_connected {
+            let status = if was_connected { "Gamepad disconnected" } else { "Gamepad connected" };
+            post_status(&mut context, status);
+        }
+        handle_gamepad_buttons(&mut context);
+        let events: Vec<WindowEvent> = glfw::flush_messages(&context.gl.events).map(|(_, event)| event).collect();
+        for event in events.iter() {
+            handle_action_event(&mut context, event);
+            handle_mouse_event(&mut context, event);
+        }
+
+        // Run as many fixed simulation steps as it takes to catch up with the clock.
+        let steps = context.timestep.advance(&context.gl.glfw);
+        let elapsed_seconds = context.timestep.timestep();
+        for _ in 0..steps {
+            // Check input.
+            context.previous_camera_pose = context.camera.pose();
+            update_camera(&mut context, elapsed_seconds);
+
+            // Advance the animations by however much simulated time passes this step.
+            let simulated_seconds = context.time_control.simulated_seconds(elapsed_seconds);
+            context.scene.store_previous_transforms();
+            context.animator.update(&mut context.scene, simulated_seconds as f32);
+        }
+
+        let (width, height) = context.gl.window.get_framebuffer_size();
+        if (width != context.gl.width as i32) || (height != context.gl.height as i32) {
+            glfw_framebuffer_size_callback(
+                &mut context, width as u32, height as u32
             );
         }
 
+        // Draw the scene part of the way between the last two simulation steps.
+        let alpha = context.timestep.alpha();
+        update_scene(&mut context, alpha);
+        update_overlay(&mut context, &ids);
+        update_debug_draw(&mut context);
+
+        // Draw the camera part of the way between the last two simulation steps too,
+        // and put it back where the simulation left it once the frame is drawn.
+        let camera_pose = context.camera.pose();
+        context.camera.set_pose(context.previous_camera_pose.interpolate(&camera_pose, alpha));
+        update_view_matrices(&context, &ids);
+
+        // Skip drawing the entities that lie entirely outside the view frustum.
+        let frustum = Frustum::from_matrix(&(context.camera.proj_mat * context.camera.view_mat));
+
         // Render the results.
         unsafe {
             // Clear the screen.
@@ -855,40 +2528,113 @@ fn main() {
             gl::ClearColor(0.2, 0.2, 0.2, 1.0);
             gl::Viewport(0, 0, context.gl.width as i32, context.gl.height as i32);
 
+            // The wireframe view draws the edges of the entities, but not of the skybox.
+            if context.render_mode.is_wireframe() {
+                gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+            }
+
             // Render the ground plane.
-            gl::UseProgram(context.entities.shaders[&ids[0]].handle.into());
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, context.entities.textures[&ids[0]].into());
-            gl::BindVertexArray(context.entities.buffers[&ids[0]][0].vao);
-            gl::DrawArrays(
-                gl::TRIANGLES, 0, context.entities.meshes[&ids[0]].len() as i32
-            );
+            if is_visible(&context, &frustum, ids[0]) {
+                gl::UseProgram(context.entities.shaders[&ids[0]].handle.into());
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, context.entities.textures[&ids[0]].handle().into());
+                gl::BindVertexArray(context.entities.vertex_arrays[&ids[0]].vao);
+                gl::DrawArrays(
+                    gl::TRIANGLES, 0, context.entities.meshes[&ids[0]].len() as i32
+                );
+            }
 
             // Render the triforce.
-            gl::UseProgram(context.entities.shaders[&ids[1]].handle.into());
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, context.entities.textures[&ids[1]].into());
-            gl::BindVertexArray(context.entities.buffers[&ids[1]][0].vao);
-            gl::DrawArrays(
-                gl::TRIANGLES, 0, context.entities.meshes[&ids[1]].len() as i32
-            );
+            if is_visible(&context, &frustum, ids[1]) {
+                gl::UseProgram(context.entities.shaders[&ids[1]].handle.into());
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, context.entities.textures[&ids[1]].handle().into());
+                gl::ActiveTexture(gl::TEXTURE1);
+                gl::BindTexture(gl::TEXTURE_CUBE_MAP, context.entities.textures[&ids[4]].handle().into());
+                gl::BindVertexArray(context.entities.vertex_arrays[&ids[1]].vao);
+                gl::DrawArrays(
+                    gl::TRIANGLES, 0, context.entities.meshes[&ids[1]].len() as i32
+                );
+            }
 
-            gl::UseProgram(context.entities.shaders[&ids[2]].handle.into());
-            gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, context.entities.textures[&ids[2]].into());
-            gl::BindVertexArray(context.entities.buffers[&ids[2]][0].vao);
-            gl::DrawArrays(
-                gl::TRIANGLES, 0, context.entities.meshes[&ids[2]].len() as i32
-            );
+            if is_visible(&context, &frustum, ids[2]) {
+                gl::UseProgram(context.entities.shaders[&ids[2]].handle.into());
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, context.entities.textures[&ids[2]].handle().into());
+                gl::ActiveTexture(gl::TEXTURE1);
+                gl::BindTexture(gl::TEXTURE_CUBE_MAP, context.entities.textures[&ids[4]].handle().into());
+                gl::BindVertexArray(context.entities.vertex_arrays[&ids[2]].vao);
+                gl::DrawArrays(
+                    gl::TRIANGLES, 0, context.entities.meshes[&ids[2]].len() as i32
+                );
+            }
+
+            if is_visible(&context, &frustum, ids[3]) {
+                gl::UseProgram(context.entities.shaders[&ids[3]].handle.into());
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, context.entities.textures[&ids[3]].handle().into());
+                gl::ActiveTexture(gl::TEXTURE1);
+                gl::BindTexture(gl::TEXTURE_CUBE_MAP, context.entities.textures[&ids[4]].handle().into());
+                gl::BindVertexArray(context.entities.vertex_arrays[&ids[3]].vao);
+                gl::DrawArrays(
+                    gl::TRIANGLES, 0, context.entities.meshes[&ids[3]].len() as i32
+                );
+            }
 
-            gl::UseProgram(context.entities.shaders[&ids[3]].handle.into());
+            // Render the imported glTF scene. Its base colors are sampled as linear
+            // values, so the framebuffer encodes the shaded result back into sRGB. The
+            // debug views show raw values and skip the encoding.
+            let encode_srgb = match context.render_mode {
+                RenderMode::Shaded | RenderMode::Wireframe | RenderMode::Lighting => true,
+                RenderMode::Normals | RenderMode::TexCoords | RenderMode::Depth => false,
+            };
+            if encode_srgb {
+                gl::Enable(gl::FRAMEBUFFER_SRGB);
+            }
+            for id in context.gltf_entities.iter() {
+                if !is_visible(&context, &frustum, *id) {
+                    continue;
+                }
+                let double_sided = context.entities.double_sided.contains(id);
+                if double_sided {
+                    gl::Disable(gl::CULL_FACE);
+                }
+                gl::UseProgram(context.entities.shaders[id].handle.into());
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, context.entities.textures[id].handle().into());
+                gl::ActiveTexture(gl::TEXTURE1);
+                gl::BindTexture(gl::TEXTURE_CUBE_MAP, context.entities.textures[&ids[4]].handle().into());
+                gl::BindVertexArray(context.entities.vertex_arrays[id].vao);
+                gl::DrawArrays(
+                    gl::TRIANGLES, 0, context.entities.meshes[id].len() as i32
+                );
+                if double_sided {
+                    gl::Enable(gl::CULL_FACE);
+                }
+            }
+            if encode_srgb {
+                gl::Disable(gl::FRAMEBUFFER_SRGB);
+            }
+
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+
+            // Render the skybox last. Its depth sits on the far plane, so only the
+            // pixels the scene did not cover pass the depth test. The camera is inside
+            // the cube, so face culling is switched off while drawing it.
+            gl::DepthFunc(gl::LEQUAL);
+            gl::Disable(gl::CULL_FACE);
+            gl::UseProgram(context.entities.shaders[&ids[4]].handle.into());
             gl::ActiveTexture(gl::TEXTURE0);
-            gl::BindTexture(gl::TEXTURE_2D, context.entities.textures[&ids[3]].into());
-            gl::BindVertexArray(context.entities.buffers[&ids[3]][0].vao);
-            gl::DrawArrays(
-                gl::TRIANGLES, 0, context.entities.meshes[&ids[3]].len() as i32
-            );
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, context.entities.textures[&ids[4]].handle().into());
+            gl::BindVertexArray(context.entities.vertex_arrays[&ids[4]].vao);
+            gl::DrawArrays(gl::TRIANGLES, 0, (SKYBOX_POINTS.len() / 3) as i32);
+            gl::Enable(gl::CULL_FACE);
+            gl::DepthFunc(gl::LESS);
         }
+        render_debug_draw(&context);
+        render_selection_outline(&context);
+        render_overlay(&context);
+        context.camera.set_pose(camera_pose);
         
         // Send the results to the output.
         context.gl.window.swap_buffers();
@@ -0,0 +1,297 @@
+//! Mouse picking. A click on the screen becomes a ray from the camera through the
+//! point under the cursor, found by running the cursor position back through the
+//! inverse of the projection and view matrices. The ray is tested against the
+//! bounding box of each entity first, and only the meshes whose boxes it passes
+//! through get tested triangle by triangle.
+use crate::frustum::Aabb;
+use cglinalg::{
+    Matrix4,
+    Vector3,
+    Vector4,
+};
+
+
+/// Triangles closer to parallel with a ray than this never count as hit.
+const PARALLEL_EPSILON: f32 = 1e-7;
+
+/// A half-line starting at `origin` and running along `direction`, which has
+/// unit length so that distances along the ray are distances in world space.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Ray {
+    pub origin: Vector3<f32>,
+    pub direction: Vector3<f32>,
+}
+
+/// Carry a point in normalized device coordinates back into world space.
+fn unproject(inverse_proj_view: &Matrix4<f32>, x: f32, y: f32, z: f32) -> Vector3<f32> {
+    let point = *inverse_proj_view * Vector4::new(x, y, z, 1.0);
+
+    Vector3::new(point.x / point.w, point.y / point.w, point.z / point.w)
+}
+
+impl Ray {
+    pub fn new(origin: Vector3<f32>, direction: Vector3<f32>) -> Ray {
+        Ray {
+            origin: origin,
+            direction: direction / direction.magnitude(),
+        }
+    }
+
+    /// The ray through the point under the cursor. The cursor position is given in
+    /// window coordinates, with the origin at the top left corner of a window
+    /// `width` by `height` in size. The ray starts on the near plane and runs towards
+    /// the far plane, for perspective and orthographic projections alike. There is
+    /// no ray when `proj_view`, the product `proj_mat * view_mat`, has no inverse.
+    pub fn from_cursor(
+        cursor_x: f64, cursor_y: f64, width: f64, height: f64, proj_view: &Matrix4<f32>) -> Option<Ray> {
+
+        let inverse_proj_view = proj_view.inverse()?;
+        let ndc_x = (2.0 * cursor_x / width - 1.0) as f32;
+        let ndc_y = (1.0 - 2.0 * cursor_y / height) as f32;
+        let near = unproject(&inverse_proj_view, ndc_x, ndc_y, -1.0);
+        let far = unproject(&inverse_proj_view, ndc_x, ndc_y, 1.0);
+
+        Some(Ray::new(near, far - near))
+    }
+
+    /// The point `distance` along the ray.
+    #[inline]
+    pub fn point_at(&self, distance: f32) -> Vector3<f32> {
+        self.origin + self.direction * distance
+    }
+
+    /// The distance along the ray to where it enters a box, or zero if it starts
+    /// inside the box. Returns `None` if the ray misses the box.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        let origin = [self.origin.x, self.origin.y, self.origin.z];
+        let direction = [self.direction.x, self.direction.y, self.direction.z];
+        let min = [aabb.min.x, aabb.min.y, aabb.min.z];
+        let max = [aabb.max.x, aabb.max.y, aabb.max.z];
+        let mut entry = 0.0_f32;
+        let mut exit = f32::INFINITY;
+        for axis in 0..3 {
+            if direction[axis] == 0.0 {
+                // A ray parallel to a pair of faces misses unless it runs between them.
+                if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (min[axis] - origin[axis]) / direction[axis];
+            let t1 = (max[axis] - origin[axis]) / direction[axis];
+            entry = f32::max(entry, f32::min(t0, t1));
+            exit = f32::min(exit, f32::max(t0, t1));
+            if entry > exit {
+                return None;
+            }
+        }
+
+        Some(entry)
+    }
+
+    /// The distance along the ray to where it hits a triangle, from either side.
+    /// Returns `None` if the ray misses the triangle.
+    pub fn intersect_triangle(
+        &self, p0: &Vector3<f32>, p1: &Vector3<f32>, p2: &Vector3<f32>) -> Option<f32> {
+
+        // Moller-Trumbore: solve for the distance along the ray together with the
+        // barycentric coordinates of the hit point.
+        let edge1 = *p1 - *p0;
+        let edge2 = *p2 - *p0;
+        let p = self.direction.cross(&edge2);
+        let determinant = edge1.dot(&p);
+        if determinant.abs() < PARALLEL_EPSILON {
+            return None;
+        }
+        let inverse_determinant = 1.0 / determinant;
+        let s = self.origin - *p0;
+        let u = s.dot(&p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = s.cross(&edge1);
+        let v = self.direction.dot(&q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let distance = edge2.dot(&q) * inverse_determinant;
+        if distance < 0.0 {
+            return None;
+        }
+
+        Some(distance)
+    }
+
+    /// The distance along the ray to the closest triangle of a mesh it hits. The
+    /// mesh is a triangle list in model space placed in the world by `model_mat`.
+    /// Returns `None` if the ray misses every triangle.
+    pub fn intersect_mesh(&self, points: &[[f32; 3]], model_mat: &Matrix4<f32>) -> Option<f32> {
+        let to_world = |point: &[f32; 3]| {
+            let point = *model_mat * Vector4::new(point[0], point[1], point[2], 1.0);
+            Vector3::new(point.x, point.y, point.z)
+        };
+
+        points.chunks(3).filter(|triangle| triangle.len() == 3).filter_map(|triangle| {
+            let p0 = to_world(&triangle[0]);
+            let p1 = to_world(&triangle[1]);
+            let p2 = to_world(&triangle[2]);
+            self.intersect_triangle(&p0, &p1, &p2)
+        }).fold(None, |closest: Option<f32>, distance| match closest {
+            Some(closest) if closest <= distance => Some(closest),
+            _ => Some(distance),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cglinalg::Degrees;
+
+
+    const EPSILON: f32 = 1e-4;
+
+    fn ray(origin: [f32; 3], direction: [f32; 3]) -> Ray {
+        Ray::new(
+            Vector3::new(origin[0], origin[1], origin[2]),
+            Vector3::new(direction[0], direction[1], direction[2])
+        )
+    }
+
+    fn aabb(min: [f32; 3], max: [f32; 3]) -> Aabb {
+        Aabb::new(Vector3::new(min[0], min[1], min[2]), Vector3::new(max[0], max[1], max[2]))
+    }
+
+    /// A triangle in the plane `z == 0` covering the lower left half of the unit
+    /// square.
+    const TRIANGLE: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]];
+
+    fn triangle() -> [Vector3<f32>; 3] {
+        [
+            Vector3::new(TRIANGLE[0][0], TRIANGLE[0][1], TRIANGLE[0][2]),
+            Vector3::new(TRIANGLE[1][0], TRIANGLE[1][1], TRIANGLE[1][2]),
+            Vector3::new(TRIANGLE[2][0], TRIANGLE[2][1], TRIANGLE[2][2]),
+        ]
+    }
+
+    #[test]
+    fn test_ray_direction_is_normalized() {
+        let result = ray([0.0, 0.0, 0.0], [3.0, 0.0, 4.0]);
+
+        assert!((result.direction.magnitude() - 1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_cursor_at_the_center_looks_down_the_view_axis() {
+        let proj_mat = Matrix4::from_perspective_fov(Degrees(90.0), 1.0, 0.1, 100.0);
+        let result = Ray::from_cursor(400.0, 300.0, 800.0, 600.0, &proj_mat).unwrap();
+
+        assert!((result.origin - Vector3::new(0.0, 0.0, -0.1)).magnitude() < EPSILON);
+        assert!((result.direction - Vector3::new(0.0, 0.0, -1.0)).magnitude() < EPSILON);
+    }
+
+    #[test]
+    fn test_cursor_at_the_top_left_corner_points_up_and_left() {
+        // With a 90 degree field of view and a square window, the corner ray runs
+        // along the diagonal of the frustum.
+        let proj_mat = Matrix4::from_perspective_fov(Degrees(90.0), 1.0, 0.1, 100.0);
+        let result = Ray::from_cursor(0.0, 0.0, 600.0, 600.0, &proj_mat).unwrap();
+        let expected = Vector3::new(-1.0, 1.0, -1.0) / f32::sqrt(3.0);
+
+        assert!((result.direction - expected).magnitude() < EPSILON);
+    }
+
+    #[test]
+    fn test_cursor_ray_follows_the_camera() {
+        // The camera sits at z == 10, so the ray through the center of the screen
+        // starts just in front of it.
+        let proj_mat = Matrix4::from_perspective_fov(Degrees(90.0), 1.0, 0.1, 100.0);
+        let view_mat = Matrix4::from_affine_translation(&Vector3::new(0.0, 0.0, -10.0));
+        let result = Ray::from_cursor(300.0, 300.0, 600.0, 600.0, &(proj_mat * view_mat)).unwrap();
+
+        assert!((result.origin - Vector3::new(0.0, 0.0, 9.9)).magnitude() < EPSILON);
+        assert!((result.direction - Vector3::new(0.0, 0.0, -1.0)).magnitude() < EPSILON);
+    }
+
+    #[test]
+    fn test_orthographic_cursor_rays_are_parallel() {
+        let proj_mat = Matrix4::from_orthographic(-2.0, 2.0, -1.0, 1.0, 0.1, 100.0);
+        let result = Ray::from_cursor(600.0, 0.0, 800.0, 400.0, &proj_mat).unwrap();
+
+        assert!((result.origin - Vector3::new(1.0, 1.0, -0.1)).magnitude() < EPSILON);
+        assert!((result.direction - Vector3::new(0.0, 0.0, -1.0)).magnitude() < EPSILON);
+    }
+
+    #[test]
+    fn test_ray_hits_a_box_in_front_of_it() {
+        let result = ray([0.0, 0.0, 10.0], [0.0, 0.0, -1.0]).intersect_aabb(&aabb([-1.0; 3], [1.0; 3]));
+
+        assert!((result.unwrap() - 9.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_ray_starting_inside_a_box_hits_it_immediately() {
+        let result = ray([0.0, 0.0, 0.0], [1.0, 1.0, 0.0]).intersect_aabb(&aabb([-1.0; 3], [1.0; 3]));
+
+        assert_eq!(result, Some(0.0));
+    }
+
+    #[test]
+    fn test_ray_misses_a_box_beside_it() {
+        let result = ray([3.0, 0.0, 10.0], [0.0, 0.0, -1.0]).intersect_aabb(&aabb([-1.0; 3], [1.0; 3]));
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_ray_misses_a_box_behind_it() {
+        let result = ray([0.0, 0.0, 10.0], [0.0, 0.0, 1.0]).intersect_aabb(&aabb([-1.0; 3], [1.0; 3]));
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_ray_hits_a_triangle_from_either_side() {
+        let [p0, p1, p2] = triangle();
+        let front = ray([0.25, 0.25, 5.0], [0.0, 0.0, -1.0]).intersect_triangle(&p0, &p1, &p2);
+        let back = ray([0.25, 0.25, -2.0], [0.0, 0.0, 1.0]).intersect_triangle(&p0, &p1, &p2);
+
+        assert!((front.unwrap() - 5.0).abs() < EPSILON);
+        assert!((back.unwrap() - 2.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn test_ray_misses_outside_the_triangle() {
+        let [p0, p1, p2] = triangle();
+        let result = ray([0.75, 0.75, 5.0], [0.0, 0.0, -1.0]).intersect_triangle(&p0, &p1, &p2);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_ray_parallel_to_a_triangle_misses_it() {
+        let [p0, p1, p2] = triangle();
+        let result = ray([-1.0, 0.25, 0.0], [1.0, 0.0, 0.0]).intersect_triangle(&p0, &p1, &p2);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_ray_misses_a_triangle_behind_it() {
+        let [p0, p1, p2] = triangle();
+        let result = ray([0.25, 0.25, 5.0], [0.0, 0.0, 1.0]).intersect_triangle(&p0, &p1, &p2);
+
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_ray_hits_the_closest_triangle_of_a_mesh() {
+        let mut points = TRIANGLE.to_vec();
+        points.extend(TRIANGLE.iter().map(|point| [point[0], point[1], 2.0]));
+        let model_mat = Matrix4::from_affine_translation(&Vector3::new(0.0, 0.0, -1.0));
+        let result = ray([0.25, 0.25, 5.0], [0.0, 0.0, -1.0]).intersect_mesh(&points, &model_mat);
+
+        // The mesh moved back by one, so its nearer triangle sits at z == 1.
+        assert!((result.unwrap() - 4.0).abs() < EPSILON);
+    }
+}
@@ -0,0 +1,97 @@
+//! Debug views of the scene. Apart from the wireframe view, which draws the usual
+//! shading through `glPolygonMode`, each render mode swaps what the entity shaders
+//! write out for one of the inputs to the shading. The shaders pick the view from
+//! the `render_mode` uniform.
+
+
+/// What the entity shaders draw.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RenderMode {
+    /// The usual textured Blinn-Phong shading.
+    Shaded,
+    /// The edges of the triangles, shaded as usual.
+    Wireframe,
+    /// The world space normals, mapped from `[-1, 1]` to colors in `[0, 1]`.
+    Normals,
+    /// The texture coordinates, as red for `u` and green for `v`. The coordinates
+    /// wrap around at whole numbers, so tiled textures show up as repeated ramps.
+    TexCoords,
+    /// The distance in front of the camera, from black at the near plane to white
+    /// at the far plane.
+    Depth,
+    /// The shading of a white surface, leaving the textures out.
+    Lighting,
+}
+
+/// Every render mode, in the order they are cycled through.
+const RENDER_MODES: [RenderMode; 6] = [
+    RenderMode::Shaded,
+    RenderMode::Wireframe,
+    RenderMode::Normals,
+    RenderMode::TexCoords,
+    RenderMode::Depth,
+    RenderMode::Lighting,
+];
+
+impl RenderMode {
+    /// The render mode after this one, wrapping around after the last.
+    pub fn next(self) -> RenderMode {
+        let index = RENDER_MODES.iter().position(|&mode| mode == self).unwrap();
+
+        RENDER_MODES[(index + 1) % RENDER_MODES.len()]
+    }
+
+    /// Determine whether the triangles are drawn as lines.
+    #[inline]
+    pub fn is_wireframe(self) -> bool {
+        self == RenderMode::Wireframe
+    }
+
+    /// The value of the `render_mode` uniform in the entity shaders.
+    pub fn shader_mode(self) -> i32 {
+        match self {
+            RenderMode::Shaded | RenderMode::Wireframe => 0,
+            RenderMode::Normals => 1,
+            RenderMode::TexCoords => 2,
+            RenderMode::Depth => 3,
+            RenderMode::Lighting => 4,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[test]
+    fn test_next_visits_every_mode_and_wraps_around() {
+        let mut mode = RenderMode::Shaded;
+        let mut visited = vec![];
+        for _ in 0..RENDER_MODES.len() {
+            visited.push(mode);
+            mode = mode.next();
+        }
+
+        assert_eq!(visited, RENDER_MODES.to_vec());
+        assert_eq!(mode, RenderMode::Shaded);
+    }
+
+    #[test]
+    fn test_wireframe_uses_the_usual_shading() {
+        assert!(RenderMode::Wireframe.is_wireframe());
+        assert_eq!(RenderMode::Wireframe.shader_mode(), RenderMode::Shaded.shader_mode());
+    }
+
+    #[test]
+    fn test_every_other_mode_has_its_own_shader_mode() {
+        let mut shader_modes: Vec<i32> = RENDER_MODES.iter()
+            .filter(|mode| !mode.is_wireframe())
+            .map(|mode| mode.shader_mode())
+            .collect();
+        shader_modes.sort();
+        shader_modes.dedup();
+
+        assert_eq!(shader_modes.len(), RENDER_MODES.len() - 1);
+    }
+}
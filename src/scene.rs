@@ -0,0 +1,157 @@
+use crate::animation::Interpolate;
+use crate::component::{
+    EntityID,
+    Transform,
+};
+use cglinalg::Matrix4;
+
+
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+pub struct SceneNodeID {
+    id: usize,
+}
+
+/// A node in the scene graph. Each node carries its transform relative to its parent,
+/// along with the transform it had as of the previous simulation step.
+struct SceneNode {
+    name: String,
+    parent: Option<SceneNodeID>,
+    children: Vec<SceneNodeID>,
+    entity: Option<EntityID>,
+    transform: Transform,
+    previous: Transform,
+    world_mat: Matrix4<f32>,
+    dirty: bool,
+}
+
+/// A transform hierarchy over the entities in the scene. The world matrix of a node
+/// is its parent's world matrix times its own local matrix, and world matrices are
+/// only recomputed for nodes whose transform, or whose ancestor's transform, changed
+/// since the last update.
+pub struct SceneGraph {
+    // A parent is always created before its children, so walking the nodes in
+    // order visits every parent before any of its children.
+    nodes: Vec<SceneNode>,
+}
+
+impl SceneGraph {
+    pub fn new() -> SceneGraph {
+        SceneGraph {
+            nodes: vec![],
+        }
+    }
+
+    /// Add a node to the scene graph. A node with no parent is a root of the scene.
+    /// Nodes attached to an entity drive that entity's model matrix.
+    pub fn add_node(
+        &mut self, name: &str, parent: Option<SceneNodeID>, entity: Option<EntityID>,
+        transform: Transform) -> SceneNodeID {
+
+        let id = SceneNodeID { id: self.nodes.len() };
+        self.nodes.push(SceneNode {
+            name: String::from(name),
+            parent: parent,
+            children: vec![],
+            entity: entity,
+            transform: transform,
+            previous: transform,
+            world_mat: Matrix4::identity(),
+            dirty: true,
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent.id].children.push(id);
+        }
+
+        id
+    }
+
+    /// Look up the node that drives the model matrix of an entity.
+    pub fn find_entity(&self, entity: EntityID) -> Option<SceneNodeID> {
+        self.nodes.iter()
+            .position(|node| node.entity == Some(entity))
+            .map(|id| SceneNodeID { id: id })
+    }
+
+    #[inline]
+    pub fn name(&self, node: SceneNodeID) -> &str {
+        &self.nodes[node.id].name
+    }
+
+    #[inline]
+    pub fn parent(&self, node: SceneNodeID) -> Option<SceneNodeID> {
+        self.nodes[node.id].parent
+    }
+
+    #[inline]
+    pub fn children(&self, node: SceneNodeID) -> &[SceneNodeID] {
+        &self.nodes[node.id].children
+    }
+
+    #[inline]
+    pub fn transform(&self, node: SceneNodeID) -> Transform {
+        self.nodes[node.id].transform
+    }
+
+    #[inline]
+    pub fn set_transform(&mut self, node: SceneNodeID, transform: Transform) {
+        self.nodes[node.id].transform = transform;
+        self.nodes[node.id].dirty = true;
+    }
+
+    /// Remember the current transform of every node as its previous transform. Call
+    /// this at the start of every simulation step, so that rendering can interpolate
+    /// between the last two steps.
+    pub fn store_previous_transforms(&mut self) {
+        for node in self.nodes.iter_mut() {
+            if node.previous != node.transform {
+                node.previous = node.transform;
+                node.dirty = true;
+            }
+        }
+    }
+
+    /// The world matrix of a node as of the last update.
+    #[inline]
+    pub fn world_matrix(&self, node: SceneNodeID) -> &Matrix4<f32> {
+        &self.nodes[node.id].world_mat
+    }
+
+    /// Recompute the world matrices of the nodes that changed since the last update,
+    /// along with all of their descendants. A node that moved during the last
+    /// simulation step is placed `alpha` of the way from its previous transform to its
+    /// current one. This returns the entities whose model matrices changed, together
+    /// with their new model matrices.
+    pub fn update(&mut self, alpha: f32) -> Vec<(EntityID, Matrix4<f32>)> {
+        let mut updated = vec![false; self.nodes.len()];
+        let mut changed = vec![];
+        for i in 0..self.nodes.len() {
+            let parent_updated = match self.nodes[i].parent {
+                Some(parent) => updated[parent.id],
+                None => false,
+            };
+            let moving = self.nodes[i].previous != self.nodes[i].transform;
+            if !self.nodes[i].dirty && !moving && !parent_updated {
+                continue;
+            }
+
+            let parent_mat = match self.nodes[i].parent {
+                Some(parent) => self.nodes[parent.id].world_mat,
+                None => Matrix4::identity(),
+            };
+            let node = &mut self.nodes[i];
+            let transform = if moving {
+                node.previous.interpolate(&node.transform, alpha)
+            } else {
+                node.transform
+            };
+            node.world_mat = parent_mat * transform.to_matrix();
+            node.dirty = false;
+            updated[i] = true;
+            if let Some(entity) = node.entity {
+                changed.push((entity, node.world_mat));
+            }
+        }
+
+        changed
+    }
+}
@@ -0,0 +1,249 @@
+//! The skybox drawn behind the scene. The skybox is a cube map, loaded either from
+//! six face images or resampled from a single equirectangular panorama, and drawn
+//! on a unit cube around the camera. The triforce reflects the same cube map in its
+//! specular term.
+use crate::gl;
+use crate::gl::types::{
+    GLfloat, GLint, GLuint, GLvoid
+};
+use crate::gl_help as glh;
+use crate::component::{Texture, TextureHandle};
+use crate::texture;
+use teximage2d::TexImage2D;
+
+use std::f32::consts;
+use std::fs;
+use std::path::Path;
+use std::slice;
+
+
+// The cube map face targets in the order OpenGL lays them out.
+const CUBE_MAP_FACE_TARGETS: [GLuint; 6] = [
+    gl::TEXTURE_CUBE_MAP_POSITIVE_X,
+    gl::TEXTURE_CUBE_MAP_NEGATIVE_X,
+    gl::TEXTURE_CUBE_MAP_POSITIVE_Y,
+    gl::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+    gl::TEXTURE_CUBE_MAP_POSITIVE_Z,
+    gl::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+];
+
+// The edge length in texels of each face of the skybox cube map.
+pub const SKYBOX_FACE_SIZE: usize = 256;
+
+// The file names of the six faces of a skybox given with `--skybox`, in the same
+// order as the cube map face targets.
+const SKYBOX_FACE_FILES: [&str; 6] = ["px.png", "nx.png", "py.png", "ny.png", "pz.png", "nz.png"];
+
+// The unit cube the skybox is drawn on, two triangles per face.
+pub const SKYBOX_POINTS: [GLfloat; 108] = [
+    -1.0,  1.0, -1.0,  -1.0, -1.0, -1.0,   1.0, -1.0, -1.0,
+     1.0, -1.0, -1.0,   1.0,  1.0, -1.0,  -1.0,  1.0, -1.0,
+
+    -1.0, -1.0,  1.0,  -1.0, -1.0, -1.0,  -1.0,  1.0, -1.0,
+    -1.0,  1.0, -1.0,  -1.0,  1.0,  1.0,  -1.0, -1.0,  1.0,
+
+     1.0, -1.0, -1.0,   1.0, -1.0,  1.0,   1.0,  1.0,  1.0,
+     1.0,  1.0,  1.0,   1.0,  1.0, -1.0,   1.0, -1.0, -1.0,
+
+    -1.0, -1.0,  1.0,  -1.0,  1.0,  1.0,   1.0,  1.0,  1.0,
+     1.0,  1.0,  1.0,   1.0, -1.0,  1.0,  -1.0, -1.0,  1.0,
+
+    -1.0,  1.0, -1.0,   1.0,  1.0, -1.0,   1.0,  1.0,  1.0,
+     1.0,  1.0,  1.0,  -1.0,  1.0,  1.0,  -1.0,  1.0, -1.0,
+
+    -1.0, -1.0, -1.0,  -1.0, -1.0,  1.0,   1.0, -1.0, -1.0,
+     1.0, -1.0, -1.0,  -1.0, -1.0,  1.0,   1.0, -1.0,  1.0,
+];
+
+/// Upload the six faces of a cube map into the GPU. Each face is a square RGBA image
+/// of `face_size` texels on a side, given in the order +X, -X, +Y, -Y, +Z, -Z.
+fn upload_cube_map(
+    context: &glh::GLState, face_size: usize, faces: [*const GLvoid; 6]) -> Texture {
+
+    let mut tex = 0;
+    unsafe {
+        gl::GenTextures(1, &mut tex);
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_CUBE_MAP, tex);
+        for i in 0..6 {
+            gl::TexImage2D(
+                CUBE_MAP_FACE_TARGETS[i], 0, gl::RGBA as i32, face_size as i32, face_size as i32, 0,
+                gl::RGBA, gl::UNSIGNED_BYTE,
+                faces[i]
+            );
+        }
+        gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as GLint);
+        gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as GLint);
+        gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_WRAP_R, gl::CLAMP_TO_EDGE as GLint);
+        gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+        gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR as GLint);
+    }
+    assert!(tex > 0);
+
+    texture::apply_anisotropy(context, gl::TEXTURE_CUBE_MAP);
+
+    Texture::new(TextureHandle::new(tex))
+}
+
+/// Load a cube map into the GPU from six square images of the same size, given in
+/// the order +X, -X, +Y, -Y, +Z, -Z.
+pub fn load_cube_map(context: &glh::GLState, faces: &[TexImage2D]) -> Result<Texture, String> {
+    if faces.len() != 6 {
+        return Err(format!("A cube map needs six faces, got {}.", faces.len()));
+    }
+    let face_size = faces[0].width;
+    for face in faces.iter() {
+        if face.width != face_size || face.height != face_size {
+            return Err(format!(
+                "Cube map faces must be square and of equal size. Expected {}x{}, got {}x{}.",
+                face_size, face_size, face.width, face.height
+            ));
+        }
+    }
+
+    let tex = upload_cube_map(context, face_size as usize, [
+        faces[0].as_ptr() as *const GLvoid, faces[1].as_ptr() as *const GLvoid,
+        faces[2].as_ptr() as *const GLvoid, faces[3].as_ptr() as *const GLvoid,
+        faces[4].as_ptr() as *const GLvoid, faces[5].as_ptr() as *const GLvoid,
+    ]);
+
+    Ok(tex)
+}
+
+/// Read the six faces of a skybox from the image files in a directory.
+pub fn load_skybox_faces(dir: &str) -> Result<Vec<TexImage2D>, String> {
+    let mut faces = vec![];
+    for file_name in SKYBOX_FACE_FILES.iter() {
+        let path = Path::new(dir).join(file_name);
+        let buffer = fs::read(&path).map_err(|e| {
+            format!("Could not read the skybox face {}: {}", path.display(), e)
+        })?;
+        let result = teximage2d::load_from_memory(&buffer).map_err(|_| {
+            format!("Could not decode the skybox face {}", path.display())
+        })?;
+        faces.push(result.image);
+    }
+
+    Ok(faces)
+}
+
+/// Resample an equirectangular panorama into the six faces of a cube map, in the
+/// order +X, -X, +Y, -Y, +Z, -Z. Longitude zero of the panorama faces down the -Z axis.
+/// The panorama is given as tightly packed RGBA8 texels, row by row.
+fn equirectangular_to_cube_faces(
+    pixels: &[u8], width: usize, height: usize, face_size: usize) -> Vec<Vec<u8>> {
+
+    let mut faces = vec![];
+    for face in 0..6 {
+        let mut data = vec![0_u8; 4 * face_size * face_size];
+        for row in 0..face_size {
+            for col in 0..face_size {
+                // Map the texel onto the face of the cube following the OpenGL cube map
+                // conventions for each major axis.
+                let sc = 2.0 * (col as f32 + 0.5) / face_size as f32 - 1.0;
+                let tc = 2.0 * (row as f32 + 0.5) / face_size as f32 - 1.0;
+                let (x, y, z) = match face {
+                    0 => ( 1.0, -tc, -sc),
+                    1 => (-1.0, -tc,  sc),
+                    2 => ( sc,  1.0,  tc),
+                    3 => ( sc, -1.0, -tc),
+                    4 => ( sc, -tc,  1.0),
+                    _ => (-sc, -tc, -1.0),
+                };
+                let length = (x * x + y * y + z * z).sqrt();
+                let longitude = (x / length).atan2(-z / length);
+                let latitude = (y / length).asin();
+
+                let u = 0.5 + longitude / (2.0 * consts::PI);
+                let v = 0.5 - latitude / consts::PI;
+                let src_x = usize::min((u * width as f32) as usize, width - 1);
+                let src_y = usize::min((v * height as f32) as usize, height - 1);
+                let src = 4 * (src_y * width + src_x);
+                let dst = 4 * (row * face_size + col);
+                data[dst..(dst + 4)].copy_from_slice(&pixels[src..(src + 4)]);
+            }
+        }
+        faces.push(data);
+    }
+
+    faces
+}
+
+/// Load a cube map into the GPU from a single equirectangular panorama image.
+pub fn load_cube_map_from_equirectangular(
+    context: &glh::GLState,
+    tex_data: &TexImage2D, face_size: usize) -> Result<Texture, String> {
+
+    if tex_data.width != 2 * tex_data.height {
+        return Err(format!(
+            "An equirectangular image must be twice as wide as it is high. Got {}x{}.",
+            tex_data.width, tex_data.height
+        ));
+    }
+
+    let width = tex_data.width as usize;
+    let height = tex_data.height as usize;
+    let pixels = unsafe {
+        slice::from_raw_parts(tex_data.as_ptr(), 4 * width * height)
+    };
+    let faces = equirectangular_to_cube_faces(pixels, width, height, face_size);
+    let tex = upload_cube_map(context, face_size, [
+        faces[0].as_ptr() as *const GLvoid, faces[1].as_ptr() as *const GLvoid,
+        faces[2].as_ptr() as *const GLvoid, faces[3].as_ptr() as *const GLvoid,
+        faces[4].as_ptr() as *const GLvoid, faces[5].as_ptr() as *const GLvoid,
+    ]);
+
+    Ok(tex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// A panorama with the top half red and the bottom half blue.
+    fn panorama(width: usize, height: usize) -> Vec<u8> {
+        let mut pixels = vec![];
+        for row in 0..height {
+            for _ in 0..width {
+                if row < height / 2 {
+                    pixels.extend_from_slice(&[255, 0, 0, 255]);
+                } else {
+                    pixels.extend_from_slice(&[0, 0, 255, 255]);
+                }
+            }
+        }
+
+        pixels
+    }
+
+    #[test]
+    fn test_equirectangular_image_becomes_six_square_faces() {
+        let faces = equirectangular_to_cube_faces(&panorama(16, 8), 16, 8, 4);
+
+        assert_eq!(faces.len(), 6);
+        for face in faces.iter() {
+            assert_eq!(face.len(), 4 * 4 * 4);
+        }
+    }
+
+    #[test]
+    fn test_top_and_bottom_faces_sample_the_poles() {
+        let faces = equirectangular_to_cube_faces(&panorama(16, 8), 16, 8, 4);
+
+        assert!(faces[2].chunks(4).all(|texel| texel == [255, 0, 0, 255]));
+        assert!(faces[3].chunks(4).all(|texel| texel == [0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_side_faces_sample_the_horizon() {
+        let faces = equirectangular_to_cube_faces(&panorama(16, 8), 16, 8, 4);
+
+        for face in [0, 1, 4, 5].iter() {
+            let top_row = &faces[*face][0..4];
+            let bottom_row = &faces[*face][(4 * 4 * 3)..(4 * 4 * 3 + 4)];
+            assert_eq!(top_row, [255, 0, 0, 255]);
+            assert_eq!(bottom_row, [0, 0, 255, 255]);
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! Text for the on-screen overlays. The printable ASCII characters of a TrueType font
+//! are rasterized once at startup into a single channel atlas image, and a string is
+//! drawn as one textured quad per character. Text is laid out in window coordinates,
+//! with the origin at the top left corner of the window and y growing downwards.
+use ab_glyph::{
+    point,
+    Font,
+    FontRef,
+    OutlinedGlyph,
+    PxScale,
+    ScaleFont,
+};
+
+use std::collections::HashMap;
+
+
+/// The first and last characters baked into the atlas.
+const FIRST_CHAR: char = ' ';
+const LAST_CHAR: char = '~';
+
+/// Characters missing from the atlas are drawn as this character instead.
+const REPLACEMENT_CHAR: char = '?';
+
+/// The width of the atlas image in texels. The height grows to fit the glyphs.
+const ATLAS_WIDTH: usize = 256;
+
+/// The number of empty texels left around each glyph, so that filtering never
+/// bleeds one glyph into its neighbors.
+const GLYPH_PADDING: usize = 1;
+
+/// The edge length of the block of solid texels in the corner of the atlas. Solid
+/// shapes sample the middle of the block, so the same texture draws text and
+/// filled rectangles.
+const SOLID_BLOCK_SIZE: usize = 2;
+
+/// The placement of a character in the atlas, and how it sits on a line of text.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Glyph {
+    /// The offset in pixels from the pen position on the baseline to the top left
+    /// corner of the glyph image.
+    pub offset: [f32; 2],
+    /// The size of the glyph image in pixels. Blank characters have no image.
+    pub size: [f32; 2],
+    /// The corners of the glyph image in texture coordinates.
+    pub tex_min: [f32; 2],
+    pub tex_max: [f32; 2],
+    /// How far the pen moves to the right after drawing the character.
+    pub advance: f32,
+}
+
+/// A character of laid out text, as a rectangle in window coordinates and the
+/// part of the atlas to fill it with.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct GlyphQuad {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    pub tex_min: [f32; 2],
+    pub tex_max: [f32; 2],
+}
+
+/// A font rasterized at a single size.
+pub struct FontAtlas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+    glyphs: HashMap<char, Glyph>,
+    ascent: f32,
+    line_height: f32,
+}
+
+impl FontAtlas {
+    /// Rasterize the printable ASCII characters of a TrueType or OpenType font with
+    /// lines `pixel_height` pixels tall.
+    pub fn from_ttf(data: &[u8], pixel_height: f32) -> Result<FontAtlas, String> {
+        let font = FontRef::try_from_slice(data).map_err(|e| format!("Could not read the font: {}", e))?;
+        let scale = PxScale::from(pixel_height);
+        let scaled_font = font.as_scaled(scale);
+
+        // Pack the glyph images left to right in rows, starting after the solid block.
+        let mut placements: Vec<(char, OutlinedGlyph, usize, usize)> = vec![];
+        let mut advances = HashMap::new();
+        let mut x = SOLID_BLOCK_SIZE + GLYPH_PADDING;
+        let mut y = 0;
+        let mut row_height = SOLID_BLOCK_SIZE;
+        for ch in FIRST_CHAR..=LAST_CHAR {
+            let id = font.glyph_id(ch);
+            advances.insert(ch, scaled_font.h_advance(id));
+            let outlined = match font.outline_glyph(id.with_scale_and_position(scale, point(0.0, 0.0))) {
+                Some(outlined) => outlined,
+                None => continue,
+            };
+            let bounds = outlined.px_bounds();
+            let glyph_width = bounds.width() as usize;
+            let glyph_height = bounds.height() as usize;
+            if glyph_width + 2 * GLYPH_PADDING > ATLAS_WIDTH {
+                return Err(format!("The character `{}` is too wide for the font atlas", ch));
+            }
+            if x + glyph_width + GLYPH_PADDING > ATLAS_WIDTH {
+                x = GLYPH_PADDING;
+                y += row_height + GLYPH_PADDING;
+                row_height = 0;
+            }
+            placements.push((ch, outlined, x, y));
+            x += glyph_width + GLYPH_PADDING;
+            row_height = usize::max(row_height, glyph_height);
+        }
+
+        let width = ATLAS_WIDTH;
+        let height = (y + row_height + GLYPH_PADDING).next_power_of_two();
+        let mut pixels = vec![0; width * height];
+        for row in 0..SOLID_BLOCK_SIZE {
+            for column in 0..SOLID_BLOCK_SIZE {
+                pixels[row * width + column] = 0xFF;
+            }
+        }
+
+        let mut glyphs = HashMap::new();
+        for (ch, outlined, x, y) in placements.iter() {
+            outlined.draw(|glyph_x, glyph_y, coverage| {
+                let index = (y + glyph_y as usize) * width + x + glyph_x as usize;
+                pixels[index] = (f32::min(coverage, 1.0) * 255.0).round() as u8;
+            });
+            let bounds = outlined.px_bounds();
+            glyphs.insert(*ch, Glyph {
+                offset: [bounds.min.x, bounds.min.y],
+                size: [bounds.width(), bounds.height()],
+                tex_min: [*x as f32 / width as f32, *y as f32 / height as f32],
+                tex_max: [
+                    (*x as f32 + bounds.width()) / width as f32,
+                    (*y as f32 + bounds.height()) / height as f32,
+                ],
+                advance: advances[ch],
+            });
+        }
+        // Blank characters like the space only move the pen.
+        for (&ch, &advance) in advances.iter() {
+            glyphs.entry(ch).or_insert(Glyph {
+                offset: [0.0, 0.0],
+                size: [0.0, 0.0],
+                tex_min: [0.0, 0.0],
+                tex_max: [0.0, 0.0],
+                advance: advance,
+            });
+        }
+
+        Ok(FontAtlas {
+            width: width,
+            height: height,
+            pixels: pixels,
+            glyphs: glyphs,
+            ascent: scaled_font.ascent(),
+            line_height: scaled_font.height() + scaled_font.line_gap(),
+        })
+    }
+
+    /// The width of the atlas image in texels.
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the atlas image in texels.
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The atlas image, one coverage byte per texel, row by row from the top.
+    #[inline]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// The distance from one baseline to the next.
+    #[inline]
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// The texture coordinates of a fully covered texel, for drawing solid shapes
+    /// with the atlas.
+    #[inline]
+    pub fn solid_tex_coords(&self) -> [f32; 2] {
+        let middle = SOLID_BLOCK_SIZE as f32 / 2.0;
+
+        [middle / self.width as f32, middle / self.height as f32]
+    }
+
+    /// The glyph for a character, or the replacement glyph if the atlas does not
+    /// have the character.
+    pub fn glyph(&self, ch: char) -> &Glyph {
+        self.glyphs.get(&ch).unwrap_or_else(|| &self.glyphs[&REPLACEMENT_CHAR])
+    }
+
+    /// The width in pixels of the widest line of a string.
+    pub fn text_width(&self, text: &str) -> f32 {
+        text.lines()
+            .map(|line| line.chars().map(|ch| self.glyph(ch).advance).sum::<f32>())
+            .fold(0.0, f32::max)
+    }
+
+    /// The height in pixels of a string, one line height per line.
+    pub fn text_height(&self, text: &str) -> f32 {
+        usize::max(text.lines().count(), 1) as f32 * self.line_height
+    }
+
+    /// Lay out a string with the top left corner of its first line at `(x, y)`.
+    /// Each line after a newline starts back at `x`. The glyphs are placed on whole
+    /// pixels so they stay sharp.
+    pub fn layout(&self, text: &str, x: f32, y: f32) -> Vec<GlyphQuad> {
+        let mut quads = vec![];
+        let mut pen_x = x;
+        let mut baseline = y + self.ascent;
+        for ch in text.chars() {
+            if ch == '\n' {
+                pen_x = x;
+                baseline += self.line_height;
+                continue;
+            }
+            let glyph = self.glyph(ch);
+            if glyph.size[0] > 0.0 && glyph.size[1] > 0.0 {
+                let min = [(pen_x + glyph.offset[0]).round(), (baseline + glyph.offset[1]).round()];
+                quads.push(GlyphQuad {
+                    min: min,
+                    max: [min[0] + glyph.size[0], min[1] + glyph.size[1]],
+                    tex_min: glyph.tex_min,
+                    tex_max: glyph.tex_max,
+                });
+            }
+            pen_x += glyph.advance;
+        }
+
+        quads
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn font() -> FontAtlas {
+        FontAtlas::from_ttf(include_asset!("DejaVuSansMono.ttf"), 16.0).unwrap()
+    }
+
+    #[test]
+    fn test_every_printable_character_has_a_glyph() {
+        let font = font();
+        for ch in FIRST_CHAR..=LAST_CHAR {
+            assert!(font.glyphs.contains_key(&ch), "missing `{}`", ch);
+            assert!(font.glyphs[&ch].advance > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_space_has_no_image() {
+        let font = font();
+
+        assert_eq!(font.glyph(' ').size, [0.0, 0.0]);
+        assert!(font.layout(" ", 0.0, 0.0).is_empty());
+    }
+
+    #[test]
+    fn test_glyph_images_lie_inside_the_atlas() {
+        let font = font();
+        for ch in FIRST_CHAR..=LAST_CHAR {
+            let glyph = font.glyph(ch);
+            for &coordinate in glyph.tex_min.iter().chain(glyph.tex_max.iter()) {
+                assert!((0.0..=1.0).contains(&coordinate));
+            }
+        }
+    }
+
+    #[test]
+    fn test_solid_block_is_fully_covered() {
+        let font = font();
+        let [u, v] = font.solid_tex_coords();
+        let column = (u * font.width() as f32) as usize;
+        let row = (v * font.height() as f32) as usize;
+
+        assert_eq!(font.pixels()[(row - 1) * font.width() + column - 1], 0xFF);
+        assert_eq!(font.pixels()[row * font.width() + column], 0xFF);
+    }
+
+    #[test]
+    fn test_missing_characters_use_the_replacement_glyph() {
+        let font = font();
+
+        assert_eq!(font.glyph('\u{263a}'), font.glyph(REPLACEMENT_CHAR));
+    }
+
+    #[test]
+    fn test_text_width_adds_up_the_advances() {
+        // The font is monospaced, so every character advances the pen the same amount.
+        let font = font();
+        let advance = font.glyph('m').advance;
+
+        assert!((font.text_width("abc") - 3.0 * advance).abs() < 1e-4);
+        assert!((font.text_width("ab\nwxyz") - 4.0 * advance).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_layout_advances_the_pen_along_the_line() {
+        let font = font();
+        let quads = font.layout("ii", 10.0, 20.0);
+
+        assert_eq!(quads.len(), 2);
+        assert!((quads[1].min[0] - quads[0].min[0] - font.glyph('i').advance).abs() <= 1.0);
+        assert_eq!(quads[1].min[1], quads[0].min[1]);
+    }
+
+    #[test]
+    fn test_layout_starts_a_new_line_after_a_newline() {
+        let font = font();
+        let quads = font.layout("i\ni", 10.0, 20.0);
+
+        assert_eq!(quads.len(), 2);
+        assert_eq!(quads[0].min[0], quads[1].min[0]);
+        assert!((quads[1].min[1] - quads[0].min[1] - font.line_height()).abs() <= 1.0);
+    }
+
+    #[test]
+    fn test_layout_keeps_glyphs_below_the_top_of_the_line() {
+        let font = font();
+        for quad in font.layout("Hello, world!", 0.0, 50.0).iter() {
+            assert!(quad.min[1] >= 50.0 - 1.0);
+            assert!(quad.max[1] <= 50.0 + font.line_height() + 1.0);
+        }
+    }
+}
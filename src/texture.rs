@@ -0,0 +1,602 @@
+use crate::gl;
+use crate::gl::types::{
+    GLenum, GLint, GLvoid
+};
+use crate::gl_help as glh;
+use crate::bcn;
+use crate::bcn::BlockFormat;
+use crate::component::{Texture, TextureHandle};
+use crate::texture_container;
+use crate::texture_container::CompressedImage;
+use teximage2d::TexImage2D;
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+
+use log::{info, warn};
+
+
+/// The storage format of a texture on the GPU. Encoded images are always decoded to
+/// RGBA on the CPU side, and OpenGL converts them to the storage format on upload.
+/// Texels that are already decoded carry exactly the channels of their format.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum TextureFormat {
+    /// A single linear channel taken from the red channel of the image. It samples as
+    /// a grey colour with full opacity.
+    Greyscale,
+    /// Three linear colour channels.
+    Rgb,
+    /// Three linear colour channels and an alpha channel.
+    Rgba,
+    /// Three colour channels encoded in the sRGB colour space.
+    Srgb,
+    /// Three colour channels encoded in the sRGB colour space and a linear alpha channel.
+    SrgbAlpha,
+}
+
+impl TextureFormat {
+    /// The OpenGL internal format used to store the texture.
+    #[inline]
+    pub fn internal_format(self) -> GLenum {
+        match self {
+            TextureFormat::Greyscale => gl::R8,
+            TextureFormat::Rgb => gl::RGB8,
+            TextureFormat::Rgba => gl::RGBA8,
+            TextureFormat::Srgb => gl::SRGB8,
+            TextureFormat::SrgbAlpha => gl::SRGB8_ALPHA8,
+        }
+    }
+
+    /// The number of channels in a texel of the format.
+    #[inline]
+    pub fn channels(self) -> usize {
+        match self {
+            TextureFormat::Greyscale => 1,
+            TextureFormat::Rgb | TextureFormat::Srgb => 3,
+            TextureFormat::Rgba | TextureFormat::SrgbAlpha => 4,
+        }
+    }
+
+    /// The OpenGL pixel format of texels with the channels of the format.
+    #[inline]
+    fn pixel_format(self) -> GLenum {
+        match self.channels() {
+            1 => gl::RED,
+            3 => gl::RGB,
+            _ => gl::RGBA,
+        }
+    }
+}
+
+/// The sampling parameters of a texture.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SamplerSettings {
+    pub wrap_s: GLenum,
+    pub wrap_t: GLenum,
+    pub mag_filter: GLenum,
+    pub min_filter: GLenum,
+    /// Generate a mipmap chain on upload. The minification filter must not use mipmaps
+    /// when this is off.
+    pub mipmaps: bool,
+    /// Apply the anisotropic filtering level in effect for the OpenGL context.
+    pub anisotropic: bool,
+}
+
+impl SamplerSettings {
+    /// Construct trilinear sampler settings with anisotropic filtering, using the same
+    /// wrapping mode in both directions.
+    pub fn new(wrapping_mode: GLenum) -> SamplerSettings {
+        SamplerSettings {
+            wrap_s: wrapping_mode,
+            wrap_t: wrapping_mode,
+            mag_filter: gl::LINEAR,
+            min_filter: gl::LINEAR_MIPMAP_LINEAR,
+            mipmaps: true,
+            anisotropic: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum TextureError {
+    TextureNotFound(String),
+    CouldNotDecodeTexture(String),
+    UnsupportedFormat(String),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::TextureNotFound(name) => {
+                write!(f, "Could not open the texture file for reading: {}", name)
+            }
+            TextureError::CouldNotDecodeTexture(name) => {
+                write!(f, "The texture exists, but there was an error in decoding it: {}", name)
+            }
+            TextureError::UnsupportedFormat(name) => {
+                write!(f, "Neither the driver nor the software decoder supports the format of the texture: {}", name)
+            }
+        }
+    }
+}
+
+/// Apply the anisotropic filtering level in effect to the texture bound to `target`.
+/// The level has already been checked against what the context supports, and a level
+/// of one means the extension is missing or anisotropic filtering is switched off.
+pub fn apply_anisotropy(context: &glh::GLState, target: GLenum) {
+    if context.anisotropy > 1.0 {
+        unsafe {
+            gl::TexParameterf(target, glh::GL_TEXTURE_MAX_ANISOTROPY_EXT, context.anisotropy);
+        }
+    }
+}
+
+/// Upload a texture image into the GPU.
+fn upload_texture(
+    context: &glh::GLState,
+    tex_data: &TexImage2D, format: TextureFormat, sampler: &SamplerSettings) -> Texture {
+
+    upload_pixels(
+        context, tex_data.width as i32, tex_data.height as i32,
+        tex_data.as_ptr() as *const GLvoid, gl::RGBA, format, sampler
+    )
+}
+
+/// Upload tightly packed 8 bit texels laid out as `pixel_format` into the GPU.
+fn upload_pixels(
+    context: &glh::GLState, width: i32, height: i32,
+    pixels: *const GLvoid, pixel_format: GLenum,
+    format: TextureFormat, sampler: &SamplerSettings) -> Texture {
+
+    let mut tex = 0;
+    unsafe {
+        gl::GenTextures(1, &mut tex);
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_2D, tex);
+        // Rows of one or three channel texels need not end on a four byte boundary.
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+        gl::TexImage2D(
+            gl::TEXTURE_2D, 0, format.internal_format() as GLint,
+            width, height, 0,
+            pixel_format, gl::UNSIGNED_BYTE,
+            pixels
+        );
+        gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+        if format == TextureFormat::Greyscale {
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_SWIZZLE_G, gl::RED as GLint);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_SWIZZLE_B, gl::RED as GLint);
+        }
+        if sampler.mipmaps {
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+        }
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, sampler.wrap_s as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, sampler.wrap_t as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, sampler.mag_filter as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, sampler.min_filter as GLint);
+    }
+    assert!(tex > 0);
+
+    if sampler.anisotropic {
+        apply_anisotropy(context, gl::TEXTURE_2D);
+    }
+
+    Texture::new(TextureHandle::new(tex))
+}
+
+/// The OpenGL internal format of a block compressed texture.
+fn compressed_internal_format(format: BlockFormat, srgb: bool) -> GLenum {
+    match (format, srgb) {
+        (BlockFormat::Bc1, false) => glh::GL_COMPRESSED_RGBA_S3TC_DXT1_EXT,
+        (BlockFormat::Bc1, true)  => glh::GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT1_EXT,
+        (BlockFormat::Bc2, false) => glh::GL_COMPRESSED_RGBA_S3TC_DXT3_EXT,
+        (BlockFormat::Bc2, true)  => glh::GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT3_EXT,
+        (BlockFormat::Bc3, false) => glh::GL_COMPRESSED_RGBA_S3TC_DXT5_EXT,
+        (BlockFormat::Bc3, true)  => glh::GL_COMPRESSED_SRGB_ALPHA_S3TC_DXT5_EXT,
+        (BlockFormat::Bc4, _)     => gl::COMPRESSED_RED_RGTC1,
+        (BlockFormat::Bc5, _)     => gl::COMPRESSED_RG_RGTC2,
+        (BlockFormat::Bc6h, _)    => glh::GL_COMPRESSED_RGB_BPTC_UNSIGNED_FLOAT,
+        (BlockFormat::Bc7, false) => glh::GL_COMPRESSED_RGBA_BPTC_UNORM,
+        (BlockFormat::Bc7, true)  => glh::GL_COMPRESSED_SRGB_ALPHA_BPTC_UNORM,
+    }
+}
+
+/// Determine whether the driver can sample a block compressed format directly.
+/// RGTC is part of core OpenGL 3.0; S3TC is only ever an extension, and BPTC is core
+/// from OpenGL 4.2 onwards.
+fn is_compressed_format_supported(format: BlockFormat, srgb: bool) -> bool {
+    match format {
+        BlockFormat::Bc1 | BlockFormat::Bc2 | BlockFormat::Bc3 => {
+            let s3tc = glh::has_extension("GL_EXT_texture_compression_s3tc");
+            let s3tc_srgb = glh::has_extension("GL_EXT_texture_sRGB")
+                || glh::has_extension("GL_EXT_texture_compression_s3tc_srgb");
+
+            s3tc && (!srgb || s3tc_srgb)
+        }
+        BlockFormat::Bc4 | BlockFormat::Bc5 => true,
+        BlockFormat::Bc6h | BlockFormat::Bc7 => {
+            glh::has_version(4, 2) || glh::has_extension("GL_ARB_texture_compression_bptc")
+        }
+    }
+}
+
+/// Upload a block compressed texture and its mipmap chain into the GPU. When the
+/// driver cannot sample the format, every level is decoded in software and uploaded
+/// as RGBA8 instead.
+fn upload_compressed_texture(
+    context: &glh::GLState, name: &str,
+    image: &CompressedImage, sampler: &SamplerSettings) -> Result<Texture, TextureError> {
+
+    let supported = is_compressed_format_supported(image.format, image.srgb);
+    let decoded_levels = if supported {
+        None
+    } else {
+        warn!(
+            "The driver does not support {:?} textures. Decoding {} in software.",
+            image.format, name
+        );
+        let mut decoded_levels = vec![];
+        for level in image.levels.iter() {
+            match bcn::decode(image.format, level.width, level.height, &level.data) {
+                Some(pixels) => decoded_levels.push(pixels),
+                None => return Err(TextureError::UnsupportedFormat(String::from(name))),
+            }
+        }
+        Some(decoded_levels)
+    };
+
+    let mut tex = 0;
+    unsafe {
+        gl::GenTextures(1, &mut tex);
+        gl::ActiveTexture(gl::TEXTURE0);
+        gl::BindTexture(gl::TEXTURE_2D, tex);
+        for (i, level) in image.levels.iter().enumerate() {
+            match decoded_levels {
+                None => {
+                    gl::CompressedTexImage2D(
+                        gl::TEXTURE_2D, i as GLint, compressed_internal_format(image.format, image.srgb),
+                        level.width as i32, level.height as i32, 0,
+                        level.data.len() as i32, level.data.as_ptr() as *const GLvoid
+                    );
+                }
+                Some(ref decoded_levels) => {
+                    let internal_format = if image.srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 };
+                    gl::TexImage2D(
+                        gl::TEXTURE_2D, i as GLint, internal_format as GLint,
+                        level.width as i32, level.height as i32, 0,
+                        gl::RGBA, gl::UNSIGNED_BYTE,
+                        decoded_levels[i].as_ptr() as *const GLvoid
+                    );
+                }
+            }
+        }
+        // The container supplies the mipmap chain, so clamp sampling to the levels it
+        // actually has instead of generating the rest.
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_BASE_LEVEL, 0);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, (image.levels.len() - 1) as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, sampler.wrap_s as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, sampler.wrap_t as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, sampler.mag_filter as GLint);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, sampler.min_filter as GLint);
+    }
+    assert!(tex > 0);
+
+    if sampler.anisotropic {
+        apply_anisotropy(context, gl::TEXTURE_2D);
+    }
+
+    Ok(Texture::new(TextureHandle::new(tex)))
+}
+
+/// Where the image of a texture came from. An image file is known by its path, and
+/// an image in memory by a hash of its contents, so the cache never keeps a second
+/// copy of the image around.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+enum TextureSource {
+    File(PathBuf),
+    Memory(u64),
+}
+
+/// The cache key of a texture. Two textures with the same source, storage format and
+/// sampler settings are interchangeable, so they share one GL texture.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+struct TextureKey {
+    source: TextureSource,
+    // Compressed textures have no format of their own; their container decides it.
+    format: Option<TextureFormat>,
+    sampler: SamplerSettings,
+}
+
+impl TextureKey {
+    fn from_file(path: &Path, format: Option<TextureFormat>, sampler: SamplerSettings) -> TextureKey {
+        TextureKey {
+            source: TextureSource::File(path.to_path_buf()),
+            format: format,
+            sampler: sampler,
+        }
+    }
+
+    /// The key of an image in memory. Decoded texels carry their dimensions alongside;
+    /// encoded images carry them in their contents.
+    fn from_memory(
+        contents: &[u8], dimensions: Option<(usize, usize)>,
+        format: Option<TextureFormat>, sampler: SamplerSettings) -> TextureKey {
+
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        dimensions.hash(&mut hasher);
+
+        TextureKey {
+            source: TextureSource::Memory(hasher.finish()),
+            format: format,
+            sampler: sampler,
+        }
+    }
+}
+
+/// A cache that does not keep its values alive on its own. An entry lasts for as
+/// long as some user holds on to its value.
+struct TextureCache<T> {
+    entries: HashMap<TextureKey, Weak<T>>,
+}
+
+impl<T> TextureCache<T> {
+    fn new() -> TextureCache<T> {
+        TextureCache {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Look up a cached value that is still alive.
+    fn get(&self, key: &TextureKey) -> Option<Rc<T>> {
+        self.entries.get(key)?.upgrade()
+    }
+
+    fn insert(&mut self, key: TextureKey, value: T) -> Rc<T> {
+        // Forget the values that every user has dropped since the last insert.
+        self.entries.retain(|_, value| value.strong_count() > 0);
+        let value = Rc::new(value);
+        self.entries.insert(key, Rc::downgrade(&value));
+
+        value
+    }
+}
+
+/// Decode an encoded image and upload it into the GPU.
+fn load_encoded_texture(
+    context: &glh::GLState, name: &str,
+    buffer: &[u8], format: TextureFormat, sampler: &SamplerSettings) -> Result<Texture, TextureError> {
+
+    let result = match teximage2d::load_from_memory(buffer) {
+        Ok(val) => val,
+        Err(_) => return Err(TextureError::CouldNotDecodeTexture(String::from(name))),
+    };
+    let texture = upload_texture(context, &result.image, format, sampler);
+    info!("Loaded texture {} from {} as {:?}.", texture.handle().inner, name, format);
+
+    Ok(texture)
+}
+
+/// Parse a DDS or KTX2 container and upload its block compressed image into the GPU.
+fn load_compressed_texture(
+    context: &glh::GLState, name: &str,
+    buffer: &[u8], sampler: &SamplerSettings) -> Result<Texture, TextureError> {
+
+    let image = match texture_container::load_from_memory(buffer) {
+        Ok(val) => val,
+        Err(e) => {
+            warn!("{}: {}", name, e);
+            return Err(TextureError::CouldNotDecodeTexture(String::from(name)));
+        }
+    };
+    // Without a software decoder there is no fallback, so reject the texture before
+    // anything goes to the GPU when the driver cannot sample its format.
+    if !image.format.has_software_decoder() && !is_compressed_format_supported(image.format, image.srgb) {
+        warn!("{}: The driver does not support {:?} textures.", name, image.format);
+        return Err(TextureError::UnsupportedFormat(String::from(name)));
+    }
+    let texture = upload_compressed_texture(context, name, &image, sampler)?;
+    info!(
+        "Loaded texture {} from {} as {}x{} {:?} with {} mipmap levels.",
+        texture.handle().inner, name, image.width(), image.height(), image.format, image.levels.len()
+    );
+
+    Ok(texture)
+}
+
+/// The texture manager uploads textures into the GPU and caches them by the file or
+/// the contents of the image they were loaded from. Loading the same image twice
+/// returns the same GL texture. The manager does not keep textures alive on its own:
+/// a texture is deleted as soon as the last of its users drops it.
+pub struct TextureManager {
+    cache: TextureCache<Texture>,
+}
+
+impl TextureManager {
+    pub fn new() -> TextureManager {
+        TextureManager {
+            cache: TextureCache::new(),
+        }
+    }
+
+    /// Load a texture from an encoded image in memory. The name is used for
+    /// error reporting only.
+    pub fn load_from_memory(
+        &mut self, context: &glh::GLState, name: &str,
+        buffer: &[u8], format: TextureFormat, sampler: SamplerSettings) -> Result<Rc<Texture>, TextureError> {
+
+        let key = TextureKey::from_memory(buffer, None, Some(format), sampler);
+        if let Some(texture) = self.acquire(&key, name) {
+            return Ok(texture);
+        }
+        let texture = load_encoded_texture(context, name, buffer, format, &sampler)?;
+
+        Ok(self.cache.insert(key, texture))
+    }
+
+    /// Load a texture from an image that has already been decoded into tightly packed
+    /// texels with the channels of `format`. The dimensions are given as `(width, height)`.
+    /// The name is used for error reporting only.
+    pub fn load_from_pixels(
+        &mut self, context: &glh::GLState, name: &str,
+        (width, height): (usize, usize), pixels: &[u8],
+        format: TextureFormat, sampler: SamplerSettings) -> Result<Rc<Texture>, TextureError> {
+
+        if pixels.len() != format.channels() * width * height {
+            return Err(TextureError::CouldNotDecodeTexture(String::from(name)));
+        }
+        let key = TextureKey::from_memory(pixels, Some((width, height)), Some(format), sampler);
+        if let Some(texture) = self.acquire(&key, name) {
+            return Ok(texture);
+        }
+
+        let texture = upload_pixels(
+            context, width as i32, height as i32,
+            pixels.as_ptr() as *const GLvoid, format.pixel_format(), format, &sampler
+        );
+        info!("Loaded texture {} from {} as {:?}.", texture.handle().inner, name, format);
+
+        Ok(self.cache.insert(key, texture))
+    }
+
+    /// Load a texture from an image file. The file is only read when no texture
+    /// loaded from the same path is still alive.
+    pub fn load_file<P: AsRef<Path>>(
+        &mut self, context: &glh::GLState,
+        path: P, format: TextureFormat, sampler: SamplerSettings) -> Result<Rc<Texture>, TextureError> {
+
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let key = TextureKey::from_file(path, Some(format), sampler);
+        if let Some(texture) = self.acquire(&key, &name) {
+            return Ok(texture);
+        }
+        let buffer = match fs::read(path) {
+            Ok(val) => val,
+            Err(_) => return Err(TextureError::TextureNotFound(name)),
+        };
+        let texture = load_encoded_texture(context, &name, &buffer, format, &sampler)?;
+
+        Ok(self.cache.insert(key, texture))
+    }
+
+    /// Load a block compressed texture from a DDS or KTX2 file, along with its
+    /// precomputed mipmap chain. BC6H textures have no software fallback and only load
+    /// when the driver supports BPTC. The file is only read when no texture loaded from
+    /// the same path is still alive.
+    pub fn load_compressed_file<P: AsRef<Path>>(
+        &mut self, context: &glh::GLState,
+        path: P, sampler: SamplerSettings) -> Result<Rc<Texture>, TextureError> {
+
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let key = TextureKey::from_file(path, None, sampler);
+        if let Some(texture) = self.acquire(&key, &name) {
+            return Ok(texture);
+        }
+        let buffer = match fs::read(path) {
+            Ok(val) => val,
+            Err(_) => return Err(TextureError::TextureNotFound(name)),
+        };
+        let texture = load_compressed_texture(context, &name, &buffer, &sampler)?;
+
+        Ok(self.cache.insert(key, texture))
+    }
+
+    /// Look up a cached texture that is still alive.
+    fn acquire(&self, key: &TextureKey, name: &str) -> Option<Rc<Texture>> {
+        let texture = self.cache.get(key)?;
+        info!("Reusing texture {} for {}.", texture.handle().inner, name);
+
+        Some(texture)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sampler() -> SamplerSettings {
+        SamplerSettings::new(gl::REPEAT)
+    }
+
+    #[test]
+    fn test_same_image_gives_the_same_key() {
+        let first = TextureKey::from_memory(&[1, 2, 3, 4], None, Some(TextureFormat::Rgba), sampler());
+        let second = TextureKey::from_memory(&[1, 2, 3, 4], None, Some(TextureFormat::Rgba), sampler());
+
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_contents_format_and_sampler_all_tell_keys_apart() {
+        let key = TextureKey::from_memory(&[1, 2, 3, 4], None, Some(TextureFormat::Rgba), sampler());
+        let contents = TextureKey::from_memory(&[1, 2, 3, 5], None, Some(TextureFormat::Rgba), sampler());
+        let dimensions = TextureKey::from_memory(&[1, 2, 3, 4], Some((1, 1)), Some(TextureFormat::Rgba), sampler());
+        let format = TextureKey::from_memory(&[1, 2, 3, 4], None, Some(TextureFormat::SrgbAlpha), sampler());
+        let clamped = TextureKey::from_memory(
+            &[1, 2, 3, 4], None, Some(TextureFormat::Rgba), SamplerSettings::new(gl::CLAMP_TO_EDGE)
+        );
+
+        assert_ne!(key, contents);
+        assert_ne!(key, dimensions);
+        assert_ne!(key, format);
+        assert_ne!(key, clamped);
+    }
+
+    #[test]
+    fn test_files_are_keyed_by_path() {
+        let key = TextureKey::from_file(Path::new("ground.png"), Some(TextureFormat::Rgb), sampler());
+
+        assert_eq!(key, TextureKey::from_file(Path::new("ground.png"), Some(TextureFormat::Rgb), sampler()));
+        assert_ne!(key, TextureKey::from_file(Path::new("sky.png"), Some(TextureFormat::Rgb), sampler()));
+    }
+
+    #[test]
+    fn test_cache_misses_before_the_first_insert() {
+        let cache: TextureCache<u32> = TextureCache::new();
+        let key = TextureKey::from_memory(&[1, 2, 3, 4], None, None, sampler());
+
+        assert!(cache.get(&key).is_none());
+    }
+
+    #[test]
+    fn test_cache_hit_shares_the_value() {
+        let mut cache = TextureCache::new();
+        let key = TextureKey::from_memory(&[1, 2, 3, 4], None, None, sampler());
+        let value = cache.insert(key.clone(), 7);
+        let cached = cache.get(&key).unwrap();
+
+        assert!(Rc::ptr_eq(&value, &cached));
+    }
+
+    #[test]
+    fn test_cache_forgets_values_once_every_user_drops_them() {
+        let mut cache = TextureCache::new();
+        let first = TextureKey::from_memory(&[1, 2, 3, 4], None, None, sampler());
+        let second = TextureKey::from_memory(&[5, 6, 7, 8], None, None, sampler());
+        let value = cache.insert(first.clone(), 7);
+        drop(value);
+
+        assert!(cache.get(&first).is_none());
+        assert_eq!(cache.entries.len(), 1);
+
+        let _value = cache.insert(second.clone(), 8);
+
+        assert_eq!(cache.entries.len(), 1);
+        assert!(cache.get(&second).is_some());
+    }
+
+    #[test]
+    fn test_texels_carry_the_channels_of_their_format() {
+        assert_eq!(TextureFormat::Greyscale.channels(), 1);
+        assert_eq!(TextureFormat::Rgb.channels(), 3);
+        assert_eq!(TextureFormat::Srgb.channels(), 3);
+        assert_eq!(TextureFormat::Rgba.channels(), 4);
+        assert_eq!(TextureFormat::SrgbAlpha.channels(), 4);
+    }
+}
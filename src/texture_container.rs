@@ -0,0 +1,462 @@
+//! Parsers for the DDS and KTX2 texture containers. Both containers carry a
+//! precomputed mipmap chain of block compressed images, so the textures can go
+//! straight to the GPU without decoding or generating mipmaps at load time.
+use crate::bcn::BlockFormat;
+
+use std::fmt;
+
+
+/// One level of a mipmap chain.
+pub struct MipLevel {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<u8>,
+}
+
+/// A block compressed image with its mipmap chain. Level zero is the full size image.
+pub struct CompressedImage {
+    pub format: BlockFormat,
+    pub srgb: bool,
+    pub levels: Vec<MipLevel>,
+}
+
+impl CompressedImage {
+    #[inline]
+    pub fn width(&self) -> usize {
+        self.levels[0].width
+    }
+
+    #[inline]
+    pub fn height(&self) -> usize {
+        self.levels[0].height
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ContainerError {
+    UnknownContainer,
+    Truncated,
+    TooManyLevels(usize),
+    UnsupportedFormat(String),
+    UnsupportedLayout(String),
+}
+
+impl fmt::Display for ContainerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ContainerError::UnknownContainer => {
+                write!(f, "The data is neither a DDS nor a KTX2 texture container.")
+            }
+            ContainerError::Truncated => {
+                write!(f, "The texture container ends before all of its image data.")
+            }
+            ContainerError::TooManyLevels(level_count) => {
+                write!(f, "The texture container has more mipmap levels than its image: {}", level_count)
+            }
+            ContainerError::UnsupportedFormat(format) => {
+                write!(f, "The texture container uses an unsupported pixel format: {}", format)
+            }
+            ContainerError::UnsupportedLayout(layout) => {
+                write!(f, "The texture container holds an unsupported kind of texture: {}", layout)
+            }
+        }
+    }
+}
+
+const DDS_MAGIC: &[u8] = b"DDS ";
+const DDS_HEADER_SIZE: usize = 128;
+const DDS_DX10_HEADER_SIZE: usize = 20;
+const DDSD_MIPMAPCOUNT: u32 = 0x0002_0000;
+const DDSCAPS2_CUBEMAP: u32 = 0x0000_0200;
+const DDSCAPS2_VOLUME: u32 = 0x0020_0000;
+
+const KTX2_MAGIC: &[u8] = &[
+    0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A
+];
+const KTX2_HEADER_SIZE: usize = 80;
+const KTX2_LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+
+#[inline]
+fn read_u32(buffer: &[u8], offset: usize) -> Result<u32, ContainerError> {
+    if offset + 4 > buffer.len() {
+        return Err(ContainerError::Truncated);
+    }
+    let bytes = &buffer[offset..(offset + 4)];
+
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[inline]
+fn read_u64(buffer: &[u8], offset: usize) -> Result<u64, ContainerError> {
+    let low = read_u32(buffer, offset)? as u64;
+    let high = read_u32(buffer, offset + 4)? as u64;
+
+    Ok(low | (high << 32))
+}
+
+/// Check the dimensions and the number of mip levels of an image against each other.
+/// A full mipmap chain halves the larger dimension down to one texel, so an image
+/// has at most `floor(log2(max(width, height))) + 1` levels.
+fn check_level_count(width: usize, height: usize, level_count: usize) -> Result<(), ContainerError> {
+    if width == 0 || height == 0 {
+        return Err(ContainerError::UnsupportedLayout(String::from("image with no texels")));
+    }
+
+    let mut max_level_count = 0;
+    let mut size = usize::max(width, height);
+    while size > 0 {
+        max_level_count += 1;
+        size >>= 1;
+    }
+    if level_count > max_level_count {
+        return Err(ContainerError::TooManyLevels(level_count));
+    }
+
+    Ok(())
+}
+
+/// Slice the mip levels of an image out of the container one after another,
+/// starting at `offset`.
+fn read_sequential_levels(
+    buffer: &[u8], offset: usize, format: BlockFormat,
+    width: usize, height: usize, level_count: usize) -> Result<Vec<MipLevel>, ContainerError> {
+
+    check_level_count(width, height, level_count)?;
+    let mut levels = vec![];
+    let mut offset = offset;
+    for level in 0..level_count {
+        let level_width = usize::max(1, width >> level);
+        let level_height = usize::max(1, height >> level);
+        let size = format.image_size(level_width, level_height);
+        let end = match offset.checked_add(size) {
+            Some(end) if end <= buffer.len() => end,
+            _ => return Err(ContainerError::Truncated),
+        };
+        levels.push(MipLevel {
+            width: level_width,
+            height: level_height,
+            data: buffer[offset..end].to_vec(),
+        });
+        offset = end;
+    }
+
+    Ok(levels)
+}
+
+/// Map a DXGI format from the DX10 extension header onto a block format.
+fn dds_dxgi_format(dxgi_format: u32) -> Result<(BlockFormat, bool), ContainerError> {
+    match dxgi_format {
+        70 | 71 => Ok((BlockFormat::Bc1, false)),
+        72      => Ok((BlockFormat::Bc1, true)),
+        73 | 74 => Ok((BlockFormat::Bc2, false)),
+        75      => Ok((BlockFormat::Bc2, true)),
+        76 | 77 => Ok((BlockFormat::Bc3, false)),
+        78      => Ok((BlockFormat::Bc3, true)),
+        79 | 80 => Ok((BlockFormat::Bc4, false)),
+        82 | 83 => Ok((BlockFormat::Bc5, false)),
+        94 | 95 => Ok((BlockFormat::Bc6h, false)),
+        97 | 98 => Ok((BlockFormat::Bc7, false)),
+        99      => Ok((BlockFormat::Bc7, true)),
+        _ => Err(ContainerError::UnsupportedFormat(format!("DXGI format {}", dxgi_format))),
+    }
+}
+
+/// Map a legacy DDS four character code onto a block format.
+fn dds_four_cc_format(four_cc: &[u8]) -> Result<(BlockFormat, bool), ContainerError> {
+    match four_cc {
+        b"DXT1" => Ok((BlockFormat::Bc1, false)),
+        b"DXT2" | b"DXT3" => Ok((BlockFormat::Bc2, false)),
+        b"DXT4" | b"DXT5" => Ok((BlockFormat::Bc3, false)),
+        b"ATI1" | b"BC4U" => Ok((BlockFormat::Bc4, false)),
+        b"ATI2" | b"BC5U" => Ok((BlockFormat::Bc5, false)),
+        _ => Err(ContainerError::UnsupportedFormat(
+            format!("four character code {}", String::from_utf8_lossy(four_cc))
+        )),
+    }
+}
+
+/// Parse a DDS texture container holding a single 2D texture.
+pub fn load_dds(buffer: &[u8]) -> Result<CompressedImage, ContainerError> {
+    if buffer.len() < DDS_HEADER_SIZE || &buffer[0..4] != DDS_MAGIC {
+        return Err(ContainerError::UnknownContainer);
+    }
+
+    let flags = read_u32(buffer, 8)?;
+    let height = read_u32(buffer, 12)? as usize;
+    let width = read_u32(buffer, 16)? as usize;
+    let mip_map_count = read_u32(buffer, 28)? as usize;
+    let four_cc = &buffer[84..88];
+    let caps2 = read_u32(buffer, 112)?;
+    if caps2 & (DDSCAPS2_CUBEMAP | DDSCAPS2_VOLUME) != 0 {
+        return Err(ContainerError::UnsupportedLayout(String::from("cube map or volume texture")));
+    }
+
+    let (format, srgb, data_offset) = if four_cc == b"DX10" {
+        let dxgi_format = read_u32(buffer, DDS_HEADER_SIZE)?;
+        let array_size = read_u32(buffer, DDS_HEADER_SIZE + 12)?;
+        if array_size > 1 {
+            return Err(ContainerError::UnsupportedLayout(String::from("texture array")));
+        }
+        let (format, srgb) = dds_dxgi_format(dxgi_format)?;
+        (format, srgb, DDS_HEADER_SIZE + DDS_DX10_HEADER_SIZE)
+    } else {
+        let (format, srgb) = dds_four_cc_format(four_cc)?;
+        (format, srgb, DDS_HEADER_SIZE)
+    };
+
+    let level_count = if flags & DDSD_MIPMAPCOUNT != 0 && mip_map_count > 0 {
+        mip_map_count
+    } else {
+        1
+    };
+    let levels = read_sequential_levels(buffer, data_offset, format, width, height, level_count)?;
+
+    Ok(CompressedImage {
+        format: format,
+        srgb: srgb,
+        levels: levels,
+    })
+}
+
+/// Map a Vulkan format from a KTX2 header onto a block format.
+fn ktx2_vk_format(vk_format: u32) -> Result<(BlockFormat, bool), ContainerError> {
+    match vk_format {
+        131 | 133 => Ok((BlockFormat::Bc1, false)),
+        132 | 134 => Ok((BlockFormat::Bc1, true)),
+        135       => Ok((BlockFormat::Bc2, false)),
+        136       => Ok((BlockFormat::Bc2, true)),
+        137       => Ok((BlockFormat::Bc3, false)),
+        138       => Ok((BlockFormat::Bc3, true)),
+        139       => Ok((BlockFormat::Bc4, false)),
+        141       => Ok((BlockFormat::Bc5, false)),
+        143       => Ok((BlockFormat::Bc6h, false)),
+        145       => Ok((BlockFormat::Bc7, false)),
+        146       => Ok((BlockFormat::Bc7, true)),
+        _ => Err(ContainerError::UnsupportedFormat(format!("Vulkan format {}", vk_format))),
+    }
+}
+
+/// Parse a KTX2 texture container holding a single 2D texture without supercompression.
+pub fn load_ktx2(buffer: &[u8]) -> Result<CompressedImage, ContainerError> {
+    if buffer.len() < KTX2_HEADER_SIZE || &buffer[0..12] != KTX2_MAGIC {
+        return Err(ContainerError::UnknownContainer);
+    }
+
+    let vk_format = read_u32(buffer, 12)?;
+    let width = read_u32(buffer, 20)? as usize;
+    let height = read_u32(buffer, 24)? as usize;
+    let depth = read_u32(buffer, 28)?;
+    let layer_count = read_u32(buffer, 32)?;
+    let face_count = read_u32(buffer, 36)?;
+    let level_count = usize::max(1, read_u32(buffer, 40)? as usize);
+    let supercompression_scheme = read_u32(buffer, 44)?;
+    if depth > 0 || layer_count > 0 || face_count != 1 {
+        return Err(ContainerError::UnsupportedLayout(
+            String::from("cube map, array or volume texture")
+        ));
+    }
+    if supercompression_scheme != 0 {
+        return Err(ContainerError::UnsupportedLayout(
+            format!("supercompression scheme {}", supercompression_scheme)
+        ));
+    }
+    let (format, srgb) = ktx2_vk_format(vk_format)?;
+    check_level_count(width, height, level_count)?;
+
+    // Unlike DDS, the level index records where every level lives, and the levels
+    // are stored smallest first.
+    let mut levels = vec![];
+    for level in 0..level_count {
+        let entry = KTX2_HEADER_SIZE + level * KTX2_LEVEL_INDEX_ENTRY_SIZE;
+        let offset = read_u64(buffer, entry)? as usize;
+        let length = read_u64(buffer, entry + 8)? as usize;
+        let level_width = usize::max(1, width >> level);
+        let level_height = usize::max(1, height >> level);
+        let end = match offset.checked_add(length) {
+            Some(end) if end <= buffer.len() => end,
+            _ => return Err(ContainerError::Truncated),
+        };
+        if length < format.image_size(level_width, level_height) {
+            return Err(ContainerError::Truncated);
+        }
+        levels.push(MipLevel {
+            width: level_width,
+            height: level_height,
+            data: buffer[offset..end].to_vec(),
+        });
+    }
+
+    Ok(CompressedImage {
+        format: format,
+        srgb: srgb,
+        levels: levels,
+    })
+}
+
+/// Parse a DDS or KTX2 texture container, telling them apart by their magic numbers.
+pub fn load_from_memory(buffer: &[u8]) -> Result<CompressedImage, ContainerError> {
+    if buffer.starts_with(DDS_MAGIC) {
+        load_dds(buffer)
+    } else if buffer.starts_with(KTX2_MAGIC) {
+        load_ktx2(buffer)
+    } else {
+        Err(ContainerError::UnknownContainer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    fn write_u32(buffer: &mut [u8], offset: usize, value: u32) {
+        buffer[offset..(offset + 4)].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn write_u64(buffer: &mut [u8], offset: usize, value: u64) {
+        buffer[offset..(offset + 8)].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// A DDS container with the given four character code, followed by `data_size`
+    /// bytes of image data.
+    fn dds(four_cc: &[u8], width: u32, height: u32, mip_map_count: u32, data_size: usize) -> Vec<u8> {
+        let mut buffer = vec![0; DDS_HEADER_SIZE + data_size];
+        buffer[0..4].copy_from_slice(DDS_MAGIC);
+        write_u32(&mut buffer, 4, 124);
+        write_u32(&mut buffer, 8, if mip_map_count > 0 { DDSD_MIPMAPCOUNT } else { 0 });
+        write_u32(&mut buffer, 12, height);
+        write_u32(&mut buffer, 16, width);
+        write_u32(&mut buffer, 28, mip_map_count);
+        buffer[84..88].copy_from_slice(four_cc);
+
+        buffer
+    }
+
+    /// A KTX2 container whose level index points at the given offsets and lengths.
+    /// The image data follows the level index, smallest level first.
+    fn ktx2(vk_format: u32, width: u32, height: u32, levels: &[(u64, u64)], data_size: usize) -> Vec<u8> {
+        let index_end = KTX2_HEADER_SIZE + levels.len() * KTX2_LEVEL_INDEX_ENTRY_SIZE;
+        let mut buffer = vec![0; index_end + data_size];
+        buffer[0..12].copy_from_slice(KTX2_MAGIC);
+        write_u32(&mut buffer, 12, vk_format);
+        write_u32(&mut buffer, 20, width);
+        write_u32(&mut buffer, 24, height);
+        write_u32(&mut buffer, 36, 1);
+        write_u32(&mut buffer, 40, levels.len() as u32);
+        for (level, &(offset, length)) in levels.iter().enumerate() {
+            let entry = KTX2_HEADER_SIZE + level * KTX2_LEVEL_INDEX_ENTRY_SIZE;
+            write_u64(&mut buffer, entry, offset);
+            write_u64(&mut buffer, entry + 8, length);
+        }
+
+        buffer
+    }
+
+    #[test]
+    fn test_dds_with_a_full_mipmap_chain() {
+        let buffer = dds(b"DXT1", 8, 8, 4, 32 + 8 + 8 + 8);
+        let image = load_from_memory(&buffer).ok().unwrap();
+
+        assert_eq!(image.format, BlockFormat::Bc1);
+        assert!(!image.srgb);
+        assert_eq!((image.width(), image.height()), (8, 8));
+        let sizes: Vec<(usize, usize, usize)> = image.levels.iter()
+            .map(|level| (level.width, level.height, level.data.len()))
+            .collect();
+        assert_eq!(sizes, vec![(8, 8, 32), (4, 4, 8), (2, 2, 8), (1, 1, 8)]);
+    }
+
+    #[test]
+    fn test_dds_without_a_mipmap_count_has_one_level() {
+        let buffer = dds(b"DXT5", 4, 4, 0, 16);
+        let image = load_dds(&buffer).ok().unwrap();
+
+        assert_eq!(image.format, BlockFormat::Bc3);
+        assert_eq!(image.levels.len(), 1);
+    }
+
+    #[test]
+    fn test_dds_with_a_dx10_header() {
+        let mut buffer = dds(b"DX10", 4, 4, 1, DDS_DX10_HEADER_SIZE + 16);
+        write_u32(&mut buffer, DDS_HEADER_SIZE, 99);
+        write_u32(&mut buffer, DDS_HEADER_SIZE + 12, 1);
+        let image = load_dds(&buffer).ok().unwrap();
+
+        assert_eq!(image.format, BlockFormat::Bc7);
+        assert!(image.srgb);
+        assert_eq!(image.levels[0].data.len(), 16);
+    }
+
+    #[test]
+    fn test_truncated_dds() {
+        let buffer = dds(b"DXT1", 8, 8, 4, 32 + 8 + 8);
+
+        assert_eq!(load_dds(&buffer).err(), Some(ContainerError::Truncated));
+        assert_eq!(load_dds(&buffer[0..64]).err(), Some(ContainerError::UnknownContainer));
+    }
+
+    #[test]
+    fn test_dds_with_too_many_levels() {
+        let buffer = dds(b"DXT1", 4, 4, 64, 1024);
+
+        assert_eq!(load_dds(&buffer).err(), Some(ContainerError::TooManyLevels(64)));
+    }
+
+    #[test]
+    fn test_dds_with_an_unknown_format() {
+        let buffer = dds(b"RGBG", 4, 4, 1, 16);
+
+        assert!(matches!(load_dds(&buffer), Err(ContainerError::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn test_ktx2_with_a_full_mipmap_chain() {
+        // The smallest level comes first in the file.
+        let data_start = (KTX2_HEADER_SIZE + 3 * KTX2_LEVEL_INDEX_ENTRY_SIZE) as u64;
+        let levels = [(data_start + 32, 64), (data_start + 16, 16), (data_start, 16)];
+        let buffer = ktx2(145, 8, 8, &levels, 96);
+        let image = load_from_memory(&buffer).ok().unwrap();
+
+        assert_eq!(image.format, BlockFormat::Bc7);
+        let sizes: Vec<(usize, usize, usize)> = image.levels.iter()
+            .map(|level| (level.width, level.height, level.data.len()))
+            .collect();
+        assert_eq!(sizes, vec![(8, 8, 64), (4, 4, 16), (2, 2, 16)]);
+    }
+
+    #[test]
+    fn test_truncated_ktx2() {
+        let data_start = (KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_ENTRY_SIZE) as u64;
+        let buffer = ktx2(131, 4, 4, &[(data_start, 8)], 4);
+
+        assert_eq!(load_ktx2(&buffer).err(), Some(ContainerError::Truncated));
+    }
+
+    #[test]
+    fn test_ktx2_level_shorter_than_its_image() {
+        let data_start = (KTX2_HEADER_SIZE + KTX2_LEVEL_INDEX_ENTRY_SIZE) as u64;
+        let buffer = ktx2(131, 8, 8, &[(data_start, 8)], 32);
+
+        assert_eq!(load_ktx2(&buffer).err(), Some(ContainerError::Truncated));
+    }
+
+    #[test]
+    fn test_ktx2_with_an_overflowing_level_offset() {
+        let buffer = ktx2(131, 4, 4, &[(u64::MAX - 4, 8)], 8);
+
+        assert_eq!(load_ktx2(&buffer).err(), Some(ContainerError::Truncated));
+    }
+
+    #[test]
+    fn test_ktx2_with_too_many_levels() {
+        let levels = vec![(0, 0); 64];
+        let buffer = ktx2(131, 4, 4, &levels, 0);
+
+        assert_eq!(load_ktx2(&buffer).err(), Some(ContainerError::TooManyLevels(64)));
+    }
+
+    #[test]
+    fn test_unknown_container() {
+        assert_eq!(load_from_memory(b"not a texture").err(), Some(ContainerError::UnknownContainer));
+    }
+}
@@ -0,0 +1,402 @@
+//! Timekeeping for the simulation. The simulation advances in fixed steps no matter
+//! how long each frame takes to draw, so a slow frame runs more steps rather than
+//! one long step, and a run plays out the same way every time for the same inputs.
+//! Time is read through the `Clock` trait, so the tests can drive the simulation
+//! with a hand-operated clock instead of GLFW.
+
+
+/// A source of time, measured in seconds from an arbitrary starting point.
+pub trait Clock {
+    fn now(&self) -> f64;
+}
+
+impl Clock for glfw::Glfw {
+    #[inline]
+    fn now(&self) -> f64 {
+        self.get_time()
+    }
+}
+
+/// A fixed timestep accumulator. Each frame, the time since the previous frame goes
+/// into the accumulator, and the simulation runs one step for every whole timestep
+/// the accumulator holds. Whatever is left over is the fraction of a step the
+/// renderer should interpolate by.
+#[derive(Copy, Clone, Debug)]
+pub struct FixedTimestep {
+    timestep: f64,
+    max_frame_time: f64,
+    accumulator: f64,
+    previous_time: Option<f64>,
+}
+
+impl FixedTimestep {
+    /// Construct an accumulator that steps the simulation every `timestep` seconds.
+    /// A single frame counts for at most `max_frame_time` seconds, so a long stall
+    /// does not leave the simulation running hundreds of steps to catch up.
+    pub fn new(timestep: f64, max_frame_time: f64) -> FixedTimestep {
+        assert!(timestep > 0.0);
+        assert!(max_frame_time >= timestep);
+
+        FixedTimestep {
+            timestep: timestep,
+            max_frame_time: max_frame_time,
+            accumulator: 0.0,
+            previous_time: None,
+        }
+    }
+
+    /// The length of a simulation step in seconds.
+    #[inline]
+    pub fn timestep(&self) -> f64 {
+        self.timestep
+    }
+
+    /// Read the clock at the start of a frame, and return how many simulation steps
+    /// to run this frame. The first frame only starts the clock and runs no steps.
+    pub fn advance<C: Clock>(&mut self, clock: &C) -> u32 {
+        let now = clock.now();
+        let frame_time = match self.previous_time {
+            Some(previous_time) => now - previous_time,
+            None => 0.0,
+        };
+        self.previous_time = Some(now);
+        self.accumulator += f64::min(f64::max(frame_time, 0.0), self.max_frame_time);
+
+        let mut steps = 0;
+        while self.accumulator >= self.timestep {
+            self.accumulator -= self.timestep;
+            steps += 1;
+        }
+
+        steps
+    }
+
+    /// How far the current time sits between the last simulation step and the next
+    /// one, between zero and one.
+    #[inline]
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.timestep) as f32
+    }
+}
+
+/// The slowest the simulation can be made to run, as a multiple of real time.
+pub const MIN_TIME_SCALE: f64 = 0.1;
+
+/// The fastest the simulation can be made to run, as a multiple of real time.
+pub const MAX_TIME_SCALE: f64 = 4.0;
+
+/// Debugging controls over simulated time. Simulated time can be paused, sped up or
+/// slowed down relative to real time, and stepped forward one simulation step at a
+/// time while paused. The camera keeps running on real time, so the scene can still
+/// be looked around while it is frozen.
+#[derive(Copy, Clone, Debug)]
+pub struct TimeControl {
+    paused: bool,
+    time_scale: f64,
+    step_requested: bool,
+}
+
+impl TimeControl {
+    pub fn new() -> TimeControl {
+        TimeControl {
+            paused: false,
+            time_scale: 1.0,
+            step_requested: false,
+        }
+    }
+
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    #[inline]
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    #[inline]
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.step_requested = false;
+    }
+
+    /// Set how fast simulated time runs relative to real time, clamped between
+    /// `MIN_TIME_SCALE` and `MAX_TIME_SCALE`.
+    #[inline]
+    pub fn set_time_scale(&mut self, time_scale: f64) {
+        self.time_scale = time_scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+    }
+
+    /// Advance simulated time by a single step at the next opportunity. This only
+    /// does anything while paused.
+    #[inline]
+    pub fn request_step(&mut self) {
+        if self.paused {
+            self.step_requested = true;
+        }
+    }
+
+    /// Convert the length of a real time simulation step into the amount of
+    /// simulated time that passes during it.
+    pub fn simulated_seconds(&mut self, elapsed_seconds: f64) -> f64 {
+        if !self.paused {
+            elapsed_seconds * self.time_scale
+        } else if self.step_requested {
+            self.step_requested = false;
+            elapsed_seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The number of frames the frame statistics cover.
+const FRAME_HISTORY: usize = 120;
+
+/// Statistics over the lengths of the most recent frames.
+#[derive(Clone, Debug)]
+pub struct FrameTimer {
+    frame_times: Vec<f64>,
+    next: usize,
+    previous_time: Option<f64>,
+}
+
+impl FrameTimer {
+    pub fn new() -> FrameTimer {
+        FrameTimer {
+            frame_times: Vec::with_capacity(FRAME_HISTORY),
+            next: 0,
+            previous_time: None,
+        }
+    }
+
+    /// Read the clock at the start of a frame. The first reading only starts the
+    /// clock.
+    pub fn tick<C: Clock>(&mut self, clock: &C) {
+        let now = clock.now();
+        if let Some(previous_time) = self.previous_time {
+            let frame_time = f64::max(now - previous_time, 0.0);
+            if self.frame_times.len() < FRAME_HISTORY {
+                self.frame_times.push(frame_time);
+            } else {
+                self.frame_times[self.next] = frame_time;
+            }
+            self.next = (self.next + 1) % FRAME_HISTORY;
+        }
+        self.previous_time = Some(now);
+    }
+
+    /// The average length of the recent frames in seconds.
+    pub fn average_frame_time(&self) -> f64 {
+        if self.frame_times.is_empty() {
+            return 0.0;
+        }
+
+        self.frame_times.iter().sum::<f64>() / self.frame_times.len() as f64
+    }
+
+    /// The length of the longest recent frame in seconds.
+    pub fn max_frame_time(&self) -> f64 {
+        self.frame_times.iter().copied().fold(0.0, f64::max)
+    }
+
+    /// The average number of frames per second over the recent frames.
+    pub fn frames_per_second(&self) -> f64 {
+        let average = self.average_frame_time();
+        if average > 0.0 { 1.0 / average } else { 0.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    /// A clock that only moves when it is told to.
+    #[derive(Copy, Clone, Debug)]
+    struct ManualClock {
+        time: f64,
+    }
+
+    impl ManualClock {
+        fn new() -> ManualClock {
+            ManualClock { time: 0.0 }
+        }
+
+        fn advance(&mut self, seconds: f64) {
+            self.time += seconds;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> f64 {
+            self.time
+        }
+    }
+
+    fn approx_eq(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn test_first_frame_runs_no_steps() {
+        let mut clock = ManualClock::new();
+        clock.advance(5.0);
+        let mut timestep = FixedTimestep::new(0.25, 1.0);
+
+        assert_eq!(timestep.advance(&clock), 0);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn test_one_step_per_whole_timestep() {
+        let mut clock = ManualClock::new();
+        let mut timestep = FixedTimestep::new(0.25, 1.0);
+        timestep.advance(&clock);
+        clock.advance(0.6);
+
+        assert_eq!(timestep.advance(&clock), 2);
+        assert!(approx_eq(timestep.alpha(), 0.4));
+    }
+
+    #[test]
+    fn test_leftover_time_carries_over_to_the_next_frame() {
+        let mut clock = ManualClock::new();
+        let mut timestep = FixedTimestep::new(0.25, 1.0);
+        timestep.advance(&clock);
+        clock.advance(0.125);
+
+        assert_eq!(timestep.advance(&clock), 0);
+        assert!(approx_eq(timestep.alpha(), 0.5));
+
+        clock.advance(0.1875);
+
+        assert_eq!(timestep.advance(&clock), 1);
+        assert!(approx_eq(timestep.alpha(), 0.25));
+    }
+
+    #[test]
+    fn test_long_frames_are_clamped() {
+        let mut clock = ManualClock::new();
+        let mut timestep = FixedTimestep::new(0.25, 1.0);
+        timestep.advance(&clock);
+        clock.advance(10.0);
+
+        assert_eq!(timestep.advance(&clock), 4);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn test_clock_going_backwards_runs_no_steps() {
+        let mut clock = ManualClock::new();
+        clock.advance(1.0);
+        let mut timestep = FixedTimestep::new(0.25, 1.0);
+        timestep.advance(&clock);
+        clock.advance(-0.5);
+
+        assert_eq!(timestep.advance(&clock), 0);
+        assert_eq!(timestep.alpha(), 0.0);
+    }
+
+    #[test]
+    fn test_running_time_follows_the_time_scale() {
+        let mut time_control = TimeControl::new();
+        time_control.set_time_scale(2.0);
+
+        assert_eq!(time_control.simulated_seconds(0.25), 0.5);
+    }
+
+    #[test]
+    fn test_no_time_passes_while_paused() {
+        let mut time_control = TimeControl::new();
+        time_control.toggle_pause();
+
+        assert!(time_control.is_paused());
+        assert_eq!(time_control.simulated_seconds(0.25), 0.0);
+        assert_eq!(time_control.simulated_seconds(0.25), 0.0);
+    }
+
+    #[test]
+    fn test_a_step_is_consumed_exactly_once() {
+        let mut time_control = TimeControl::new();
+        time_control.set_time_scale(2.0);
+        time_control.toggle_pause();
+        time_control.request_step();
+
+        assert_eq!(time_control.simulated_seconds(0.25), 0.25);
+        assert_eq!(time_control.simulated_seconds(0.25), 0.0);
+    }
+
+    #[test]
+    fn test_step_requests_are_ignored_while_running() {
+        let mut time_control = TimeControl::new();
+        time_control.request_step();
+        time_control.toggle_pause();
+
+        assert_eq!(time_control.simulated_seconds(0.25), 0.0);
+    }
+
+    #[test]
+    fn test_time_scale_is_clamped() {
+        let mut time_control = TimeControl::new();
+        time_control.set_time_scale(100.0);
+
+        assert_eq!(time_control.time_scale(), MAX_TIME_SCALE);
+
+        time_control.set_time_scale(0.0);
+
+        assert_eq!(time_control.time_scale(), MIN_TIME_SCALE);
+    }
+
+    #[test]
+    fn test_frame_timer_starts_empty() {
+        let clock = ManualClock::new();
+        let mut timer = FrameTimer::new();
+        timer.tick(&clock);
+
+        assert_eq!(timer.average_frame_time(), 0.0);
+        assert_eq!(timer.max_frame_time(), 0.0);
+        assert_eq!(timer.frames_per_second(), 0.0);
+    }
+
+    #[test]
+    fn test_frame_timer_statistics() {
+        let mut clock = ManualClock::new();
+        let mut timer = FrameTimer::new();
+        timer.tick(&clock);
+        for frame_time in [0.25, 0.5, 0.25, 0.0].iter() {
+            clock.advance(*frame_time);
+            timer.tick(&clock);
+        }
+
+        assert_eq!(timer.average_frame_time(), 0.25);
+        assert_eq!(timer.max_frame_time(), 0.5);
+        assert_eq!(timer.frames_per_second(), 4.0);
+    }
+
+    #[test]
+    fn test_frame_timer_forgets_the_oldest_frames() {
+        let mut clock = ManualClock::new();
+        let mut timer = FrameTimer::new();
+        timer.tick(&clock);
+        clock.advance(1.0);
+        timer.tick(&clock);
+        for _ in 0..(FRAME_HISTORY - 1) {
+            clock.advance(0.25);
+            timer.tick(&clock);
+        }
+
+        assert_eq!(timer.frame_times.len(), FRAME_HISTORY);
+        assert_eq!(timer.max_frame_time(), 1.0);
+
+        // The next frame takes the place of the long one.
+        clock.advance(0.25);
+        timer.tick(&clock);
+
+        assert_eq!(timer.frame_times.len(), FRAME_HISTORY);
+        assert_eq!(timer.max_frame_time(), 0.25);
+        assert_eq!(timer.average_frame_time(), 0.25);
+        assert_eq!(timer.frames_per_second(), 4.0);
+    }
+}
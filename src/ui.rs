@@ -0,0 +1,602 @@
+//! A small immediate-mode user interface for the debug overlay. The interface is
+//! rebuilt from scratch every frame: each call to a widget both draws the widget and
+//! reports what the mouse did to it, so the state the widgets show lives in the game
+//! itself rather than in the interface. Widgets stack top to bottom inside panels.
+//! Everything the interface draws ends up in a single list of textured triangles
+//! sampling the font atlas, in window coordinates with the origin at the top left.
+use crate::text::FontAtlas;
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{
+    Hash,
+    Hasher,
+};
+
+
+/// The space in pixels between the edge of a panel and its widgets.
+const PADDING: f32 = 6.0;
+
+/// The space in pixels between rows of widgets.
+const ROW_SPACING: f32 = 3.0;
+
+/// The fraction of a row taken up by the label of a widget that also shows a value.
+const LABEL_FRACTION: f32 = 0.4;
+
+/// The gap in pixels between the boxes of a widget that edits several values.
+const BOX_SPACING: f32 = 3.0;
+
+const PANEL_COLOR: [f32; 4] = [0.08, 0.08, 0.1, 0.85];
+const TITLE_COLOR: [f32; 4] = [0.2, 0.3, 0.5, 0.95];
+const SECTION_COLOR: [f32; 4] = [0.18, 0.18, 0.22, 0.95];
+const TEXT_COLOR: [f32; 4] = [0.95, 0.95, 0.95, 1.0];
+const WIDGET_COLOR: [f32; 4] = [0.25, 0.25, 0.3, 1.0];
+const WIDGET_HOT_COLOR: [f32; 4] = [0.35, 0.35, 0.42, 1.0];
+const WIDGET_ACTIVE_COLOR: [f32; 4] = [0.45, 0.45, 0.55, 1.0];
+const FILL_COLOR: [f32; 4] = [0.3, 0.5, 0.8, 1.0];
+
+/// A vertex of the interface, laid out the way the overlay shader reads it.
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct UiVertex {
+    pub pos: [f32; 2],
+    pub tex: [f32; 2],
+    pub color: [f32; 4],
+}
+
+/// An axis-aligned rectangle in window coordinates.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Rect {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+impl Rect {
+    #[inline]
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Rect {
+        Rect {
+            min: [x, y],
+            max: [x + width, y + height],
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> f32 {
+        self.max[0] - self.min[0]
+    }
+
+    #[inline]
+    pub fn height(&self) -> f32 {
+        self.max[1] - self.min[1]
+    }
+
+    #[inline]
+    pub fn center(&self) -> [f32; 2] {
+        [(self.min[0] + self.max[0]) / 2.0, (self.min[1] + self.max[1]) / 2.0]
+    }
+
+    #[inline]
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        point[0] >= self.min[0] && point[0] < self.max[0] &&
+            point[1] >= self.min[1] && point[1] < self.max[1]
+    }
+}
+
+/// The state of the mouse for one frame of the interface.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct UiInput {
+    /// The cursor position in window coordinates.
+    pub cursor: [f32; 2],
+    /// Whether the left mouse button is down.
+    pub mouse_down: bool,
+}
+
+impl UiInput {
+    #[inline]
+    pub fn new(cursor: [f32; 2], mouse_down: bool) -> UiInput {
+        UiInput {
+            cursor: cursor,
+            mouse_down: mouse_down,
+        }
+    }
+
+    /// No mouse at all, for when the mouse belongs to something else.
+    #[inline]
+    pub fn none() -> UiInput {
+        UiInput::new([f32::NEG_INFINITY, f32::NEG_INFINITY], false)
+    }
+}
+
+/// A widget is known by a hash of its panel and label, so the same widget gets the
+/// same id every frame.
+type WidgetId = u64;
+
+/// The panel widgets are being added to.
+struct Panel {
+    id: WidgetId,
+    x: f32,
+    y: f32,
+    width: f32,
+    cursor_y: f32,
+    /// Where the background of the panel goes in the vertex list. The background is
+    /// drawn under the widgets, but its height is only known once they are all in.
+    background_index: usize,
+}
+
+/// An immediate-mode user interface.
+pub struct Ui {
+    font: FontAtlas,
+    input: UiInput,
+    previous_input: UiInput,
+    /// The widget the mouse is dragging or holding down, if any.
+    active: Option<WidgetId>,
+    collapsed_sections: HashMap<WidgetId, bool>,
+    panel: Option<Panel>,
+    panel_rects: Vec<Rect>,
+    last_rect: Rect,
+    vertices: Vec<UiVertex>,
+}
+
+impl Ui {
+    pub fn new(font: FontAtlas) -> Ui {
+        Ui {
+            font: font,
+            input: UiInput::none(),
+            previous_input: UiInput::none(),
+            active: None,
+            collapsed_sections: HashMap::new(),
+            panel: None,
+            panel_rects: vec![],
+            last_rect: Rect::new(0.0, 0.0, 0.0, 0.0),
+            vertices: vec![],
+        }
+    }
+
+    #[inline]
+    pub fn font(&self) -> &FontAtlas {
+        &self.font
+    }
+
+    /// Start building a frame of the interface.
+    pub fn begin_frame(&mut self, input: UiInput) {
+        self.previous_input = self.input;
+        self.input = input;
+        self.vertices.clear();
+        self.panel_rects.clear();
+    }
+
+    /// Finish building a frame of the interface.
+    pub fn end_frame(&mut self) {
+        assert!(self.panel.is_none(), "A panel was left open");
+        if !self.input.mouse_down {
+            self.active = None;
+        }
+    }
+
+    /// The triangles of the last frame built.
+    #[inline]
+    pub fn vertices(&self) -> &[UiVertex] {
+        &self.vertices
+    }
+
+    /// Determine whether the mouse is busy with the interface, so that clicks should
+    /// not reach the scene behind it.
+    pub fn wants_mouse(&self, cursor: [f32; 2]) -> bool {
+        self.active.is_some() || self.panel_rects.iter().any(|rect| rect.contains(cursor))
+    }
+
+    /// Start a panel with its top left corner at `(x, y)`. The panel grows downwards
+    /// to fit the widgets added before `end_panel` is called.
+    pub fn begin_panel(&mut self, title: &str, x: f32, y: f32, width: f32) {
+        assert!(self.panel.is_none(), "Panels cannot be nested");
+        let background_index = self.vertices.len();
+        let title_rect = Rect::new(x, y, width, self.font.line_height() + 2.0 * ROW_SPACING);
+        self.draw_rect(title_rect, TITLE_COLOR);
+        self.draw_text(title, x + PADDING, y + ROW_SPACING, TEXT_COLOR);
+        self.panel = Some(Panel {
+            id: hash_id(0, title),
+            x: x,
+            y: y,
+            width: width,
+            cursor_y: title_rect.max[1] + PADDING,
+            background_index: background_index,
+        });
+    }
+
+    /// Finish the current panel.
+    pub fn end_panel(&mut self) {
+        let panel = self.panel.take().expect("No panel to end");
+        let rect = Rect::new(panel.x, panel.y, panel.width, panel.cursor_y - panel.y + PADDING);
+        let background = rect_vertices(rect, self.font.solid_tex_coords(), PANEL_COLOR);
+        self.vertices.splice(panel.background_index..panel.background_index, background.iter().copied());
+        self.panel_rects.push(rect);
+    }
+
+    /// A line of text.
+    pub fn label(&mut self, text: &str) {
+        let rect = self.row();
+        self.draw_text(text, rect.min[0], rect.min[1], TEXT_COLOR);
+    }
+
+    /// A label with a value next to it that cannot be edited.
+    pub fn value(&mut self, label: &str, value: &str) {
+        let rect = self.row();
+        let value_x = rect.min[0] + LABEL_FRACTION * rect.width();
+        self.draw_text(label, rect.min[0], rect.min[1], TEXT_COLOR);
+        self.draw_text(value, value_x, rect.min[1], TEXT_COLOR);
+    }
+
+    /// A header that folds away the widgets under it when clicked. Returns whether
+    /// the section is open, in which case the caller adds the widgets of the section.
+    pub fn section(&mut self, title: &str) -> bool {
+        let id = self.widget_id(title);
+        let rect = self.row();
+        let (hot, _) = self.interact(id, rect);
+        if hot && self.pressed() {
+            let collapsed = self.collapsed_sections.entry(id).or_insert(false);
+            *collapsed = !*collapsed;
+        }
+        let open = !self.collapsed_sections.get(&id).copied().unwrap_or(false);
+        let color = if hot { WIDGET_HOT_COLOR } else { SECTION_COLOR };
+        self.draw_rect(rect, color);
+        let marker = if open { "-" } else { "+" };
+        self.draw_text(&format!("{} {}", marker, title), rect.min[0] + 2.0, rect.min[1], TEXT_COLOR);
+
+        open
+    }
+
+    /// A button. Returns true when the button is clicked, which is when the mouse
+    /// button is let go over it after being pressed over it.
+    pub fn button(&mut self, text: &str) -> bool {
+        let id = self.widget_id(text);
+        let rect = self.row();
+        let (hot, active) = self.interact(id, rect);
+        let clicked = hot && active && self.released();
+        self.draw_rect(rect, widget_color(hot, active));
+        let text_x = rect.center()[0] - self.font.text_width(text) / 2.0;
+        self.draw_text(text, text_x, rect.min[1], TEXT_COLOR);
+
+        clicked
+    }
+
+    /// A check box that flips `value` when clicked. Returns true if `value` changed.
+    pub fn checkbox(&mut self, label: &str, value: &mut bool) -> bool {
+        let id = self.widget_id(label);
+        let rect = self.row();
+        let (hot, active) = self.interact(id, rect);
+        let changed = hot && self.pressed();
+        if changed {
+            *value = !*value;
+        }
+        let size = rect.height();
+        let box_rect = Rect::new(rect.min[0], rect.min[1], size, size);
+        self.draw_rect(box_rect, widget_color(hot, active));
+        if *value {
+            let inset = size / 4.0;
+            self.draw_rect(Rect::new(rect.min[0] + inset, rect.min[1] + inset, size / 2.0, size / 2.0), FILL_COLOR);
+        }
+        self.draw_text(label, rect.min[0] + size + PADDING, rect.min[1], TEXT_COLOR);
+
+        changed
+    }
+
+    /// A slider that sets `value` between `min` and `max` from where the mouse
+    /// holds it. Returns true if `value` changed.
+    pub fn slider(&mut self, label: &str, value: &mut f32, min: f32, max: f32) -> bool {
+        let id = self.widget_id(label);
+        let rect = self.row();
+        let track = Rect::new(
+            rect.min[0] + LABEL_FRACTION * rect.width(), rect.min[1],
+            (1.0 - LABEL_FRACTION) * rect.width(), rect.height()
+        );
+        let (hot, active) = self.interact(id, track);
+        let mut changed = false;
+        if active && self.input.mouse_down {
+            let amount = ((self.input.cursor[0] - track.min[0]) / track.width()).clamp(0.0, 1.0);
+            let new_value = min + amount * (max - min);
+            changed = new_value != *value;
+            *value = new_value;
+        }
+        let amount = ((*value - min) / (max - min)).clamp(0.0, 1.0);
+        self.draw_text(label, rect.min[0], rect.min[1], TEXT_COLOR);
+        self.draw_rect(track, widget_color(hot, active));
+        self.draw_rect(Rect::new(track.min[0], track.min[1], amount * track.width(), track.height()), FILL_COLOR);
+        let text = format_value(*value);
+        let text_x = track.center()[0] - self.font.text_width(&text) / 2.0;
+        self.draw_text(&text, text_x, track.min[1], TEXT_COLOR);
+
+        changed
+    }
+
+    /// A box that changes `value` by `speed` for every pixel the mouse drags it
+    /// sideways, with no limits. Returns true if `value` changed.
+    pub fn drag(&mut self, label: &str, value: &mut f32, speed: f32) -> bool {
+        let mut values = [*value];
+        let changed = self.drag_values(label, &mut values, speed);
+        *value = values[0];
+
+        changed
+    }
+
+    /// Three drag boxes side by side, for editing a vector. Returns true if any of
+    /// the values changed.
+    pub fn drag3(&mut self, label: &str, values: &mut [f32; 3], speed: f32) -> bool {
+        self.drag_values(label, values, speed)
+    }
+
+    fn drag_values(&mut self, label: &str, values: &mut [f32], speed: f32) -> bool {
+        let rect = self.row();
+        self.draw_text(label, rect.min[0], rect.min[1], TEXT_COLOR);
+        let boxes_x = rect.min[0] + LABEL_FRACTION * rect.width();
+        let count = values.len() as f32;
+        let box_width = ((1.0 - LABEL_FRACTION) * rect.width() - (count - 1.0) * BOX_SPACING) / count;
+        let delta = self.input.cursor[0] - self.previous_input.cursor[0];
+        let mut changed = false;
+        for (i, value) in values.iter_mut().enumerate() {
+            let id = self.widget_id(&format!("{}#{}", label, i));
+            let box_rect = Rect::new(
+                boxes_x + i as f32 * (box_width + BOX_SPACING), rect.min[1], box_width, rect.height()
+            );
+            let (hot, active) = self.interact(id, box_rect);
+            // The press that grabs the box does not move it.
+            if active && self.input.mouse_down && !self.pressed() && delta != 0.0 {
+                *value += delta * speed;
+                changed = true;
+            }
+            self.draw_rect(box_rect, widget_color(hot, active));
+            let text = format_value(*value);
+            let text_x = box_rect.center()[0] - self.font.text_width(&text) / 2.0;
+            self.draw_text(&text, text_x, box_rect.min[1], TEXT_COLOR);
+        }
+
+        changed
+    }
+
+    /// Claim the next row of the current panel.
+    fn row(&mut self) -> Rect {
+        let line_height = self.font.line_height();
+        let panel = self.panel.as_mut().expect("Widgets have to go inside a panel");
+        let rect = Rect::new(panel.x + PADDING, panel.cursor_y, panel.width - 2.0 * PADDING, line_height);
+        panel.cursor_y += line_height + ROW_SPACING;
+        self.last_rect = rect;
+
+        rect
+    }
+
+    fn widget_id(&self, label: &str) -> WidgetId {
+        let panel_id = self.panel.as_ref().map_or(0, |panel| panel.id);
+
+        hash_id(panel_id, label)
+    }
+
+    #[inline]
+    fn pressed(&self) -> bool {
+        self.input.mouse_down && !self.previous_input.mouse_down
+    }
+
+    #[inline]
+    fn released(&self) -> bool {
+        !self.input.mouse_down && self.previous_input.mouse_down
+    }
+
+    /// Work out whether the mouse is over a widget, and whether it holds the widget.
+    /// Pressing the mouse button over a widget makes it the active widget until the
+    /// button is let go. While another widget is active, no other widget responds
+    /// to the mouse.
+    fn interact(&mut self, id: WidgetId, rect: Rect) -> (bool, bool) {
+        let free = self.active.is_none() || self.active == Some(id);
+        let hot = free && rect.contains(self.input.cursor);
+        if hot && self.pressed() {
+            self.active = Some(id);
+        }
+
+        (hot, self.active == Some(id))
+    }
+
+    /// Fill a rectangle with a solid color. This draws straight into the frame, so
+    /// it also works outside of panels, and the mouse passes through it.
+    pub fn draw_rect(&mut self, rect: Rect, color: [f32; 4]) {
+        let vertices = rect_vertices(rect, self.font.solid_tex_coords(), color);
+        self.vertices.extend_from_slice(&vertices);
+    }
+
+    /// Draw a string with the top left corner of its first line at `(x, y)`. Like
+    /// `draw_rect`, this works outside of panels.
+    pub fn draw_text(&mut self, text: &str, x: f32, y: f32, color: [f32; 4]) {
+        for quad in self.font.layout(text, x, y).iter() {
+            let corners = [
+                ([quad.min[0], quad.min[1]], [quad.tex_min[0], quad.tex_min[1]]),
+                ([quad.max[0], quad.min[1]], [quad.tex_max[0], quad.tex_min[1]]),
+                ([quad.max[0], quad.max[1]], [quad.tex_max[0], quad.tex_max[1]]),
+                ([quad.min[0], quad.max[1]], [quad.tex_min[0], quad.tex_max[1]]),
+            ];
+            for &i in [0, 1, 2, 0, 2, 3].iter() {
+                let (pos, tex) = corners[i];
+                self.vertices.push(UiVertex { pos: pos, tex: tex, color: color });
+            }
+        }
+    }
+}
+
+fn hash_id(parent: WidgetId, label: &str) -> WidgetId {
+    let mut hasher = DefaultHasher::new();
+    parent.hash(&mut hasher);
+    label.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+fn widget_color(hot: bool, active: bool) -> [f32; 4] {
+    if active {
+        WIDGET_ACTIVE_COLOR
+    } else if hot {
+        WIDGET_HOT_COLOR
+    } else {
+        WIDGET_COLOR
+    }
+}
+
+fn format_value(value: f32) -> String {
+    format!("{:.2}", value)
+}
+
+/// The two triangles filling a rectangle with a solid color.
+fn rect_vertices(rect: Rect, tex: [f32; 2], color: [f32; 4]) -> [UiVertex; 6] {
+    let vertex = |x, y| UiVertex { pos: [x, y], tex: tex, color: color };
+
+    [
+        vertex(rect.min[0], rect.min[1]), vertex(rect.max[0], rect.min[1]), vertex(rect.max[0], rect.max[1]),
+        vertex(rect.min[0], rect.min[1]), vertex(rect.max[0], rect.max[1]), vertex(rect.min[0], rect.max[1]),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    const OUTSIDE: [f32; 2] = [1000.0, 1000.0];
+
+    fn ui() -> Ui {
+        Ui::new(FontAtlas::from_ttf(include_asset!("DejaVuSansMono.ttf"), 16.0).unwrap())
+    }
+
+    /// Build a frame with a single panel holding the widgets `build` adds, and
+    /// return the rectangle of the last widget along with what `build` returned.
+    fn frame<T, F: FnOnce(&mut Ui) -> T>(ui: &mut Ui, input: UiInput, build: F) -> (Rect, T) {
+        ui.begin_frame(input);
+        ui.begin_panel("Test", 0.0, 0.0, 300.0);
+        let result = build(ui);
+        let rect = ui.last_rect;
+        ui.end_panel();
+        ui.end_frame();
+
+        (rect, result)
+    }
+
+    #[test]
+    fn test_button_clicks_when_released_over_it() {
+        let mut ui = ui();
+        let (rect, _) = frame(&mut ui, UiInput::new(OUTSIDE, false), |ui| ui.button("Go"));
+        let center = rect.center();
+        let (_, pressed) = frame(&mut ui, UiInput::new(center, true), |ui| ui.button("Go"));
+        let (_, released) = frame(&mut ui, UiInput::new(center, false), |ui| ui.button("Go"));
+
+        assert!(!pressed);
+        assert!(released);
+    }
+
+    #[test]
+    fn test_button_does_not_click_when_released_elsewhere() {
+        let mut ui = ui();
+        let (rect, _) = frame(&mut ui, UiInput::new(OUTSIDE, false), |ui| ui.button("Go"));
+        frame(&mut ui, UiInput::new(rect.center(), true), |ui| ui.button("Go"));
+        let (_, released) = frame(&mut ui, UiInput::new(OUTSIDE, false), |ui| ui.button("Go"));
+
+        assert!(!released);
+    }
+
+    #[test]
+    fn test_checkbox_flips_on_click() {
+        let mut ui = ui();
+        let mut value = false;
+        let (rect, _) = frame(&mut ui, UiInput::new(OUTSIDE, false), |ui| ui.checkbox("Pause", &mut value));
+        let (_, changed) = frame(&mut ui, UiInput::new(rect.center(), true), |ui| ui.checkbox("Pause", &mut value));
+
+        assert!(changed);
+        assert!(value);
+
+        // Holding the button down does not flip it again.
+        let (_, changed) = frame(&mut ui, UiInput::new(rect.center(), true), |ui| ui.checkbox("Pause", &mut value));
+
+        assert!(!changed);
+        assert!(value);
+    }
+
+    #[test]
+    fn test_slider_follows_the_mouse_within_its_range() {
+        let mut ui = ui();
+        let mut value = 0.0;
+        let (rect, _) = frame(&mut ui, UiInput::new(OUTSIDE, false), |ui| ui.slider("Fov", &mut value, 10.0, 20.0));
+        let track_x = rect.min[0] + LABEL_FRACTION * rect.width();
+        let track_width = (1.0 - LABEL_FRACTION) * rect.width();
+        let y = rect.center()[1];
+        frame(&mut ui, UiInput::new([track_x + track_width / 4.0, y], true), |ui| ui.slider("Fov", &mut value, 10.0, 20.0));
+
+        assert!((value - 12.5).abs() < 1e-3);
+
+        // The slider keeps following the mouse past its end, up to its limit.
+        frame(&mut ui, UiInput::new([track_x + 2.0 * track_width, y], true), |ui| ui.slider("Fov", &mut value, 10.0, 20.0));
+
+        assert!((value - 20.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_drag_moves_the_value_with_the_mouse() {
+        let mut ui = ui();
+        let mut values = [1.0, 2.0, 3.0];
+        let (rect, _) = frame(&mut ui, UiInput::new(OUTSIDE, false), |ui| ui.drag3("Position", &mut values, 0.5));
+        // Grab the middle box, then drag it ten pixels to the right.
+        let middle = [rect.min[0] + LABEL_FRACTION * rect.width() + (1.0 - LABEL_FRACTION) * rect.width() / 2.0, rect.center()[1]];
+        frame(&mut ui, UiInput::new(middle, true), |ui| ui.drag3("Position", &mut values, 0.5));
+        let (_, changed) = frame(&mut ui, UiInput::new([middle[0] + 10.0, middle[1]], true), |ui| {
+            ui.drag3("Position", &mut values, 0.5)
+        });
+
+        assert!(changed);
+        assert_eq!(values, [1.0, 7.0, 3.0]);
+    }
+
+    #[test]
+    fn test_section_folds_away_when_clicked() {
+        let mut ui = ui();
+        let (rect, open) = frame(&mut ui, UiInput::new(OUTSIDE, false), |ui| ui.section("Camera"));
+
+        assert!(open);
+
+        let (_, open) = frame(&mut ui, UiInput::new(rect.center(), true), |ui| ui.section("Camera"));
+
+        assert!(!open);
+    }
+
+    #[test]
+    fn test_only_the_active_widget_responds_while_dragging() {
+        let mut ui = ui();
+        let mut first = 0.0;
+        let mut second = 0.0;
+        let build = |ui: &mut Ui, first: &mut f32, second: &mut f32| {
+            ui.slider("First", first, 0.0, 1.0);
+            let rect = ui.last_rect;
+            ui.slider("Second", second, 0.0, 1.0);
+            rect
+        };
+        let (_, first_rect) = frame(&mut ui, UiInput::new(OUTSIDE, false), |ui| build(ui, &mut first, &mut second));
+        let second_rect = ui.last_rect;
+        let grab = [first_rect.max[0] - 1.0, first_rect.center()[1]];
+        frame(&mut ui, UiInput::new(grab, true), |ui| build(ui, &mut first, &mut second));
+        // Dragging down over the second slider keeps moving the first one.
+        let over_second = [first_rect.min[0] + LABEL_FRACTION * first_rect.width(), second_rect.center()[1]];
+        frame(&mut ui, UiInput::new(over_second, true), |ui| build(ui, &mut first, &mut second));
+
+        assert!(first.abs() < 1e-3);
+        assert_eq!(second, 0.0);
+    }
+
+    #[test]
+    fn test_panel_captures_the_mouse() {
+        let mut ui = ui();
+        let (rect, _) = frame(&mut ui, UiInput::new(OUTSIDE, false), |ui| ui.label("Hello"));
+
+        assert!(ui.wants_mouse(rect.center()));
+        assert!(!ui.wants_mouse(OUTSIDE));
+    }
+
+    #[test]
+    fn test_panel_background_is_drawn_first() {
+        let mut ui = ui();
+        frame(&mut ui, UiInput::new(OUTSIDE, false), |ui| ui.label("Hello"));
+
+        assert_eq!(ui.vertices().len() % 3, 0);
+        assert_eq!(ui.vertices()[0].color, PANEL_COLOR);
+    }
+}